@@ -1,15 +1,32 @@
 pub mod args;
 
-use crate::core::{resolver, search, types::FileLocation};
-use crate::error::Result;
+use crate::core::cache::DetectorCache;
+use crate::core::desktop::DesktopEntry;
+use crate::core::events::EventLog;
+use crate::core::inventory::Resolution;
+use crate::core::nix_db::NixDatabase;
+use crate::core::process::ProcessInfo;
+use crate::core::resolver::Resolver;
+use crate::core::types::{FileLocation, SymlinkChain};
+use crate::core::unit::{UnitFile, UnitScope};
+use crate::core::{diff, inventory, profile, search, shadow, watch};
+use crate::error::{Result, SymseekError};
 use crate::output::{OutputFormat, formatter, json};
 use log::debug;
+use rayon::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 /// Main CLI application struct.
 ///
 /// Handles parsing command-line arguments and running the symlink resolution logic.
 pub struct Cli {
     args: args::Args,
+    resolver: Resolver,
+    /// Events of the current resolution, recorded with `--explain`
+    explain_log: Option<Arc<EventLog>>,
+    /// Persistent detector cache, opened with `--cache`
+    cache: Option<Arc<DetectorCache>>,
 }
 
 impl Default for Cli {
@@ -22,15 +39,27 @@ impl Cli {
     /// Create a new CLI instance with parsed command-line arguments.
     #[must_use]
     pub fn new() -> Self {
-        Self {
-            args: args::Args::parse(),
-        }
+        Self::with_args(args::Args::parse())
     }
 
     /// Create a new CLI instance with provided arguments.
     #[must_use]
-    pub const fn with_args(args: args::Args) -> Self {
-        Self { args }
+    pub fn with_args(args: args::Args) -> Self {
+        let mut options = args.resolver_options();
+        let explain_log = args.explain.then(|| Arc::new(EventLog::new()));
+        if let Some(log) = &explain_log {
+            options = options.with_observer(log.clone());
+        }
+        let cache = args.cache.then(|| Arc::new(DetectorCache::open_default()));
+        if let Some(cache) = &cache {
+            options = options.with_cache(cache.clone());
+        }
+        Self {
+            args,
+            resolver: options.build(),
+            explain_log,
+            cache,
+        }
     }
 
     /// Run the CLI application.
@@ -40,49 +69,368 @@ impl Cli {
     ///
     /// # Errors
     ///
-    /// Returns an error if file lookup or symlink resolution fails.
+    /// Returns an error if file lookup or symlink resolution fails, or if
+    /// the detector cache cannot be saved.
     pub fn run(&self) -> Result<()> {
-        debug!("Searching for target: {}", &self.args.target);
-        let location = search::find_file(&self.args.target)?;
+        let result = match self.args.jobs {
+            Some(jobs) => rayon::ThreadPoolBuilder::new()
+                .num_threads(jobs.get())
+                .build()
+                .map_err(|e| SymseekError::InvalidInput {
+                    message: format!("Cannot start {jobs} worker threads: {e}"),
+                })?
+                .install(|| self.run_command()),
+            None => self.run_command(),
+        };
+        result.and(self.save_cache())
+    }
+
+    fn save_cache(&self) -> Result<()> {
+        self.cache.as_ref().map_or(Ok(()), |cache| cache.save())
+    }
+
+    fn run_command(&self) -> Result<()> {
+        if self.args.json_schema {
+            return json::print_json_schema();
+        }
+
+        if self.args.all {
+            return self.run_inventory();
+        }
+
+        if let Some(files) = &self.args.diff {
+            return self.run_diff(&files[0], &files[1]);
+        }
+
+        if let Some(pid) = self.args.pid {
+            return self.run_process(pid);
+        }
+
+        if let Some(unit) = &self.args.unit {
+            return self.run_unit(unit);
+        }
+
+        if let Some(desktop) = &self.args.desktop {
+            return self.run_desktop(desktop);
+        }
+
+        let target = self.target()?;
+        if self.args.watch {
+            return self.run_watch(target);
+        }
+
+        self.run_target(target)?;
+        Ok(())
+    }
+
+    /// Resolve and print a single target, returning the resolved chains.
+    fn run_target(&self, target: &str) -> Result<Vec<SymlinkChain>> {
+        debug!("Searching for target: {target}");
+        let location = search::find_file(target)?;
         let format = self.args.output_format();
+        let chains = self.resolve_location(&location)?;
 
         match location {
-            FileLocation::CurrentDirectory(path) => {
-                debug!("Found in current directory: {}", path.display());
-                let chain = resolver::resolve(&path)?;
-
+            FileLocation::CurrentDirectory(_) => {
                 match format {
-                    OutputFormat::Json => json::print_json_single(&chain)?,
-                    OutputFormat::Tree => formatter::print_tree(&chain),
+                    OutputFormat::Json => json::print_json_single(&chains[0])?,
+                    OutputFormat::Tree => formatter::print_tree(&chains[0]),
                 }
+                Ok(chains)
             }
             FileLocation::PathEnvironment(paths) => {
-                debug!("Found {} matches in PATH", paths.len());
+                let statuses = shadow::match_statuses(&chains);
+                let same_files = shadow::same_file_matches(&chains);
 
                 match format {
                     OutputFormat::Json => {
-                        let chains: Result<Vec<_>> =
-                            paths.iter().map(|p| resolver::resolve(p)).collect();
-                        json::print_json_multiple(&chains?)?;
+                        json::print_json_multiple(&chains, &statuses, &same_files)?;
                     }
                     OutputFormat::Tree => {
                         formatter::print_header(paths.len());
-                        for (idx, path) in paths.iter().enumerate() {
-                            debug!(
-                                "Resolving PATH match {}/{}: {}",
-                                idx + 1,
-                                paths.len(),
-                                path.display()
-                            );
-                            let chain = resolver::resolve(path)?;
-                            formatter::print_tree(&chain);
+                        for ((chain, status), same_file) in
+                            chains.iter().zip(&statuses).zip(&same_files)
+                        {
+                            formatter::print_match_status(*status, *same_file);
+                            formatter::print_tree(chain);
                             formatter::print_separator();
                         }
                     }
                 }
+                Ok(chains)
+            }
+        }
+    }
+
+    /// Resolve every path a target was found at.
+    fn resolve_location(&self, location: &FileLocation) -> Result<Vec<SymlinkChain>> {
+        let nix_db = self.open_nix_db()?;
+
+        match location {
+            FileLocation::CurrentDirectory(path) => {
+                debug!("Found in current directory: {}", path.display());
+                Ok(vec![self.resolve(path, nix_db.as_ref())?])
+            }
+            FileLocation::PathEnvironment(paths) => {
+                debug!("Found {} matches in PATH", paths.len());
+                let resolve = |(idx, path): (usize, &PathBuf)| {
+                    debug!(
+                        "Resolving PATH match {}/{}: {}",
+                        idx + 1,
+                        paths.len(),
+                        path.display()
+                    );
+                    self.resolve_chain(path)
+                };
+                // Explanations are printed as each resolution ends, so they
+                // must not run concurrently
+                let results: Vec<_> = if self.explain_log.is_some() {
+                    paths.iter().enumerate().map(resolve).collect()
+                } else {
+                    paths.par_iter().enumerate().map(resolve).collect()
+                };
+                results
+                    .into_iter()
+                    .map(|result| self.annotate(result?, nix_db.as_ref()))
+                    .collect()
+            }
+        }
+    }
+
+    /// Resolve a path and apply the requested annotations.
+    fn resolve(&self, path: &Path, nix_db: Option<&NixDatabase>) -> Result<SymlinkChain> {
+        self.annotate(self.resolve_chain(path)?, nix_db)
+    }
+
+    /// Resolve a path, with the annotations that are safe to add from
+    /// several threads at once.
+    fn resolve_chain(&self, path: &Path) -> Result<SymlinkChain> {
+        let result = self.resolver.resolve(path);
+        if let Some(log) = &self.explain_log {
+            formatter::print_explanation(&log.take());
+        }
+        let mut chain = result?;
+        if self.args.generations {
+            profile::annotate_siblings(&mut chain, &self.resolver);
+            // Resolutions in other generations are not part of the target's
+            // explanation
+            if let Some(log) = &self.explain_log {
+                log.clear();
+            }
+        }
+        Ok(chain)
+    }
+
+    /// Add information from the Nix database, whose connection cannot be
+    /// shared between threads.
+    fn annotate(
+        &self,
+        mut chain: SymlinkChain,
+        nix_db: Option<&NixDatabase>,
+    ) -> Result<SymlinkChain> {
+        if let Some(nix_db) = nix_db {
+            nix_db.annotate(&mut chain)?;
+        }
+        Ok(chain)
+    }
+
+    fn open_nix_db(&self) -> Result<Option<NixDatabase>> {
+        if !self.args.nix_info {
+            return Ok(None);
+        }
+        if self.args.nix_db_immutable {
+            eprintln!(
+                "warning: opening {} as immutable; results may be inconsistent while Nix writes to it",
+                self.args.nix_db.display()
+            );
+            return NixDatabase::open_immutable(&self.args.nix_db, self.resolver.stores())
+                .map(Some);
+        }
+        NixDatabase::open(&self.args.nix_db, self.resolver.stores()).map(Some)
+    }
+
+    fn run_watch(&self, target: &str) -> Result<()> {
+        let mut watcher = watch::Watcher::new()?;
+        let mut chain_paths = Vec::new();
+        loop {
+            // Keep watching the previous chain while resolving, so changes
+            // made meanwhile are queued instead of lost
+            let mut watched = search::candidate_paths(target)?;
+            watched.extend(chain_paths.iter().cloned());
+            watcher.watch(&watched)?;
+
+            chain_paths = match self.run_target(target) {
+                Ok(chains) => chains.iter().flat_map(watch::chain_paths).collect(),
+                Err(e) => {
+                    eprintln!("Error: {e}");
+                    Vec::new()
+                }
+            };
+            let unwatched = chain_paths.iter().any(|path| !watched.contains(path));
+            watched.extend(chain_paths.iter().cloned());
+            debug!("Watching {} paths for changes", watched.len());
+            watcher.watch(&watched)?;
+            if let Err(e) = self.save_cache() {
+                eprintln!("Error: {e}");
+            }
+
+            // Paths new to this chain were not watched while it was
+            // resolved, so check that they did not change before the wait
+            if !unwatched || self.chain_paths(target) == chain_paths {
+                watcher.wait()?;
+            }
+            self.resolver.refresh();
+            match self.args.output_format() {
+                OutputFormat::Json => formatter::eprint_change_marker(),
+                OutputFormat::Tree => formatter::print_change_marker(),
+            }
+        }
+    }
+
+    /// Resolve a target again without printing anything, to compare it with
+    /// the chain last printed.
+    fn chain_paths(&self, target: &str) -> Vec<PathBuf> {
+        let paths = match search::find_file(target) {
+            Ok(FileLocation::CurrentDirectory(path)) => vec![path],
+            Ok(FileLocation::PathEnvironment(paths)) => paths,
+            Err(_) => Vec::new(),
+        };
+        let chain_paths = paths
+            .iter()
+            .filter_map(|path| self.resolver.resolve(path).ok())
+            .flat_map(|chain| watch::chain_paths(&chain))
+            .collect();
+        if let Some(log) = &self.explain_log {
+            let _ = log.take();
+        }
+        chain_paths
+    }
+
+    fn run_process(&self, pid: u32) -> Result<()> {
+        let process = ProcessInfo::read(pid)?;
+        let entry_point = process.entry_point();
+        debug!(
+            "Resolving process {pid} from {}, loaded {}",
+            entry_point.display(),
+            process.exe.display()
+        );
+
+        // A deleted executable with no other way in has nothing to follow
+        let chain = if entry_point.symlink_metadata().is_ok() {
+            self.resolve(&entry_point, self.open_nix_db()?.as_ref())?
+        } else {
+            SymlinkChain::new(entry_point)
+        };
+        let loads = process.loads(&chain);
+
+        match self.args.output_format() {
+            OutputFormat::Json => json::print_json_process(&process, &chain, loads)?,
+            OutputFormat::Tree => {
+                formatter::print_process(&process, loads);
+                formatter::print_tree(&chain);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_unit(&self, name: &str) -> Result<()> {
+        let scope = if self.args.user {
+            UnitScope::User
+        } else {
+            UnitScope::System
+        };
+        let unit = UnitFile::load(name, scope)?;
+        debug!(
+            "Resolving {} commands of {}",
+            unit.commands.len(),
+            unit.path.display()
+        );
+        let nix_db = self.open_nix_db()?;
+
+        let resolutions: Vec<Resolution> = unit
+            .commands
+            .iter()
+            .map(|command| match &command.path {
+                Some(path) => match self.resolve(path, nix_db.as_ref()) {
+                    Ok(chain) => Resolution::Resolved(chain),
+                    Err(e) => {
+                        debug!("Failed to resolve {}: {e}", path.display());
+                        Resolution::Broken(e.to_string())
+                    }
+                },
+                None => Resolution::Broken(format!("`{}` not found in PATH", command.executable)),
+            })
+            .collect();
+
+        match self.args.output_format() {
+            OutputFormat::Json => json::print_json_unit(&unit, &resolutions)?,
+            OutputFormat::Tree => formatter::print_unit(&unit, &resolutions),
+        }
+
+        Ok(())
+    }
+
+    fn run_desktop(&self, file_or_id: &str) -> Result<()> {
+        let entry = DesktopEntry::load(file_or_id)?;
+        debug!(
+            "Resolving {} from desktop entry {}",
+            entry.executable(),
+            entry.path.display()
+        );
+
+        match self.args.output_format() {
+            OutputFormat::Json => {
+                let location = search::find_file(entry.executable())?;
+                let chains = self.resolve_location(&location)?;
+                let statuses = shadow::match_statuses(&chains);
+                let same_files = shadow::same_file_matches(&chains);
+                json::print_json_desktop(&entry, &chains, &statuses, &same_files)?;
+            }
+            OutputFormat::Tree => {
+                formatter::print_desktop(&entry);
+                self.run_target(entry.executable())?;
             }
         }
 
         Ok(())
     }
+
+    fn run_inventory(&self) -> Result<()> {
+        let commands = search::list_path_executables()?;
+        debug!("Resolving {} commands from PATH", commands.len());
+        let inventory = inventory::build_inventory(commands, &self.resolver);
+
+        match self.args.output_format() {
+            OutputFormat::Json => json::print_json_inventory(&inventory)?,
+            OutputFormat::Tree => formatter::print_inventory(&inventory),
+        }
+
+        Ok(())
+    }
+
+    fn run_diff(&self, before: &Path, after: &Path) -> Result<()> {
+        debug!("Comparing {} with {}", before.display(), after.display());
+        let report = diff::diff_chains(
+            &json::load_chains(before)?,
+            &json::load_chains(after)?,
+            self.resolver.stores(),
+        );
+
+        match self.args.output_format() {
+            OutputFormat::Json => json::print_json_diff(&report)?,
+            OutputFormat::Tree => formatter::print_diff(&report),
+        }
+
+        Ok(())
+    }
+
+    fn target(&self) -> Result<&str> {
+        self.args
+            .target
+            .as_deref()
+            .ok_or_else(|| SymseekError::InvalidInput {
+                message: "No target specified".to_string(),
+            })
+    }
 }
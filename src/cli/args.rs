@@ -1,12 +1,18 @@
+use crate::core::nix_db::DEFAULT_NIX_DB_PATH;
+use crate::core::resolver::ResolverOptions;
+use crate::core::store::Stores;
 use crate::output::OutputFormat;
 use clap::Parser;
+use std::num::NonZeroUsize;
+use std::path::{self, PathBuf};
 
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Default)]
 #[command(name = "symseek")]
 #[command(version, about, long_about = None)]
 pub struct Args {
     /// Target file or binary name to trace
-    pub target: String,
+    #[arg(required_unless_present_any = ["json_schema", "diff", "all", "pid", "unit", "desktop"])]
+    pub target: Option<String>,
 
     /// Enable verbose logging
     #[arg(short, long)]
@@ -15,6 +21,97 @@ pub struct Args {
     /// Output in JSON format
     #[arg(long)]
     pub json: bool,
+
+    /// Annotate Nix store paths with deriver, size, references and GC root
+    /// status from the local Nix database
+    #[arg(long)]
+    pub nix_info: bool,
+
+    /// Nix database to read with `--nix-info`
+    #[arg(long, value_name = "PATH", default_value = DEFAULT_NIX_DB_PATH, requires = "nix_info")]
+    pub nix_db: PathBuf,
+
+    /// Open the Nix database as immutable, for users who cannot open it
+    /// read-only. Reads may be inconsistent while Nix writes to it
+    #[arg(long, requires = "nix_info")]
+    pub nix_db_immutable: bool,
+
+    /// Nix store directory, for stores relocated with e.g. nix-user-chroot
+    /// [default: $NIX_STORE_DIR or /nix/store]
+    #[arg(long, value_name = "DIR")]
+    pub nix_store_dir: Option<PathBuf>,
+
+    /// List other generations of traversed Nix profiles in which the target
+    /// resolves differently
+    #[arg(long)]
+    pub generations: bool,
+
+    /// Fail instead of following a chain past this many hops
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Stop after following this many symlinks and mark the chain as
+    /// truncated; wrappers are still detected and do not count
+    #[arg(long, value_name = "N")]
+    pub max_symlinks: Option<usize>,
+
+    /// Number of threads resolving PATH matches and inventory entries;
+    /// defaults to the number of CPUs
+    #[arg(short, long, value_name = "N")]
+    pub jobs: Option<NonZeroUsize>,
+
+    /// Remember wrapper detection results for store files between runs, in
+    /// `$XDG_CACHE_HOME/symseek`
+    #[arg(long)]
+    pub cache: bool,
+
+    /// Explain step by step how each path was resolved, on stderr
+    #[arg(long, conflicts_with_all = ["all", "diff", "json_schema"])]
+    pub explain: bool,
+
+    /// Keep running and re-resolve whenever a path in the chain changes
+    #[arg(long, conflicts_with_all = ["all", "diff", "json_schema"])]
+    pub watch: bool,
+
+    /// Resolve every executable in PATH and print an inventory
+    #[arg(long, conflicts_with = "target")]
+    pub all: bool,
+
+    /// Resolve the executable of a running process, starting from the name
+    /// it was invoked as
+    #[arg(long, value_name = "PID", conflicts_with_all = ["target", "all", "diff", "watch"])]
+    pub pid: Option<u32>,
+
+    /// Resolve the commands a systemd unit runs, read from the local unit
+    /// file and its drop-ins
+    #[arg(long, value_name = "UNIT", conflicts_with_all = ["target", "all", "diff", "pid", "watch"])]
+    pub unit: Option<String>,
+
+    /// Look the unit up in the user unit directories instead of the system
+    /// ones, like `systemctl --user`
+    #[arg(
+        long,
+        requires = "unit",
+        conflicts_with_all = ["target", "all", "diff", "pid", "desktop", "watch"]
+    )]
+    pub user: bool,
+
+    /// Resolve the command a desktop entry launches, given as a `.desktop`
+    /// file or a desktop file ID looked up in `XDG_DATA_DIRS`
+    #[arg(
+        long,
+        value_name = "FILE_OR_ID",
+        conflicts_with_all = ["target", "all", "diff", "pid", "unit", "watch"]
+    )]
+    pub desktop: Option<String>,
+
+    /// Compare two results saved with `--json` and report what changed
+    #[arg(long, num_args = 2, value_names = ["BEFORE", "AFTER"], conflicts_with = "target")]
+    pub diff: Option<Vec<PathBuf>>,
+
+    /// Print the JSON Schema of the `--json` output and exit
+    #[arg(long)]
+    pub json_schema: bool,
 }
 
 impl Args {
@@ -33,6 +130,30 @@ impl Args {
             OutputFormat::Tree
         }
     }
+
+    /// Get the resolver options selected by the flags.
+    #[must_use]
+    pub fn resolver_options(&self) -> ResolverOptions {
+        let mut options = ResolverOptions::new().with_stores(self.stores());
+        if let Some(max_depth) = self.max_depth {
+            options = options.with_max_depth(max_depth);
+        }
+        if let Some(max_symlinks) = self.max_symlinks {
+            options = options.with_max_symlinks(max_symlinks);
+        }
+        options
+    }
+
+    /// Get the stores to recognize, with `--nix-store-dir` taking precedence
+    /// over `NIX_STORE_DIR`.
+    #[must_use]
+    pub fn stores(&self) -> Stores {
+        match &self.nix_store_dir {
+            Some(dir) => Stores::from_env()
+                .with_nix_store_dir(path::absolute(dir).unwrap_or_else(|_| dir.clone())),
+            None => Stores::from_env(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -42,9 +163,10 @@ mod tests {
     #[test]
     fn test_output_format_default() {
         let args = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: false,
             json: false,
+            ..Default::default()
         };
         assert_eq!(args.output_format(), OutputFormat::Tree);
     }
@@ -52,9 +174,10 @@ mod tests {
     #[test]
     fn test_output_format_json() {
         let args = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: false,
             json: true,
+            ..Default::default()
         };
         assert_eq!(args.output_format(), OutputFormat::Json);
     }
@@ -62,16 +185,18 @@ mod tests {
     #[test]
     fn test_output_format_with_verbose() {
         let args = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: true,
             json: false,
+            ..Default::default()
         };
         assert_eq!(args.output_format(), OutputFormat::Tree);
 
         let args_json = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: true,
             json: true,
+            ..Default::default()
         };
         assert_eq!(args_json.output_format(), OutputFormat::Json);
     }
@@ -80,31 +205,52 @@ mod tests {
     fn test_output_format_both_flags() {
         // Test combinations of verbose and json flags
         let args_tree_quiet = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: false,
             json: false,
+            ..Default::default()
         };
         assert_eq!(args_tree_quiet.output_format(), OutputFormat::Tree);
 
         let args_tree_verbose = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: true,
             json: false,
+            ..Default::default()
         };
         assert_eq!(args_tree_verbose.output_format(), OutputFormat::Tree);
 
         let args_json_quiet = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: false,
             json: true,
+            ..Default::default()
         };
         assert_eq!(args_json_quiet.output_format(), OutputFormat::Json);
 
         let args_json_verbose = Args {
-            target: "test".to_string(),
+            target: Some("test".to_string()),
             verbose: true,
             json: true,
+            ..Default::default()
         };
         assert_eq!(args_json_verbose.output_format(), OutputFormat::Json);
     }
+
+    #[test]
+    fn test_stores_with_nix_store_dir() {
+        let args = Args {
+            target: Some("test".to_string()),
+            nix_store_dir: Some(PathBuf::from("/home/u/.nix/store")),
+            ..Default::default()
+        };
+        let stores = args.stores();
+
+        assert!(stores.contains(std::path::Path::new(
+            "/home/u/.nix/store/abc123-hello-2.12/bin/hello"
+        )));
+        assert!(!stores.contains(std::path::Path::new(
+            "/nix/store/abc123-hello-2.12/bin/hello"
+        )));
+    }
 }
@@ -1,4 +1,18 @@
+pub mod cache;
+pub mod desktop;
 pub mod detector;
+pub mod diff;
+pub mod events;
+pub mod homebrew;
+pub mod inventory;
+pub mod mount;
+pub mod nix_db;
+pub mod process;
+pub mod profile;
 pub mod resolver;
 pub mod search;
+pub mod shadow;
+pub mod store;
 pub mod types;
+pub mod unit;
+pub mod watch;
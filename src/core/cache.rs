@@ -0,0 +1,336 @@
+use crate::core::resolver::Detector;
+use crate::error::{Result, SymseekError};
+use log::debug;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Default from the XDG Base Directory Specification.
+const DEFAULT_CACHE_HOME: &str = ".cache";
+const CACHE_FILE: &str = "symseek/detectors.json";
+/// Bumped whenever detectors change what they report, which invalidates
+/// every persisted result.
+const CACHE_VERSION: u32 = 2;
+
+/// Identity of a file's content: the same inode with the same modification
+/// time and size is assumed not to have changed.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct FileKey {
+    pub dev: u64,
+    pub ino: u64,
+    pub mtime: i64,
+    pub mtime_nsec: i64,
+    pub size: u64,
+}
+
+impl FileKey {
+    /// Read the key of a file, following symlinks.
+    #[must_use]
+    pub fn of(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        Some(Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            mtime: metadata.mtime(),
+            mtime_nsec: metadata.mtime_nsec(),
+            size: metadata.size(),
+        })
+    }
+}
+
+/// What a detector was asked about. The file name and root are part of the
+/// key because hard-linked store files share an inode, and detectors match
+/// on the name the file was reached by. The store directories are part of it
+/// because they decide which references detectors recognize.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CacheKey {
+    #[serde(flatten)]
+    pub file: FileKey,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<PathBuf>,
+    pub stores: Vec<PathBuf>,
+    pub detector: Detector,
+}
+
+#[derive(Debug, Clone)]
+struct CacheEntry {
+    /// File the result is about, checked against the key before saving
+    path: PathBuf,
+    target: Option<String>,
+    /// Only results for immutable store files are written to disk
+    persist: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    entries: Vec<PersistedEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedEntry {
+    #[serde(flatten)]
+    key: CacheKey,
+    path: PathBuf,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+}
+
+/// Results of the wrapper detectors, shared by every resolution of a run
+/// and optionally kept on disk between runs.
+#[derive(Debug, Default)]
+pub struct DetectorCache {
+    entries: Mutex<HashMap<CacheKey, CacheEntry>>,
+    /// File the cache was loaded from and is saved to
+    path: Option<PathBuf>,
+}
+
+impl DetectorCache {
+    /// An empty cache that only lives as long as the process.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a persistent cache from a file. A missing, unreadable or
+    /// outdated file gives an empty cache.
+    #[must_use]
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(
+                |content| match serde_json::from_str::<CacheFile>(&content) {
+                    Ok(file) if file.version == CACHE_VERSION => Some(file.entries),
+                    Ok(file) => {
+                        debug!("Ignoring detector cache version {}", file.version);
+                        None
+                    }
+                    Err(e) => {
+                        debug!("Ignoring invalid detector cache {}: {e}", path.display());
+                        None
+                    }
+                },
+            )
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| {
+                let value = CacheEntry {
+                    path: entry.path,
+                    target: entry.target,
+                    persist: true,
+                };
+                (entry.key, value)
+            })
+            .collect();
+
+        Self {
+            entries: Mutex::new(entries),
+            path: Some(path),
+        }
+    }
+
+    /// Load the persistent cache in `$XDG_CACHE_HOME/symseek`, or an
+    /// in-memory one when no cache directory is known.
+    #[must_use]
+    pub fn open_default() -> Self {
+        env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| {
+                env::var_os("HOME").map(|home| PathBuf::from(home).join(DEFAULT_CACHE_HOME))
+            })
+            .map_or_else(Self::new, |dir| Self::open(dir.join(CACHE_FILE)))
+    }
+
+    /// Look up an earlier result. The outer `None` means the detector has
+    /// not seen the file yet.
+    #[must_use]
+    pub fn get(&self, key: &CacheKey) -> Option<Option<String>> {
+        self.entries
+            .lock()
+            .ok()?
+            .get(key)
+            .map(|entry| entry.target.clone())
+    }
+
+    /// Record a result about the file at `path`. Results with `persist` set
+    /// are written by [`save`](Self::save).
+    pub fn insert(&self, key: CacheKey, path: &Path, target: Option<String>, persist: bool) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.insert(
+                key,
+                CacheEntry {
+                    path: path.to_path_buf(),
+                    target,
+                    persist,
+                },
+            );
+        }
+    }
+
+    /// Write the persistable results back to the file the cache was opened
+    /// from, dropping those about files that were since removed or changed.
+    /// Does nothing for an in-memory cache.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache directory or file cannot be written.
+    pub fn save(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let entries = self
+            .entries
+            .lock()
+            .map(|entries| {
+                entries
+                    .iter()
+                    .filter(|(key, entry)| {
+                        entry.persist && FileKey::of(&entry.path).as_ref() == Some(&key.file)
+                    })
+                    .map(|(key, entry)| PersistedEntry {
+                        key: key.clone(),
+                        path: entry.path.clone(),
+                        target: entry.target.clone(),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let content = serde_json::to_string(&CacheFile {
+            version: CACHE_VERSION,
+            entries,
+        })?;
+
+        let io_error = |context: String| move |source| SymseekError::Io { context, source };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(io_error(format!("Failed to create {}", dir.display())))?;
+        }
+        // Write a sibling and rename it, so concurrent runs never read a
+        // partial file
+        let temp = path.with_extension(format!("json.{}", std::process::id()));
+        fs::write(&temp, content)
+            .map_err(io_error(format!("Failed to write {}", temp.display())))?;
+        fs::rename(&temp, path).map_err(io_error(format!("Failed to write {}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn key(temp: &TempDir, detector: Detector) -> CacheKey {
+        let file = temp.child("wrapper");
+        if !file.exists() {
+            file.write_str("#!/bin/sh\n").unwrap();
+        }
+        CacheKey {
+            file: FileKey::of(file.path()).unwrap(),
+            name: "wrapper".to_string(),
+            root: None,
+            stores: vec![PathBuf::from("/nix/store")],
+            detector,
+        }
+    }
+
+    fn wrapper(temp: &TempDir) -> PathBuf {
+        temp.child("wrapper").to_path_buf()
+    }
+
+    #[test]
+    fn test_cache_get_insert() {
+        let temp = TempDir::new().unwrap();
+        let cache = DetectorCache::new();
+        let key = key(&temp, Detector::NixProgramName);
+
+        assert_eq!(cache.get(&key), None);
+        cache.insert(key.clone(), &wrapper(&temp), None, false);
+        assert_eq!(cache.get(&key), Some(None));
+        cache.insert(
+            key.clone(),
+            &wrapper(&temp),
+            Some("/nix/store/abc-foo/bin/foo".to_string()),
+            false,
+        );
+        assert_eq!(
+            cache.get(&key),
+            Some(Some("/nix/store/abc-foo/bin/foo".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_cache_save_only_persists_store_results() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("cache/detectors.json");
+        let persisted = key(&temp, Detector::NixBinaryWrapper);
+        let transient = key(&temp, Detector::NixProgramName);
+
+        let cache = DetectorCache::open(path.path());
+        cache.insert(
+            persisted.clone(),
+            &wrapper(&temp),
+            Some("/nix/store/abc-foo/bin/foo".to_string()),
+            true,
+        );
+        cache.insert(transient.clone(), &wrapper(&temp), None, false);
+        cache.save().unwrap();
+
+        let reopened = DetectorCache::open(path.path());
+        assert_eq!(
+            reopened.get(&persisted),
+            Some(Some("/nix/store/abc-foo/bin/foo".to_string()))
+        );
+        assert_eq!(reopened.get(&transient), None);
+    }
+
+    #[test]
+    fn test_cache_ignores_invalid_file() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("detectors.json");
+        path.write_str("not json").unwrap();
+
+        let cache = DetectorCache::open(path.path());
+        assert_eq!(cache.get(&key(&temp, Detector::NixProgramName)), None);
+    }
+
+    #[test]
+    fn test_cache_save_prunes_changed_files() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.child("detectors.json");
+        let key = key(&temp, Detector::NixBinaryWrapper);
+
+        let cache = DetectorCache::open(path.path());
+        cache.insert(key.clone(), &wrapper(&temp), None, true);
+        cache.save().unwrap();
+        assert_eq!(DetectorCache::open(path.path()).get(&key), Some(None));
+
+        temp.child("wrapper")
+            .write_str("#!/bin/sh\nexec true\n")
+            .unwrap();
+        cache.save().unwrap();
+        assert_eq!(DetectorCache::open(path.path()).get(&key), None);
+    }
+
+    #[test]
+    fn test_cache_key_includes_stores() {
+        let temp = TempDir::new().unwrap();
+        let cache = DetectorCache::new();
+        let key = key(&temp, Detector::NixProgramName);
+        cache.insert(key.clone(), &wrapper(&temp), None, false);
+
+        let relocated = CacheKey {
+            stores: vec![PathBuf::from("/home/u/nix/store")],
+            ..key
+        };
+        assert_eq!(cache.get(&relocated), None);
+    }
+}
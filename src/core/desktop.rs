@@ -0,0 +1,343 @@
+use crate::error::{Result, SymseekError};
+use log::{debug, trace};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DESKTOP_SUFFIX: &str = ".desktop";
+const DESKTOP_ENTRY_GROUP: &str = "[Desktop Entry]";
+const APPLICATIONS_DIR: &str = "applications";
+
+/// Defaults from the XDG Base Directory Specification.
+const DEFAULT_DATA_HOME: &str = ".local/share";
+const DEFAULT_DATA_DIRS: &str = "/usr/local/share:/usr/share";
+
+/// Characters a backslash escapes inside a quoted `Exec=` argument.
+const QUOTED_ESCAPES: &[char] = &['"', '`', '$', '\\'];
+
+/// A desktop entry and the command its `Exec=` key runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    /// Desktop file ID, e.g. `org.gnome.Terminal.desktop`
+    pub id: String,
+    pub path: PathBuf,
+    pub name: Option<String>,
+    /// `Exec=` value as written
+    pub exec: String,
+    /// Command line with quoting removed and field codes such as `%U` dropped
+    pub argv: Vec<String>,
+    /// `TryExec=` value, the file whose absence hides the entry
+    pub try_exec: Option<String>,
+    /// Where `TryExec=` was found, if it was
+    pub try_exec_path: Option<PathBuf>,
+}
+
+impl DesktopEntry {
+    /// Read a desktop entry from a file path or look up a desktop file ID
+    /// in the `applications` directories of `XDG_DATA_HOME` and
+    /// `XDG_DATA_DIRS`.
+    ///
+    /// The `.desktop` suffix of an ID may be left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the entry is not found, cannot be read or has no
+    /// `Exec=` key.
+    pub fn load(file_or_id: &str) -> Result<Self> {
+        let path = Path::new(file_or_id);
+        if file_or_id.contains('/') && path.is_file() {
+            let id = path
+                .file_name()
+                .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+            return Self::read(id, path.to_path_buf());
+        }
+        Self::load_from(&application_dirs(), file_or_id)
+    }
+
+    fn load_from(dirs: &[PathBuf], id: &str) -> Result<Self> {
+        let id = if id.ends_with(DESKTOP_SUFFIX) {
+            id.to_string()
+        } else {
+            format!("{id}{DESKTOP_SUFFIX}")
+        };
+
+        let path = dirs
+            .iter()
+            .find_map(|dir| find_in_dir(dir, "", &id))
+            .ok_or_else(|| SymseekError::NotFound {
+                name: id.clone(),
+                searched_locations: dirs.iter().map(|dir| dir.display().to_string()).collect(),
+            })?;
+        debug!("Desktop entry {id} found at {}", path.display());
+
+        Self::read(id, path)
+    }
+
+    fn read(id: String, path: PathBuf) -> Result<Self> {
+        let content = fs::read_to_string(&path).map_err(|e| SymseekError::Io {
+            context: format!("Failed to read {}", path.display()),
+            source: e,
+        })?;
+
+        let mut name = None;
+        let mut exec = None;
+        let mut try_exec = None;
+        let mut in_entry = false;
+        for line in content.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_entry = line == DESKTOP_ENTRY_GROUP;
+                continue;
+            }
+            if !in_entry {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = unescape_string(value.trim());
+            match key.trim() {
+                "Name" => name = Some(value),
+                "Exec" => exec = Some(value),
+                "TryExec" => try_exec = Some(value),
+                _ => {}
+            }
+        }
+
+        let exec = exec.ok_or_else(|| SymseekError::InvalidInput {
+            message: format!("{} has no Exec= key", path.display()),
+        })?;
+        let argv = split_exec(&exec);
+        if argv.is_empty() {
+            return Err(SymseekError::InvalidInput {
+                message: format!("{} has an empty Exec= key", path.display()),
+            });
+        }
+        let try_exec_path = try_exec.as_deref().and_then(find_program);
+        trace!("Desktop entry {id}: {argv:?}");
+
+        Ok(Self {
+            id,
+            path,
+            name,
+            exec,
+            argv,
+            try_exec,
+            try_exec_path,
+        })
+    }
+
+    /// Get the program the entry runs, as written in `Exec=`.
+    #[must_use]
+    pub fn executable(&self) -> &str {
+        &self.argv[0]
+    }
+}
+
+fn application_dirs() -> Vec<PathBuf> {
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(DEFAULT_DATA_HOME)));
+    let data_dirs = env::var_os("XDG_DATA_DIRS")
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| DEFAULT_DATA_DIRS.into());
+
+    data_home
+        .into_iter()
+        .chain(env::split_paths(&data_dirs))
+        .map(|dir| dir.join(APPLICATIONS_DIR))
+        .collect()
+}
+
+/// Find a desktop file ID below a directory. Subdirectories form part of
+/// the ID, so `kde4/konsole.desktop` has the ID `kde4-konsole.desktop`.
+fn find_in_dir(dir: &Path, prefix: &str, id: &str) -> Option<PathBuf> {
+    let rest = id.strip_prefix(prefix)?;
+    let direct = dir.join(rest);
+    if direct.is_file() {
+        return Some(direct);
+    }
+
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .find_map(|entry| {
+            let name = entry.file_name();
+            let prefix = format!("{prefix}{}-", name.to_str()?);
+            find_in_dir(&entry.path(), &prefix, id)
+        })
+}
+
+/// Look up a program in `PATH` unless it is given as an absolute path.
+fn find_program(program: &str) -> Option<PathBuf> {
+    let path = Path::new(program);
+    if path.is_absolute() {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+    env::split_paths(&env::var_os("PATH")?)
+        .map(|dir| dir.join(program))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Undo the escapes of a desktop file string value.
+fn unescape_string(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => result.push(' '),
+            Some('n') => result.push('\n'),
+            Some('t') => result.push('\t'),
+            Some('r') => result.push('\r'),
+            Some('\\') => result.push('\\'),
+            Some(other) => {
+                // Kept for the Exec= quoting rules to handle
+                result.push('\\');
+                result.push(other);
+            }
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// Split an `Exec=` value into arguments.
+///
+/// Arguments are separated by spaces and may be enclosed in double quotes,
+/// inside which a backslash escapes `"`, `` ` ``, `$` and `\`. Field codes
+/// are dropped, and an argument that consisted only of a field code is
+/// removed altogether.
+fn split_exec(exec: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quoted = false;
+    let mut only_field_codes = true;
+    let mut chars = exec.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_arg = true;
+                only_field_codes = false;
+            }
+            '\\' if quoted
+                && chars
+                    .peek()
+                    .is_some_and(|next| QUOTED_ESCAPES.contains(next)) =>
+            {
+                current.extend(chars.next());
+            }
+            '%' => {
+                in_arg = true;
+                if let Some('%') = chars.next() {
+                    current.push('%');
+                    only_field_codes = false;
+                }
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_arg && !(only_field_codes && current.is_empty()) {
+                    args.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                in_arg = false;
+                only_field_codes = true;
+            }
+            c => {
+                current.push(c);
+                in_arg = true;
+                only_field_codes = false;
+            }
+        }
+    }
+    if in_arg && !(only_field_codes && current.is_empty()) {
+        args.push(current);
+    }
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn test_split_exec_field_codes() {
+        assert_eq!(split_exec("firefox %u"), vec!["firefox"]);
+        assert_eq!(
+            split_exec("env GDK_BACKEND=x11 app --name=%c %F 100%%"),
+            vec!["env", "GDK_BACKEND=x11", "app", "--name=", "100%"]
+        );
+    }
+
+    #[test]
+    fn test_split_exec_quoting() {
+        assert_eq!(
+            split_exec(r#""/opt/My App/run" --title "say \"hi\" \$HOME" """#),
+            vec!["/opt/My App/run", "--title", "say \"hi\" $HOME", ""]
+        );
+        // String escapes are undone before the Exec= quoting rules apply
+        assert_eq!(
+            split_exec(&unescape_string(r#"sh -c "echo \\\\""#)),
+            vec!["sh", "-c", "echo \\"]
+        );
+    }
+
+    #[test]
+    fn test_load_by_id() {
+        let temp = TempDir::new().unwrap();
+        let home = temp.child("home/applications");
+        let system = temp.child("usr/applications");
+        system
+            .child("org.example.App.desktop")
+            .write_str(
+                "# comment\n[Desktop Entry]\nName=App\nName[de]=Anwendung\n\
+                 TryExec=/nonexistent/app\nExec=app\\s--new %U\n\n\
+                 [Desktop Action private]\nExec=app --private\n",
+            )
+            .unwrap();
+        home.child("kde4/konsole.desktop")
+            .write_str("[Desktop Entry]\nExec=konsole\n")
+            .unwrap();
+        let dirs = [home.to_path_buf(), system.to_path_buf()];
+
+        let entry = DesktopEntry::load_from(&dirs, "org.example.App").unwrap();
+        assert_eq!(entry.id, "org.example.App.desktop");
+        assert_eq!(entry.name.as_deref(), Some("App"));
+        assert_eq!(entry.exec, "app --new %U");
+        assert_eq!(entry.argv, vec!["app", "--new"]);
+        assert_eq!(entry.try_exec.as_deref(), Some("/nonexistent/app"));
+        assert_eq!(entry.try_exec_path, None);
+
+        let entry = DesktopEntry::load_from(&dirs, "kde4-konsole.desktop").unwrap();
+        assert_eq!(entry.path, home.child("kde4/konsole.desktop").path());
+        assert_eq!(entry.executable(), "konsole");
+    }
+
+    #[test]
+    fn test_load_without_exec() {
+        let temp = TempDir::new().unwrap();
+        temp.child("link.desktop")
+            .write_str("[Desktop Entry]\nType=Link\nURL=https://example.com\n")
+            .unwrap();
+
+        assert!(matches!(
+            DesktopEntry::load_from(&[temp.to_path_buf()], "link"),
+            Err(SymseekError::InvalidInput { .. })
+        ));
+        assert!(matches!(
+            DesktopEntry::load_from(&[temp.to_path_buf()], "missing"),
+            Err(SymseekError::NotFound { .. })
+        ));
+    }
+}
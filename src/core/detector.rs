@@ -1,11 +1,17 @@
+pub mod compiler_cache;
+pub mod launcher;
+pub mod multi_call;
 pub mod nix_binary_wrapper;
 pub mod nix_program_name;
+pub mod self_extracting;
+pub mod squashfs;
 
 use crate::error::{Result, SymseekError};
 use log::{debug, trace};
 use regex::Regex;
+use self_extracting::SelfExtractingKind;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
 const MAX_FILE_SIZE: u64 = 1_048_576;
@@ -16,9 +22,8 @@ const PRINTABLE_ASCII_MIN: u8 = 32;
 const PRINTABLE_ASCII_MAX: u8 = 126;
 const WRAPPED_SUFFIX: &str = "-wrapped";
 const UNWRAPPED_SUFFIX: &str = "-unwrapped";
-
-pub static NIX_STORE_PATH_REGEX: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new(r"/nix/store/[a-z0-9]+-[^/\s]+(?:/[^/\s]+)*").unwrap());
+/// Guix's `wrap-program` moves the real program to `.foo-real`
+const REAL_SUFFIX: &str = "-real";
 
 pub static MAKE_C_WRAPPER_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"makeCWrapper\s+'([^']+)'").unwrap());
@@ -33,6 +38,8 @@ pub enum FileType {
     ElfBinary,
     OtherBinary,
     OtherText,
+    /// AppImage, makeself archive or packed executable
+    SelfExtracting(SelfExtractingKind),
 }
 
 /// Detect the type of a file by examining its content.
@@ -41,6 +48,8 @@ pub enum FileType {
 /// Python script, Perl script, ELF binary, or other text/binary file.
 ///
 /// Uses the ELF magic number for binary detection and shebangs for script detection.
+/// AppImages, makeself archives and UPX or PyInstaller executables are
+/// reported as self-extracting.
 ///
 /// # Errors
 ///
@@ -73,6 +82,10 @@ pub fn detect_file_type(path: &Path) -> Result<FileType> {
     trace!("Read {} bytes from {}", bytes_read, path.display());
 
     if buffer.len() >= ELF_MAGIC.len() && buffer[0..ELF_MAGIC.len()] == *ELF_MAGIC {
+        if let Some(kind) = self_extracting::detect_elf(path, &buffer) {
+            trace!("Detected as self-extracting {kind:?}: {}", path.display());
+            return Ok(FileType::SelfExtracting(kind));
+        }
         trace!("Detected as ELF binary: {}", path.display());
         return Ok(FileType::ElfBinary);
     }
@@ -80,6 +93,11 @@ pub fn detect_file_type(path: &Path) -> Result<FileType> {
     if buffer.starts_with(SHEBANG_PREFIX) {
         trace!("Shebang detected in: {}", path.display());
 
+        if let Some(kind) = self_extracting::detect_script(path, &buffer) {
+            trace!("Detected as self-extracting {kind:?}: {}", path.display());
+            return Ok(FileType::SelfExtracting(kind));
+        }
+
         let newline_pos = buffer
             .iter()
             .position(|&b| b == b'\n')
@@ -115,12 +133,52 @@ pub fn detect_file_type(path: &Path) -> Result<FileType> {
 }
 
 pub trait WrapperDetector {
+    /// Check whether a file is worth reading at all, before its content is
+    /// loaded.
+    fn applies_to(&self, _path: &Path) -> bool {
+        true
+    }
+
+    /// Detect a wrapper from content already read with [`read_content`].
+    fn detect_in(&self, path: &Path, content: &str) -> Option<String>;
+
     /// Detect if the given path is a wrapper for another executable.
     ///
     /// # Errors
     ///
     /// Returns an error if the file cannot be read or analyzed.
-    fn detect(&self, path: &Path) -> Result<Option<String>>;
+    fn detect(&self, path: &Path) -> Result<Option<String>> {
+        if !self.applies_to(path) {
+            return Ok(None);
+        }
+        Ok(read_content(path)?.and_then(|content| self.detect_in(path, &content)))
+    }
+}
+
+/// Read a file for the wrapper detectors: as text, or as the printable
+/// strings of a binary. Files over `MAX_FILE_SIZE` are skipped.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read.
+pub fn read_content(path: &Path) -> Result<Option<String>> {
+    let metadata = fs::metadata(path).map_err(|e| SymseekError::Io {
+        context: format!("Failed to read metadata for {}", path.display()),
+        source: e,
+    })?;
+    if metadata.len() > MAX_FILE_SIZE {
+        trace!("{} too large for wrapper detection", path.display());
+        return Ok(None);
+    }
+
+    let bytes = fs::read(path).map_err(|e| SymseekError::Io {
+        context: format!("Failed to read file {}", path.display()),
+        source: e,
+    })?;
+    Ok(Some(match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => extract_strings_from_binary(e.as_bytes()),
+    }))
 }
 
 #[must_use]
@@ -129,6 +187,9 @@ pub fn normalize_program_name(name: &str) -> &str {
 
     if let Some(stripped) = result.strip_prefix('.') {
         result = stripped;
+        if let Some(real) = result.strip_suffix(REAL_SUFFIX) {
+            return real;
+        }
     }
 
     if result.ends_with(UNWRAPPED_SUFFIX) {
@@ -154,6 +215,24 @@ pub fn programs_match(current: &Path, candidate: &Path) -> bool {
     !current_name.is_empty() && current_name == candidate_name
 }
 
+/// Map an absolute path inside a root directory to the host, leaving it
+/// unchanged without a root.
+#[must_use]
+pub fn root_path(root: Option<&Path>, path: &Path) -> PathBuf {
+    match root {
+        Some(root) if path.is_absolute() => root.join(path.strip_prefix("/").unwrap_or(path)),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Map a host path below a root directory to the path it has inside the
+/// root, leaving other paths unchanged.
+#[must_use]
+pub fn unroot_path(root: Option<&Path>, path: &Path) -> PathBuf {
+    root.and_then(|root| path.strip_prefix(root).ok())
+        .map_or_else(|| path.to_path_buf(), |path| Path::new("/").join(path))
+}
+
 #[must_use]
 pub fn extract_strings_from_binary(bytes: &[u8]) -> String {
     let mut result = String::new();
@@ -211,6 +290,14 @@ mod tests {
         assert_eq!(normalize_program_name(".python-unwrapped"), "python");
     }
 
+    #[test]
+    fn test_normalize_program_name_guix_real() {
+        assert_eq!(normalize_program_name(".emacs-real"), "emacs");
+        assert_eq!(normalize_program_name(".foo-bar-real"), "foo-bar");
+        assert_eq!(normalize_program_name("unreal"), "unreal");
+        assert_eq!(normalize_program_name("foo-real"), "foo-real");
+    }
+
     #[test]
     fn test_normalize_program_name_edge_cases() {
         assert_eq!(normalize_program_name(""), "");
@@ -398,6 +485,37 @@ mod tests {
             assert!(matches!(file_type, FileType::OtherBinary));
         }
 
+        #[test]
+        fn test_detect_appimage() {
+            use super::super::self_extracting::AppImageType;
+
+            let temp = TempDir::new().unwrap();
+            let header = [
+                0x7f, b'E', b'L', b'F', 0x02, 0x01, 0x01, 0x00, b'A', b'I', 0x02,
+            ];
+            let file = temp.child("Hello.AppImage");
+            file.write_binary(&header).unwrap();
+
+            let file_type = detect_file_type(file.path()).unwrap();
+            assert_eq!(
+                file_type,
+                FileType::SelfExtracting(SelfExtractingKind::AppImage(AppImageType::Type2))
+            );
+        }
+
+        #[test]
+        fn test_detect_makeself_archive() {
+            let temp = TempDir::new().unwrap();
+            let script = "#!/bin/sh\n# This script was generated using Makeself 2.5.0\n\nMS_Printf()\n{\n}\n";
+            let path = create_executable_script(&temp, "installer.run", script);
+
+            let file_type = detect_file_type(&path).unwrap();
+            assert_eq!(
+                file_type,
+                FileType::SelfExtracting(SelfExtractingKind::Makeself)
+            );
+        }
+
         #[test]
         fn test_detect_symlink() {
             let temp = TempDir::new().unwrap();
@@ -420,6 +538,7 @@ mod tests {
         #[test]
         fn test_nix_binary_wrapper_detector() {
             use super::super::nix_binary_wrapper::NixBinaryWrapperDetector;
+            use crate::core::store::Stores;
 
             let temp = TempDir::new().unwrap();
 
@@ -430,7 +549,8 @@ mod tests {
             let wrapper = bin_dir.child("noctalia-shell-wrapped");
             wrapper.write_str(script_content).unwrap();
 
-            let detector = NixBinaryWrapperDetector;
+            let stores = Stores::default();
+            let detector = NixBinaryWrapperDetector::new(&stores);
             let result = detector.detect(wrapper.path()).unwrap();
             assert!(result.is_some());
             assert_eq!(result.unwrap(), "/nix/store/abc123-quickshell-0.2.1/bin/qs");
@@ -439,6 +559,7 @@ mod tests {
         #[test]
         fn test_nix_binary_wrapper_detector_not_nix_path() {
             use super::super::nix_binary_wrapper::NixBinaryWrapperDetector;
+            use crate::core::store::Stores;
 
             let temp = TempDir::new().unwrap();
 
@@ -449,9 +570,47 @@ mod tests {
             let wrapper = bin_dir.child("wrapper");
             wrapper.write_str(script_content).unwrap();
 
-            let detector = NixBinaryWrapperDetector;
+            let stores = Stores::default();
+            let detector = NixBinaryWrapperDetector::new(&stores);
             let result = detector.detect(wrapper.path()).unwrap();
             assert!(result.is_none());
         }
+
+        #[test]
+        fn test_program_name_detector_guix_real_wrapper() {
+            use super::super::nix_program_name::NixProgramNameDetector;
+            use crate::core::store::{Store, StoreFlavor, Stores};
+
+            let temp = TempDir::new().unwrap();
+            let bin = temp.child("store/abc123-foo-1.0/bin");
+            bin.create_dir_all().unwrap();
+            let real = create_executable_script(
+                &temp,
+                "store/abc123-foo-1.0/bin/.foo-real",
+                "#!/bin/sh\n",
+            );
+            let script = format!(
+                "#!/bin/bash\nexport PATH=\"/usr/bin\"\nexec -a \"$0\" \"{}\" \"$@\"\n",
+                real.display()
+            );
+            let wrapper = create_executable_script(&temp, "store/abc123-foo-1.0/bin/foo", &script);
+
+            let stores = Stores::new(vec![Store::new(
+                temp.child("store").path(),
+                StoreFlavor::Guix,
+            )]);
+            let result = NixProgramNameDetector::new(&stores)
+                .detect(&wrapper)
+                .unwrap();
+            assert_eq!(result, Some(real.to_string_lossy().into_owned()));
+
+            let other_stores = Stores::default();
+            assert!(
+                NixProgramNameDetector::new(&other_stores)
+                    .detect(&wrapper)
+                    .unwrap()
+                    .is_none()
+            );
+        }
     }
 }
@@ -0,0 +1,336 @@
+use crate::core::detector::{FileType, root_path};
+use log::{debug, trace};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Search path ccache uses instead of `PATH` when set
+const CCACHE_PATH_ENV: &str = "CCACHE_PATH";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompilerCacheKind {
+    Ccache,
+    Sccache,
+    Distcc,
+    Icecc,
+}
+
+impl CompilerCacheKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Ccache => "ccache",
+            Self::Sccache => "sccache",
+            Self::Distcc => "distcc",
+            Self::Icecc => "icecc",
+        }
+    }
+
+    /// Recognize a tool by its binary name, with or without a version
+    /// suffix such as `ccache-4.9`.
+    fn from_binary_name(name: &str) -> Option<Self> {
+        [Self::Ccache, Self::Sccache, Self::Distcc, Self::Icecc]
+            .into_iter()
+            .find(|kind| match name.strip_prefix(kind.as_str()) {
+                Some("") => true,
+                Some(suffix) => suffix
+                    .strip_prefix('-')
+                    .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit())),
+                None => false,
+            })
+    }
+
+    /// Recognize the tool `path` runs, by its own name or, when reached
+    /// through further links, by the name of the binary they end at.
+    fn of_binary(path: &Path) -> Option<Self> {
+        let by_name = |path: &Path| Self::from_binary_name(path.file_name()?.to_str()?);
+        by_name(path).or_else(|| by_name(&fs::canonicalize(path).ok()?))
+    }
+
+    /// Variable overriding the directories searched for the real compiler.
+    const fn search_path_env(self) -> Option<&'static str> {
+        match self {
+            Self::Ccache => Some(CCACHE_PATH_ENV),
+            Self::Sccache | Self::Distcc | Self::Icecc => None,
+        }
+    }
+}
+
+/// A compiler cache or distributor invoked through a masquerade link such
+/// as `/usr/lib/ccache/gcc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompilerCache {
+    pub kind: CompilerCacheKind,
+    /// Compiler name the tool was invoked as
+    pub compiler_name: String,
+    /// Real compiler the tool would execute, inside the root resolved in
+    pub compiler: PathBuf,
+}
+
+/// Recognize a masquerade link to a compiler cache and find the compiler
+/// it would run, searching the tool's own search path variable or `PATH`.
+///
+/// `invoked` is the masquerade link the program was executed through, whose
+/// name is the compiler's, and `current` the cache binary it finally
+/// reaches, both on the host. The search path is taken to list
+/// directories inside `root`. Nothing is reported when no real compiler is
+/// found.
+#[must_use]
+pub fn detect(
+    invoked: &Path,
+    current: &Path,
+    file_type: &FileType,
+    root: Option<&Path>,
+) -> Option<CompilerCache> {
+    let kind = CompilerCacheKind::of_binary(current)?;
+    let search_path = kind
+        .search_path_env()
+        .and_then(env::var_os)
+        .or_else(|| env::var_os("PATH"))?;
+    detect_in(invoked, current, file_type, &search_path, root)
+}
+
+/// Like [`detect`], but search the directories of `search_path` for the
+/// real compiler instead of the environment's.
+#[must_use]
+pub fn detect_in(
+    invoked: &Path,
+    current: &Path,
+    file_type: &FileType,
+    search_path: &OsStr,
+    root: Option<&Path>,
+) -> Option<CompilerCache> {
+    if *file_type != FileType::ElfBinary {
+        return None;
+    }
+    let kind = CompilerCacheKind::of_binary(current)?;
+    let compiler_name = invoked.file_name()?.to_str()?;
+    if CompilerCacheKind::from_binary_name(compiler_name).is_some() {
+        trace!("{} invoked directly, no compiler to follow", kind.as_str());
+        return None;
+    }
+
+    let Some(compiler) = find_compiler(invoked, current, compiler_name, search_path, root) else {
+        debug!(
+            "No real compiler {compiler_name} found for {}",
+            kind.as_str()
+        );
+        return None;
+    };
+    debug!(
+        "{} masquerading as {compiler_name} runs {}",
+        kind.as_str(),
+        compiler.display()
+    );
+
+    Some(CompilerCache {
+        kind,
+        compiler_name: compiler_name.to_string(),
+        compiler,
+    })
+}
+
+/// Search the path like ccache does: skip the masquerade directory, and any
+/// candidate that resolves back to the cache tool itself.
+///
+/// The compiler is returned as found in the search path, inside `root`.
+fn find_compiler(
+    invoked: &Path,
+    tool: &Path,
+    name: &str,
+    search_path: &OsStr,
+    root: Option<&Path>,
+) -> Option<PathBuf> {
+    let masquerade_dir = invoked.parent().and_then(|dir| fs::canonicalize(dir).ok());
+    let tool = fs::canonicalize(tool).ok()?;
+
+    env::split_paths(search_path).find_map(|dir| {
+        if !dir.is_absolute() {
+            return None;
+        }
+        let host_dir = root_path(root, &dir);
+        let canonical_dir = fs::canonicalize(&host_dir).ok()?;
+        if masquerade_dir.as_ref() == Some(&canonical_dir) {
+            trace!("Skipping masquerade directory: {}", dir.display());
+            return None;
+        }
+
+        let resolved = fs::canonicalize(host_dir.join(name)).ok()?;
+        if resolved == tool {
+            trace!(
+                "Skipping link back to the cache: {}",
+                dir.join(name).display()
+            );
+            return None;
+        }
+        resolved.is_file().then(|| dir.join(name))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Build `bin/ccache`, a masquerade directory `lib/ccache` and a real
+    /// `gcc` in `usr/bin`, plus a `local/bin/gcc` linking back to ccache.
+    fn create_layout(temp: &TempDir) {
+        temp.child("bin/ccache").write_binary(b"\x7fELF").unwrap();
+        temp.child("lib/ccache").create_dir_all().unwrap();
+        temp.child("lib/ccache/gcc")
+            .symlink_to_file("../../bin/ccache")
+            .unwrap();
+        temp.child("local/bin").create_dir_all().unwrap();
+        temp.child("local/bin/gcc")
+            .symlink_to_file("../../bin/ccache")
+            .unwrap();
+        temp.child("usr/bin/gcc").write_binary(b"\x7fELF").unwrap();
+    }
+
+    fn search_path(temp: &TempDir, dirs: &[&str]) -> std::ffi::OsString {
+        env::join_paths(dirs.iter().map(|dir| temp.path().join(dir))).unwrap()
+    }
+
+    #[test]
+    fn test_masquerade_skips_own_directory_and_links() {
+        let temp = TempDir::new().unwrap();
+        create_layout(&temp);
+
+        let cache = detect_in(
+            &temp.path().join("lib/ccache/gcc"),
+            &temp.path().join("bin/ccache"),
+            &FileType::ElfBinary,
+            &search_path(&temp, &["lib/ccache", "local/bin", "usr/bin"]),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(cache.compiler_name, "gcc");
+        assert_eq!(cache.compiler, temp.path().join("usr/bin/gcc"));
+    }
+
+    #[test]
+    fn test_masquerade_without_real_compiler() {
+        let temp = TempDir::new().unwrap();
+        create_layout(&temp);
+
+        assert!(
+            detect_in(
+                &temp.path().join("lib/ccache/gcc"),
+                &temp.path().join("bin/ccache"),
+                &FileType::ElfBinary,
+                &search_path(&temp, &["lib/ccache", "local/bin"]),
+                None,
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_invoked_directly() {
+        let temp = TempDir::new().unwrap();
+        create_layout(&temp);
+        let ccache = temp.path().join("bin/ccache");
+
+        assert!(
+            detect_in(
+                &ccache,
+                &ccache,
+                &FileType::ElfBinary,
+                &search_path(&temp, &["usr/bin"]),
+                None,
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_masquerade_requires_cache_binary() {
+        let temp = TempDir::new().unwrap();
+        create_layout(&temp);
+        let search_path = search_path(&temp, &["usr/bin"]);
+        let gcc = temp.path().join("lib/ccache/gcc");
+
+        assert_eq!(
+            detect_in(
+                &gcc,
+                &temp.path().join("bin/ccache"),
+                &FileType::ElfBinary,
+                &search_path,
+                None,
+            )
+            .unwrap()
+            .kind,
+            CompilerCacheKind::Ccache
+        );
+        assert!(
+            detect_in(
+                &gcc,
+                &temp.path().join("bin/ccache"),
+                &FileType::ShellScript,
+                &search_path,
+                None,
+            )
+            .is_none()
+        );
+        assert!(
+            detect_in(
+                &gcc,
+                &temp.path().join("usr/bin/gcc"),
+                &FileType::ElfBinary,
+                &search_path,
+                None,
+            )
+            .is_none()
+        );
+    }
+
+    #[test]
+    fn test_masquerade_in_root() {
+        let temp = TempDir::new().unwrap();
+        create_layout(&temp);
+
+        let cache = detect_in(
+            &temp.path().join("lib/ccache/gcc"),
+            &temp.path().join("bin/ccache"),
+            &FileType::ElfBinary,
+            OsStr::new("/lib/ccache:/local/bin:/usr/bin"),
+            Some(temp.path()),
+        )
+        .unwrap();
+
+        assert_eq!(cache.compiler, PathBuf::from("/usr/bin/gcc"));
+    }
+
+    #[test]
+    fn test_masquerade_through_second_link() {
+        let temp = TempDir::new().unwrap();
+        temp.child("bin/ccache-4.9")
+            .write_binary(b"\x7fELF")
+            .unwrap();
+        temp.child("bin/ccache")
+            .symlink_to_file("ccache-4.9")
+            .unwrap();
+        temp.child("lib/ccache").create_dir_all().unwrap();
+        temp.child("lib/ccache/gcc")
+            .symlink_to_file("../../bin/ccache")
+            .unwrap();
+        temp.child("usr/bin/gcc").write_binary(b"\x7fELF").unwrap();
+
+        let cache = detect_in(
+            &temp.path().join("lib/ccache/gcc"),
+            &temp.path().join("bin/ccache-4.9"),
+            &FileType::ElfBinary,
+            &search_path(&temp, &["lib/ccache", "usr/bin"]),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(cache.kind, CompilerCacheKind::Ccache);
+        assert_eq!(cache.compiler_name, "gcc");
+        assert_eq!(cache.compiler, temp.path().join("usr/bin/gcc"));
+        assert_eq!(CompilerCacheKind::from_binary_name("ccache-wrapper"), None);
+    }
+}
@@ -0,0 +1,439 @@
+use crate::core::detector::{FileType, MAX_FILE_SIZE, root_path, unroot_path};
+use log::{debug, trace};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FLATPAK_SYSTEM_INSTALLATION: &str = "/var/lib/flatpak";
+const FLATPAK_USER_INSTALLATION: &str = ".local/share/flatpak";
+const FLATPAK_APP_DIR: &str = "app";
+const FLATPAK_METADATA_FILE: &str = "metadata";
+const FLATPAK_APPLICATION_GROUP: &str = "[Application]";
+/// Prefix under which a Flatpak app sees its own `files` directory
+const FLATPAK_APP_PREFIX: &str = "/app/";
+
+/// Name of the binary every `/snap/bin` entry links to.
+const SNAP_BINARY_NAME: &str = "snap";
+const SNAP_YAML_FILE: &str = "meta/snap.yaml";
+
+/// How a sandboxed application is launched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LauncherKind {
+    Flatpak,
+    Snap,
+}
+
+/// A Flatpak or Snap launcher resolved to the application it starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppLauncher {
+    pub kind: LauncherKind,
+    /// Flatpak application id, or `snap.app` for Snap
+    pub app_id: String,
+    /// Installed deployment, e.g. `/var/lib/flatpak/app/<id>/x86_64/stable/<commit>`
+    /// or `/snap/<name>/<revision>`, inside the root resolved in
+    pub deployment: PathBuf,
+    /// Command declared in the application's metadata
+    pub command: String,
+}
+
+impl AppLauncher {
+    /// Get the file the launcher finally executes inside the deployment.
+    #[must_use]
+    pub fn entry_point(&self) -> PathBuf {
+        match self.kind {
+            LauncherKind::Flatpak => {
+                let files = self.deployment.join("files");
+                match self.command.strip_prefix(FLATPAK_APP_PREFIX) {
+                    Some(inside_app) => files.join(inside_app),
+                    None => files.join("bin").join(&self.command),
+                }
+            }
+            LauncherKind::Snap => self.deployment.join(&self.command),
+        }
+    }
+}
+
+/// Recognize a Flatpak export script or a `/snap/bin` entry.
+///
+/// `invoked` is the path that led to `current`, which for Snap is the
+/// `/snap/bin/<app>` link naming the application while `current` is the
+/// `snap` binary itself. Launchers whose entry point is not installed are
+/// not reported.
+///
+/// Both paths are on the host. The default Flatpak installations are looked
+/// up below `root`, and the deployment is reported as a path inside it.
+#[must_use]
+pub fn detect(
+    invoked: &Path,
+    current: &Path,
+    file_type: &FileType,
+    root: Option<&Path>,
+) -> Option<AppLauncher> {
+    let mut launcher = match file_type {
+        FileType::ShellScript => detect_flatpak(current, root),
+        FileType::ElfBinary => detect_snap(invoked, current),
+        _ => None,
+    }?;
+    // The deployment was canonicalized, so compare it to the canonical root
+    let canonical_root = root.and_then(|root| fs::canonicalize(root).ok());
+    launcher.deployment = unroot_path(canonical_root.as_deref(), &launcher.deployment);
+
+    let entry_point = root_path(root, &launcher.entry_point());
+    if entry_point.is_file() {
+        debug!("Launcher {launcher:?} starts {}", entry_point.display());
+        Some(launcher)
+    } else {
+        debug!(
+            "Launcher entry point {} is not installed",
+            entry_point.display()
+        );
+        None
+    }
+}
+
+/// Arguments of a `flatpak run` invocation.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct FlatpakRun {
+    app_id: String,
+    arch: Option<String>,
+    branch: Option<String>,
+    command: Option<String>,
+}
+
+fn detect_flatpak(path: &Path, root: Option<&Path>) -> Option<AppLauncher> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_SIZE {
+        return None;
+    }
+    let content = fs::read_to_string(path).ok()?;
+    let run = content.lines().find_map(parse_flatpak_run)?;
+    trace!("Flatpak launcher in {}: {run:?}", path.display());
+
+    let app_dir = flatpak_installations(path, root)
+        .into_iter()
+        .map(|installation| installation.join(FLATPAK_APP_DIR).join(&run.app_id))
+        .find(|dir| dir.is_dir())?;
+    let deployment_link = match (&run.arch, &run.branch) {
+        (Some(arch), Some(branch)) => app_dir.join(arch).join(branch).join("active"),
+        _ => app_dir.join("current/active"),
+    };
+    let deployment = fs::canonicalize(&deployment_link).ok()?;
+
+    let command = run
+        .command
+        .or_else(|| flatpak_metadata_command(&deployment.join(FLATPAK_METADATA_FILE)))?;
+
+    Some(AppLauncher {
+        kind: LauncherKind::Flatpak,
+        app_id: run.app_id,
+        deployment,
+        command,
+    })
+}
+
+/// Parse a line such as
+/// `exec /usr/bin/flatpak run --branch=stable --arch=x86_64 org.foo.App "$@"`.
+fn parse_flatpak_run(line: &str) -> Option<FlatpakRun> {
+    let mut tokens = line.split_whitespace();
+    tokens.find(|token| token.ends_with("flatpak"))?;
+    if tokens.next()? != "run" {
+        return None;
+    }
+
+    let mut run = FlatpakRun::default();
+    for token in tokens {
+        if let Some(option) = token.strip_prefix("--") {
+            match option.split_once('=') {
+                Some(("arch", value)) => run.arch = Some(value.to_string()),
+                Some(("branch", value)) => run.branch = Some(value.to_string()),
+                Some(("command", value)) => run.command = Some(value.to_string()),
+                _ => {}
+            }
+        } else {
+            run.app_id = token.to_string();
+            return Some(run);
+        }
+    }
+    None
+}
+
+/// The installation the script was exported from, followed by the default
+/// system and user installations below `root`.
+fn flatpak_installations(script: &Path, root: Option<&Path>) -> Vec<PathBuf> {
+    let mut installations: Vec<PathBuf> = script
+        .ancestors()
+        .filter(|dir| dir.file_name().is_some_and(|name| name == FLATPAK_APP_DIR))
+        .filter_map(Path::parent)
+        .map(Path::to_path_buf)
+        .collect();
+    installations.push(root_path(root, Path::new(FLATPAK_SYSTEM_INSTALLATION)));
+    if let Some(home) = env::var_os("HOME") {
+        installations.push(root_path(
+            root,
+            &PathBuf::from(home).join(FLATPAK_USER_INSTALLATION),
+        ));
+    }
+    installations
+}
+
+/// Read `command=` from the `[Application]` group of a Flatpak metadata
+/// key file.
+fn flatpak_metadata_command(path: &Path) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut in_application = false;
+
+    for line in content.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_application = line == FLATPAK_APPLICATION_GROUP;
+        } else if in_application
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim() == "command"
+        {
+            return Some(value.trim().to_string());
+        }
+    }
+    None
+}
+
+fn detect_snap(invoked: &Path, current: &Path) -> Option<AppLauncher> {
+    if current.file_name()? != SNAP_BINARY_NAME || invoked == current {
+        return None;
+    }
+    // `/snap/bin/<snap>[.<app>]`, with the snaps mounted next to `bin`
+    let bin_dir = invoked.parent()?;
+    if bin_dir.file_name()? != "bin" {
+        return None;
+    }
+    let mount_dir = bin_dir.parent()?;
+
+    let invoked_name = invoked.file_name()?.to_str()?;
+    let (snap, app) = invoked_name
+        .split_once('.')
+        .unwrap_or((invoked_name, invoked_name));
+    let deployment = fs::canonicalize(mount_dir.join(snap).join("current")).ok()?;
+
+    let content = fs::read_to_string(deployment.join(SNAP_YAML_FILE)).ok()?;
+    let Some(command) = snap_app_command(&content, app) else {
+        debug!("No command for app {app} in snap.yaml of {snap}");
+        return None;
+    };
+
+    Some(AppLauncher {
+        kind: LauncherKind::Snap,
+        app_id: format!("{snap}.{app}"),
+        deployment,
+        command: snap_command_path(&command).to_string(),
+    })
+}
+
+/// Read `apps.<app>.command` from a `snap.yaml`.
+///
+/// Only block mappings with plain or quoted scalars are understood, which
+/// is how snapcraft writes the file.
+fn snap_app_command(content: &str, app: &str) -> Option<String> {
+    let mut in_apps = false;
+    // Indentation of the app names, and of the keys of the app when inside
+    // the requested one
+    let mut app_indent = None;
+    let mut key_indent = None;
+    let mut in_app = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let Some((key, value)) = trimmed.split_once(':') else {
+            continue;
+        };
+        let key = unquote(key.trim());
+        let value = value.trim();
+
+        if indent == 0 {
+            if in_apps {
+                break;
+            }
+            in_apps = key == "apps";
+            continue;
+        }
+        if !in_apps {
+            continue;
+        }
+
+        let app_indent = *app_indent.get_or_insert(indent);
+        if indent <= app_indent {
+            in_app = indent == app_indent && key == app;
+            key_indent = None;
+        } else if in_app && *key_indent.get_or_insert(indent) == indent && key == "command" {
+            return Some(unquote(value).to_string());
+        }
+    }
+    None
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+        .unwrap_or(value)
+}
+
+/// Reduce a snap `command:` to the executable it runs, relative to the snap.
+fn snap_command_path(command: &str) -> &str {
+    let executable = command.split_whitespace().next().unwrap_or(command);
+    ["$SNAP/", "${SNAP}/", "/"]
+        .iter()
+        .find_map(|prefix| executable.strip_prefix(prefix))
+        .unwrap_or(executable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn test_parse_flatpak_run() {
+        assert_eq!(
+            parse_flatpak_run(
+                "exec /usr/bin/flatpak run --branch=stable --arch=x86_64 org.gnome.Maps \"$@\""
+            ),
+            Some(FlatpakRun {
+                app_id: "org.gnome.Maps".to_string(),
+                arch: Some("x86_64".to_string()),
+                branch: Some("stable".to_string()),
+                command: None,
+            })
+        );
+        assert_eq!(parse_flatpak_run("exec /usr/bin/env bash"), None);
+    }
+
+    #[test]
+    fn test_snap_command_path() {
+        assert_eq!(snap_command_path("bin/foo"), "bin/foo");
+        assert_eq!(snap_command_path("$SNAP/usr/bin/foo --flag"), "usr/bin/foo");
+        assert_eq!(snap_command_path("${SNAP}/bin/foo"), "bin/foo");
+    }
+
+    #[test]
+    fn test_snap_app_command() {
+        let yaml = "name: hello\n\
+                    # comment: ignored\n\
+                    apps:\n\
+                    \x20 daemon:\n\
+                    \x20   daemon: simple\n\
+                    \x20   environment:\n\
+                    \x20     command: not-this\n\
+                    \x20   command: \"bin/daemon --quiet\"\n\
+                    \x20 'hello':\n\
+                    \x20   plugs: [network]\n\
+                    \x20   command: bin/hello\n\
+                    hooks:\n\
+                    \x20 install:\n\
+                    \x20   command: bin/hook\n";
+
+        assert_eq!(
+            snap_app_command(yaml, "daemon").as_deref(),
+            Some("bin/daemon --quiet")
+        );
+        assert_eq!(
+            snap_app_command(yaml, "hello").as_deref(),
+            Some("bin/hello")
+        );
+        assert_eq!(snap_app_command(yaml, "install"), None);
+        assert_eq!(snap_app_command(yaml, "environment"), None);
+    }
+
+    #[test]
+    fn test_detect_flatpak_export() {
+        let temp = TempDir::new().unwrap();
+        let deployment = temp.child("app/org.foo.App/x86_64/stable/abc123");
+        deployment
+            .child(FLATPAK_METADATA_FILE)
+            .write_str("[Application]\nname=org.foo.App\ncommand=foo-launcher\n\n[Context]\nshared=network;\n")
+            .unwrap();
+        deployment
+            .child("files/bin/foo-launcher")
+            .write_str("#!/bin/sh\n")
+            .unwrap();
+        let script = deployment.child("export/bin/org.foo.App");
+        script
+            .write_str("#!/bin/sh\nexec /usr/bin/flatpak run --branch=stable --arch=x86_64 org.foo.App \"$@\"\n")
+            .unwrap();
+        temp.child("app/org.foo.App/x86_64/stable/active")
+            .symlink_to_dir("abc123")
+            .unwrap();
+
+        let launcher = detect(script.path(), script.path(), &FileType::ShellScript, None).unwrap();
+
+        assert_eq!(launcher.kind, LauncherKind::Flatpak);
+        assert_eq!(launcher.app_id, "org.foo.App");
+        assert_eq!(
+            launcher.deployment,
+            fs::canonicalize(deployment.path()).unwrap()
+        );
+        assert_eq!(launcher.command, "foo-launcher");
+        assert!(launcher.entry_point().ends_with("files/bin/foo-launcher"));
+    }
+
+    #[test]
+    fn test_detect_snap_app() {
+        let temp = TempDir::new().unwrap();
+        let revision = temp.child("snap/hello/42");
+        revision
+            .child(SNAP_YAML_FILE)
+            .write_str("name: hello\nversion: '2.10'\napps:\n  hello:\n    command: bin/hello\n  universe:\n    command: $SNAP/bin/universe --all\n")
+            .unwrap();
+        revision
+            .child("bin/hello")
+            .write_str("#!/bin/sh\n")
+            .unwrap();
+        revision
+            .child("bin/universe")
+            .write_str("#!/bin/sh\n")
+            .unwrap();
+        temp.child("snap/hello/current")
+            .symlink_to_dir("42")
+            .unwrap();
+        let snap_binary = temp.child("usr/bin/snap");
+        snap_binary.write_binary(b"\x7fELF").unwrap();
+
+        let invoked = temp.child("snap/bin/hello.universe");
+        let launcher = detect(
+            invoked.path(),
+            snap_binary.path(),
+            &FileType::ElfBinary,
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(launcher.kind, LauncherKind::Snap);
+        assert_eq!(launcher.app_id, "hello.universe");
+        assert_eq!(launcher.command, "bin/universe");
+        assert_eq!(
+            launcher.entry_point(),
+            fs::canonicalize(revision.path())
+                .unwrap()
+                .join("bin/universe")
+        );
+
+        let default_app = detect(
+            temp.child("snap/bin/hello").path(),
+            snap_binary.path(),
+            &FileType::ElfBinary,
+            None,
+        )
+        .unwrap();
+        assert_eq!(default_app.app_id, "hello.hello");
+    }
+
+    #[test]
+    fn test_detect_ignores_plain_binary() {
+        let temp = TempDir::new().unwrap();
+        let binary = temp.child("usr/bin/ls");
+        binary.write_binary(b"\x7fELF").unwrap();
+
+        assert!(detect(binary.path(), binary.path(), &FileType::ElfBinary, None).is_none());
+    }
+}
@@ -0,0 +1,269 @@
+use log::{debug, trace};
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::Path;
+
+/// Largest binary checked for a multi-call signature; stripped uutils builds
+/// are 10 to 15 MiB, while BusyBox and toybox stay below 2 MiB
+const MAX_MULTI_CALL_SIZE: u64 = 16 * 1_048_576;
+/// Bytes read at a time while looking for a multi-call signature
+const SCAN_CHUNK_SIZE: usize = 65_536;
+
+const BUSYBOX_MARKER: &[u8] = b"BusyBox v";
+/// BusyBox keeps its applet names sorted in one NUL-separated table, which
+/// starts with the `[` and `[[` aliases of `test`
+const BUSYBOX_TABLE_START: &[u8] = b"[\0[[\0";
+const TOYBOX_MARKER: &[u8] = b"\0toybox\0";
+const COREUTILS_MARKER: &[u8] = b"--coreutils-prog=";
+const UUTILS_MARKER: &[u8] = b"(multi-call binary)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultiCallKind {
+    Busybox,
+    Toybox,
+    /// GNU coreutils built with `--enable-single-binary`
+    Coreutils,
+    /// uutils coreutils multicall binary
+    Uutils,
+}
+
+impl MultiCallKind {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Busybox => "busybox",
+            Self::Toybox => "toybox",
+            Self::Coreutils => "coreutils",
+            Self::Uutils => "uutils",
+        }
+    }
+}
+
+/// The applet a multi-call binary runs, selected by the name it was invoked as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Applet {
+    pub kind: MultiCallKind,
+    pub name: String,
+    /// Whether the binary's applet table contains `name`
+    pub compiled_in: bool,
+}
+
+/// Recognize `binary` as a multi-call binary invoked through `invoked`.
+///
+/// Nothing is reported when the binary is run under its own name, or when
+/// the names match up to wrapper suffixes, since no applet is selected then.
+#[must_use]
+pub fn detect(invoked: &Path, binary: &Path) -> Option<Applet> {
+    let name = invoked.file_name()?.to_str()?;
+    let binary_name = binary.file_name()?.to_str()?;
+    if super::programs_match(invoked, binary) {
+        return None;
+    }
+
+    let size = fs::metadata(binary).ok()?.len();
+    if size > MAX_MULTI_CALL_SIZE {
+        trace!("Skipping applet scan of large binary: {}", binary.display());
+        return None;
+    }
+
+    let kind = scan_kind(binary)?;
+    if name == kind.as_str() {
+        return None;
+    }
+    let content = fs::read(binary).ok()?;
+
+    let compiled_in = match kind {
+        MultiCallKind::Busybox => busybox_applets(&content).map_or_else(
+            || contains_token(&content, name),
+            |applets| applets.contains(&name),
+        ),
+        MultiCallKind::Toybox | MultiCallKind::Coreutils => contains_token(&content, name),
+        MultiCallKind::Uutils => contains(&content, format!("/uu/{name}/").as_bytes()),
+    };
+
+    debug!(
+        "{} ({binary_name}) invoked as applet {name}, compiled in: {compiled_in}",
+        kind.as_str()
+    );
+    Some(Applet {
+        kind,
+        name: name.to_string(),
+        compiled_in,
+    })
+}
+
+/// Look for a multi-call signature without loading the whole binary, reading
+/// it in chunks that overlap by enough bytes not to miss a marker split
+/// between two reads.
+fn scan_kind(binary: &Path) -> Option<MultiCallKind> {
+    let overlap = [
+        BUSYBOX_MARKER,
+        TOYBOX_MARKER,
+        COREUTILS_MARKER,
+        UUTILS_MARKER,
+    ]
+    .iter()
+    .map(|marker| marker.len() - 1)
+    .max()
+    .unwrap_or(0);
+
+    let mut file = File::open(binary).ok()?;
+    let mut window = Vec::with_capacity(overlap + SCAN_CHUNK_SIZE);
+    let mut chunk = vec![0u8; SCAN_CHUNK_SIZE];
+    loop {
+        let read = file.read(&mut chunk).ok()?;
+        if read == 0 {
+            return None;
+        }
+        window.extend_from_slice(&chunk[..read]);
+        if let Some(kind) = multi_call_kind(&window) {
+            return Some(kind);
+        }
+        window.drain(..window.len().saturating_sub(overlap));
+    }
+}
+
+fn multi_call_kind(content: &[u8]) -> Option<MultiCallKind> {
+    if contains(content, BUSYBOX_MARKER) {
+        Some(MultiCallKind::Busybox)
+    } else if contains(content, COREUTILS_MARKER) {
+        Some(MultiCallKind::Coreutils)
+    } else if contains(content, UUTILS_MARKER) {
+        Some(MultiCallKind::Uutils)
+    } else if contains(content, TOYBOX_MARKER) {
+        Some(MultiCallKind::Toybox)
+    } else {
+        None
+    }
+}
+
+/// Read BusyBox's applet name table, which ends where the names stop
+/// being sorted.
+fn busybox_applets(content: &[u8]) -> Option<Vec<&str>> {
+    let start = find(content, BUSYBOX_TABLE_START)?;
+    let mut applets: Vec<&str> = Vec::new();
+
+    for token in content[start..].split(|&b| b == 0) {
+        let Ok(name) = std::str::from_utf8(token) else {
+            break;
+        };
+        if name.is_empty() || applets.last().is_some_and(|last| *last >= name) {
+            break;
+        }
+        applets.push(name);
+    }
+
+    trace!("BusyBox applet table has {} entries", applets.len());
+    Some(applets)
+}
+
+/// Check for `name` as a NUL-terminated string of its own.
+fn contains_token(content: &[u8], name: &str) -> bool {
+    let mut token = vec![0];
+    token.extend(name.as_bytes());
+    token.push(0);
+    contains(content, &token)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    find(haystack, needle).is_some()
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    const ELF_HEADER: &[u8] = &[0x7f, b'E', b'L', b'F', 0x02, 0x01, 0x01, 0x00];
+
+    fn busybox(temp: &TempDir) -> std::path::PathBuf {
+        let mut content = ELF_HEADER.to_vec();
+        content.extend(b"\0BusyBox v1.36.1 multi-call binary\0");
+        content.extend(b"[\0[[\0cat\0ls\0sh\0\x01\x02");
+        content.extend(b"\0vi\0");
+        let file = temp.child("busybox");
+        file.write_binary(&content).unwrap();
+        file.to_path_buf()
+    }
+
+    #[test]
+    fn test_busybox_applet() {
+        let temp = TempDir::new().unwrap();
+        let binary = busybox(&temp);
+
+        let applet = detect(Path::new("/bin/ls"), &binary).unwrap();
+
+        assert_eq!(applet.kind, MultiCallKind::Busybox);
+        assert_eq!(applet.name, "ls");
+        assert!(applet.compiled_in);
+    }
+
+    #[test]
+    fn test_busybox_applet_outside_table() {
+        let temp = TempDir::new().unwrap();
+        let binary = busybox(&temp);
+
+        let applet = detect(Path::new("/bin/vi"), &binary).unwrap();
+
+        assert!(!applet.compiled_in);
+    }
+
+    #[test]
+    fn test_busybox_invoked_as_itself() {
+        let temp = TempDir::new().unwrap();
+        let binary = busybox(&temp);
+
+        assert!(detect(&binary, &binary).is_none());
+        assert!(detect(Path::new("/bin/busybox"), &binary).is_none());
+    }
+
+    #[test]
+    fn test_uutils_applet() {
+        let temp = TempDir::new().unwrap();
+        let mut content = ELF_HEADER.to_vec();
+        content.extend(b"coreutils (multi-call binary)\n");
+        content.extend(b"src/uu/ls/src/ls.rs");
+        let file = temp.child("coreutils");
+        file.write_binary(&content).unwrap();
+
+        let applet = detect(Path::new("/usr/bin/ls"), file.path()).unwrap();
+        assert_eq!(applet.kind, MultiCallKind::Uutils);
+        assert!(applet.compiled_in);
+
+        let applet = detect(Path::new("/usr/bin/stty"), file.path()).unwrap();
+        assert!(!applet.compiled_in);
+    }
+
+    #[test]
+    fn test_signature_across_chunks() {
+        let temp = TempDir::new().unwrap();
+        let mut content = ELF_HEADER.to_vec();
+        content.resize(SCAN_CHUNK_SIZE - 4, 0);
+        content.extend(b"BusyBox v1.36.1\0[\0[[\0ls\0");
+        let file = temp.child("busybox");
+        file.write_binary(&content).unwrap();
+
+        assert_eq!(scan_kind(file.path()), Some(MultiCallKind::Busybox));
+        assert!(
+            detect(Path::new("/bin/ls"), file.path())
+                .unwrap()
+                .compiled_in
+        );
+    }
+
+    #[test]
+    fn test_plain_binary() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.child("python3.12");
+        file.write_binary(ELF_HEADER).unwrap();
+
+        assert!(detect(Path::new("/usr/bin/python3"), file.path()).is_none());
+    }
+}
@@ -1,8 +1,7 @@
-use crate::core::detector::{MAX_FILE_SIZE, WrapperDetector, extract_strings_from_binary};
-use crate::error::{Result, SymseekError};
+use crate::core::detector::{WrapperDetector, root_path};
+use crate::core::store::Stores;
 use log::debug;
 use regex::Regex;
-use std::fs;
 use std::path::Path;
 use std::sync::LazyLock;
 
@@ -11,43 +10,37 @@ const DETECTOR_NAME: &str = "NixBinaryWrapperDetector";
 static MAKE_C_WRAPPER_PATH_REGEX: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"makeCWrapper\s+'([^']+)'").unwrap());
 
-pub struct NixBinaryWrapperDetector;
-
-impl WrapperDetector for NixBinaryWrapperDetector {
-    fn detect(&self, path: &Path) -> Result<Option<String>> {
-        debug!("{DETECTOR_NAME}: checking {}", path.display());
-
-        let metadata = fs::metadata(path).map_err(|e| SymseekError::Io {
-            context: format!("Failed to read metadata for {}", path.display()),
-            source: e,
-        })?;
-
-        debug!("{DETECTOR_NAME}: file size = {} bytes", metadata.len());
-
-        if metadata.len() > MAX_FILE_SIZE {
-            debug!("{DETECTOR_NAME}: file too large");
-            return Ok(None);
-        }
+pub struct NixBinaryWrapperDetector<'a> {
+    stores: &'a Stores,
+    /// Directory store paths in wrappers are relative to
+    root: Option<&'a Path>,
+}
 
-        let content_str = if let Ok(text) = fs::read_to_string(path) {
-            text
-        } else {
-            let bytes = fs::read(path).map_err(|e| SymseekError::Io {
-                context: format!("Failed to read file {}", path.display()),
-                source: e,
-            })?;
+impl<'a> NixBinaryWrapperDetector<'a> {
+    #[must_use]
+    pub const fn new(stores: &'a Stores) -> Self {
+        Self { stores, root: None }
+    }
 
-            extract_strings_from_binary(&bytes)
-        };
+    /// Look for wrapped programs below a root directory.
+    #[must_use]
+    pub const fn with_root(mut self, root: Option<&'a Path>) -> Self {
+        self.root = root;
+        self
+    }
+}
 
+impl WrapperDetector for NixBinaryWrapperDetector<'_> {
+    fn detect_in(&self, path: &Path, content_str: &str) -> Option<String> {
         debug!(
-            "{DETECTOR_NAME}: content length = {} chars",
+            "{DETECTOR_NAME}: checking {}, content length = {} chars",
+            path.display(),
             content_str.len()
         );
 
         if !content_str.contains("makeCWrapper") {
             debug!("{DETECTOR_NAME}: no makeCWrapper in content");
-            return Ok(None);
+            return None;
         }
 
         // Normalize content by removing backslash-newline continuations
@@ -60,20 +53,20 @@ impl WrapperDetector for NixBinaryWrapperDetector {
             let candidate_str = matched.as_str();
             debug!("{DETECTOR_NAME}: found makeCWrapper path: {candidate_str}");
 
-            // Only accept nix store paths as targets
-            if !candidate_str.contains("/nix/store/") {
-                debug!("{DETECTOR_NAME}: target is not a nix store path");
-                return Ok(None);
+            // Only accept store paths as targets
+            if !self.stores.contains(Path::new(candidate_str)) {
+                debug!("{DETECTOR_NAME}: target is not a store path");
+                return None;
             }
 
-            let candidate_path = Path::new(candidate_str);
+            let candidate_path = root_path(self.root, Path::new(candidate_str));
             if candidate_path != path {
                 debug!("{DETECTOR_NAME}: found target: {candidate_str}");
-                return Ok(Some(candidate_str.to_string()));
+                return Some(candidate_str.to_string());
             }
         }
 
         debug!("{DETECTOR_NAME}: no target path");
-        Ok(None)
+        None
     }
 }
@@ -1,75 +1,85 @@
-use crate::core::detector::{
-    NIX_STORE_PATH_REGEX, WrapperDetector, extract_strings_from_binary, programs_match,
-};
-use crate::error::{Result, SymseekError};
+use crate::core::detector::{WrapperDetector, programs_match, root_path};
+use crate::core::store::Stores;
 use log::{debug, trace};
 use std::fs;
 use std::path::Path;
 
 const DETECTOR_NAME: &str = "NixProgramNameDetector";
 
-const MAX_FILE_SIZE: u64 = 1_048_576;
-
-pub struct NixProgramNameDetector;
+/// Detects wrappers that reference a store path to a program of the same
+/// name, such as Nix's `.foo-wrapped` or Guix's `.foo-real`.
+pub struct NixProgramNameDetector<'a> {
+    stores: &'a Stores,
+    /// Directory the stores are relative to
+    root: Option<&'a Path>,
+}
 
-impl WrapperDetector for NixProgramNameDetector {
-    fn detect(&self, path: &Path) -> Result<Option<String>> {
-        let path_str = path.to_string_lossy();
-        trace!("{DETECTOR_NAME}: checking {path_str}");
+impl<'a> NixProgramNameDetector<'a> {
+    #[must_use]
+    pub const fn new(stores: &'a Stores) -> Self {
+        Self { stores, root: None }
+    }
 
-        if !path_str.contains("nix") {
-            trace!("{DETECTOR_NAME}: not a nix path, skipping");
-            return Ok(None);
-        }
+    /// Look for the stores below a root directory.
+    #[must_use]
+    pub const fn with_root(mut self, root: Option<&'a Path>) -> Self {
+        self.root = root;
+        self
+    }
 
-        let metadata = fs::metadata(path).map_err(|e| SymseekError::Io {
-            context: format!("Failed to read metadata for {}", path.display()),
-            source: e,
-        })?;
+    /// Wrappers live in the store, though they may be reached through a
+    /// symlinked directory such as a profile.
+    fn is_store_path(&self, path: &Path) -> bool {
+        let in_store = |path: &Path| match self.root {
+            Some(root) => path
+                .strip_prefix(root)
+                .is_ok_and(|relative| self.stores.contains(&Path::new("/").join(relative))),
+            None => self.stores.contains(path),
+        };
+        in_store(path) || fs::canonicalize(path).is_ok_and(|canonical| in_store(&canonical))
+    }
+}
 
-        if metadata.len() > MAX_FILE_SIZE {
-            trace!("{DETECTOR_NAME}: file too large");
-            return Ok(None);
+impl WrapperDetector for NixProgramNameDetector<'_> {
+    fn applies_to(&self, path: &Path) -> bool {
+        if self.is_store_path(path) {
+            return true;
         }
+        trace!(
+            "{DETECTOR_NAME}: {} is not a store path, skipping",
+            path.display()
+        );
+        false
+    }
 
-        let content_str = if let Ok(text) = fs::read_to_string(path) {
-            text
-        } else {
-            let bytes = fs::read(path).map_err(|e| SymseekError::Io {
-                context: format!("Failed to read file {}", path.display()),
-                source: e,
-            })?;
-
-            extract_strings_from_binary(&bytes)
-        };
+    fn detect_in(&self, path: &Path, content_str: &str) -> Option<String> {
+        trace!("{DETECTOR_NAME}: checking {}", path.display());
 
-        for caps in NIX_STORE_PATH_REGEX.captures_iter(&content_str) {
-            if let Some(matched) = caps.get(0) {
-                let mut candidate_str = matched.as_str();
-                while candidate_str.ends_with('"')
-                    || candidate_str.ends_with('\'')
-                    || candidate_str.ends_with('$')
-                {
-                    candidate_str = &candidate_str[..candidate_str.len() - 1];
-                }
+        for matched in self.stores.find_paths(content_str) {
+            let mut candidate_str = matched;
+            while candidate_str.ends_with('"')
+                || candidate_str.ends_with('\'')
+                || candidate_str.ends_with('$')
+            {
+                candidate_str = &candidate_str[..candidate_str.len() - 1];
+            }
 
-                let candidate_path = Path::new(candidate_str);
-                trace!("{DETECTOR_NAME}: found path in content: {candidate_str}");
+            let candidate_path = &root_path(self.root, Path::new(candidate_str));
+            trace!("{DETECTOR_NAME}: found path in content: {candidate_str}");
 
-                let names_match = programs_match(path, candidate_path);
-                let is_file = candidate_path.is_file();
-                let not_same = candidate_path != path;
+            let names_match = programs_match(path, candidate_path);
+            let is_file = candidate_path.is_file();
+            let not_same = candidate_path != path;
 
-                trace!("  names_match={names_match}, is_file={is_file}, not_same={not_same}");
+            trace!("  names_match={names_match}, is_file={is_file}, not_same={not_same}");
 
-                if names_match && is_file && not_same {
-                    debug!("{DETECTOR_NAME}: found matching path: {candidate_str}");
-                    return Ok(Some(candidate_str.to_string()));
-                }
+            if names_match && is_file && not_same {
+                debug!("{DETECTOR_NAME}: found matching path: {candidate_str}");
+                return Some(candidate_str.to_string());
             }
         }
 
         trace!("{DETECTOR_NAME}: no target path");
-        Ok(None)
+        None
     }
 }
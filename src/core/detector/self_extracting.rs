@@ -0,0 +1,347 @@
+use crate::core::detector::squashfs::{self, SQUASHFS_MAGIC};
+use log::{debug, trace};
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// AppImages mark their type right after the ELF identification bytes.
+const APPIMAGE_MAGIC_OFFSET: usize = 8;
+const APPIMAGE_MAGIC: &[u8] = b"AI";
+const UPX_MAGIC: &[u8] = b"UPX!";
+/// Cookie PyInstaller appends to the archive at the end of the bootloader
+const PYINSTALLER_COOKIE: &[u8] = b"MEI\x0c\x0b\x0a\x0b\x0e";
+/// Comment makeself writes right below the shebang of every archive
+const MAKESELF_HEADER: &[u8] = b"# This script was generated using Makeself";
+/// Helper functions defined by the makeself header script
+const MAKESELF_HELPERS: &[&[u8]] = &[b"MS_dd()", b"MS_Printf()", b"MS_Check()"];
+/// Bytes from the start of a script searched for the makeself helpers
+const MAKESELF_HEAD_SIZE: u64 = 16_384;
+/// Bytes from the end of the file searched for the PyInstaller cookie,
+/// leaving room for a code signature appended after it
+const TAIL_SIZE: u64 = 4096;
+
+const APPIMAGE_ENTRY_POINT: &str = "AppRun";
+const DESKTOP_FILE_SUFFIX: &str = ".desktop";
+
+const ELF_CLASS_32: u8 = 1;
+const ELF_CLASS_64: u8 = 2;
+const ELF_DATA_BIG_ENDIAN: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppImageType {
+    /// ISO 9660 image with the runtime in its system area
+    Type1,
+    /// Runtime ELF followed by a squashfs image
+    Type2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelfExtractingKind {
+    AppImage(AppImageType),
+    Makeself,
+    Upx,
+    PyInstaller,
+}
+
+/// Details of a self-extracting file, shown with its hop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleInfo {
+    pub kind: SelfExtractingKind,
+    /// Offset of the embedded squashfs image of a type 2 AppImage
+    pub payload_offset: Option<u64>,
+    /// File `AppRun` points to inside the AppImage, or `AppRun` itself
+    pub entry_point: Option<String>,
+    /// Desktop file at the root of the AppImage
+    pub desktop_file: Option<String>,
+}
+
+/// Recognize a self-extracting ELF binary from the head of the file.
+///
+/// The tail is only read for the PyInstaller cookie when the head does not
+/// already identify the file.
+#[must_use]
+pub fn detect_elf(path: &Path, head: &[u8]) -> Option<SelfExtractingKind> {
+    let magic_end = APPIMAGE_MAGIC_OFFSET + APPIMAGE_MAGIC.len();
+    if head.get(APPIMAGE_MAGIC_OFFSET..magic_end) == Some(APPIMAGE_MAGIC) {
+        match head.get(magic_end) {
+            Some(1) => return Some(SelfExtractingKind::AppImage(AppImageType::Type1)),
+            Some(2) => return Some(SelfExtractingKind::AppImage(AppImageType::Type2)),
+            _ => {}
+        }
+    }
+
+    if contains(head, UPX_MAGIC) {
+        return Some(SelfExtractingKind::Upx);
+    }
+
+    read_tail(path)
+        .is_some_and(|tail| contains(&tail, PYINSTALLER_COOKIE))
+        .then_some(SelfExtractingKind::PyInstaller)
+}
+
+/// Recognize a makeself archive from the head of a shell script.
+///
+/// The line after the shebang must be the comment makeself generates, and
+/// the header script's `MS_` helpers must be defined further down, so that
+/// scripts merely mentioning makeself are not taken for archives.
+#[must_use]
+pub fn detect_script(path: &Path, head: &[u8]) -> Option<SelfExtractingKind> {
+    let second_line = head.split(|&b| b == b'\n').nth(1)?;
+    if !second_line.starts_with(MAKESELF_HEADER) {
+        return None;
+    }
+
+    read_head(path, MAKESELF_HEAD_SIZE)
+        .is_some_and(|head| {
+            MAKESELF_HELPERS
+                .iter()
+                .any(|helper| contains(&head, helper))
+        })
+        .then_some(SelfExtractingKind::Makeself)
+}
+
+/// Gather the details of a self-extracting file.
+///
+/// For type 2 AppImages the embedded squashfs image is located after the
+/// runtime and its root directory listed, without mounting it.
+#[must_use]
+pub fn inspect(path: &Path, kind: SelfExtractingKind) -> BundleInfo {
+    let mut info = BundleInfo {
+        kind,
+        payload_offset: None,
+        entry_point: None,
+        desktop_file: None,
+    };
+    if kind != SelfExtractingKind::AppImage(AppImageType::Type2) {
+        return info;
+    }
+
+    let Some(offset) = squashfs_offset(path) else {
+        debug!("No squashfs image found in {}", path.display());
+        return info;
+    };
+    info.payload_offset = Some(offset);
+
+    match squashfs::root_entries(path, offset) {
+        Ok(entries) => {
+            info.entry_point = entries
+                .iter()
+                .find(|entry| entry.name == APPIMAGE_ENTRY_POINT)
+                .map(|entry| {
+                    entry
+                        .symlink_target
+                        .clone()
+                        .unwrap_or_else(|| APPIMAGE_ENTRY_POINT.to_string())
+                });
+            info.desktop_file = entries
+                .into_iter()
+                .find(|entry| entry.name.ends_with(DESKTOP_FILE_SUFFIX))
+                .map(|entry| entry.name);
+        }
+        Err(e) => debug!("Failed to list AppImage contents: {e}"),
+    }
+
+    trace!("AppImage {}: {info:?}", path.display());
+    info
+}
+
+/// Find the squashfs image appended to an AppImage runtime, which starts
+/// where the runtime's section header table ends.
+fn squashfs_offset(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 64];
+    file.read_exact(&mut header).ok()?;
+
+    let offset = elf_end(&header)?;
+    let mut magic = [0u8; 4];
+    file.seek(SeekFrom::Start(offset)).ok()?;
+    file.read_exact(&mut magic).ok()?;
+    (magic == SQUASHFS_MAGIC).then_some(offset)
+}
+
+/// Compute `e_shoff + e_shentsize * e_shnum` from an ELF header.
+fn elf_end(header: &[u8; 64]) -> Option<u64> {
+    let big_endian = header[5] == ELF_DATA_BIG_ENDIAN;
+    let u16_at = |pos: usize| {
+        let bytes = [header[pos], header[pos + 1]];
+        u64::from(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+
+    let (shoff, fields) = match header[4] {
+        ELF_CLASS_32 => {
+            let bytes = header[0x20..0x24].try_into().ok()?;
+            let shoff = if big_endian {
+                u32::from_be_bytes(bytes)
+            } else {
+                u32::from_le_bytes(bytes)
+            };
+            (u64::from(shoff), 0x2e)
+        }
+        ELF_CLASS_64 => {
+            let bytes = header[0x28..0x30].try_into().ok()?;
+            let shoff = if big_endian {
+                u64::from_be_bytes(bytes)
+            } else {
+                u64::from_le_bytes(bytes)
+            };
+            (shoff, 0x3a)
+        }
+        _ => return None,
+    };
+
+    shoff.checked_add(u16_at(fields) * u16_at(fields + 2))
+}
+
+fn read_head(path: &Path, len: u64) -> Option<Vec<u8>> {
+    let mut head = Vec::new();
+    File::open(path)
+        .ok()?
+        .take(len)
+        .read_to_end(&mut head)
+        .ok()?;
+    Some(head)
+}
+
+fn read_tail(path: &Path) -> Option<Vec<u8>> {
+    let mut file = File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_SIZE)))
+        .ok()?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).ok()?;
+    Some(tail)
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack
+        .windows(needle.len())
+        .any(|window| window == needle)
+}
+
+#[cfg(test)]
+pub mod test_appimage {
+    use crate::core::detector::squashfs::test_image;
+
+    /// Build a 64-bit little-endian type 2 AppImage whose runtime's section
+    /// headers end at `runtime_len`, followed by a squashfs image.
+    pub fn build(runtime_len: u64, entries: &[test_image::Entry<'_>]) -> Vec<u8> {
+        let shentsize: u16 = 64;
+        let shnum: u16 = 4;
+        let shoff = runtime_len - u64::from(shentsize * shnum);
+
+        let mut content = vec![0u8; usize::try_from(runtime_len).unwrap()];
+        content[0..4].copy_from_slice(&[0x7f, b'E', b'L', b'F']);
+        content[4] = 2;
+        content[5] = 1;
+        content[8..11].copy_from_slice(b"AI\x02");
+        content[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+        content[0x3a..0x3c].copy_from_slice(&shentsize.to_le_bytes());
+        content[0x3c..0x3e].copy_from_slice(&shnum.to_le_bytes());
+        content.extend(test_image::build(entries, true));
+        content
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn test_inspect_appimage() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.child("Hello.AppImage");
+        file.write_binary(&test_appimage::build(
+            4096,
+            &[
+                ("AppRun", Some("usr/bin/hello")),
+                ("hello.desktop", None),
+                ("usr", None),
+            ],
+        ))
+        .unwrap();
+
+        let info = inspect(
+            file.path(),
+            SelfExtractingKind::AppImage(AppImageType::Type2),
+        );
+
+        assert_eq!(info.payload_offset, Some(4096));
+        assert_eq!(info.entry_point.as_deref(), Some("usr/bin/hello"));
+        assert_eq!(info.desktop_file.as_deref(), Some("hello.desktop"));
+    }
+
+    #[test]
+    fn test_inspect_appimage_with_apprun_file() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.child("Hello.AppImage");
+        file.write_binary(&test_appimage::build(2048, &[("AppRun", None)]))
+            .unwrap();
+
+        let info = inspect(
+            file.path(),
+            SelfExtractingKind::AppImage(AppImageType::Type2),
+        );
+
+        assert_eq!(info.entry_point.as_deref(), Some("AppRun"));
+        assert_eq!(info.desktop_file, None);
+    }
+
+    #[test]
+    fn test_elf_end_32_bit_big_endian() {
+        let mut header = [0u8; 64];
+        header[4] = ELF_CLASS_32;
+        header[5] = ELF_DATA_BIG_ENDIAN;
+        header[0x20..0x24].copy_from_slice(&1000u32.to_be_bytes());
+        header[0x2e..0x30].copy_from_slice(&40u16.to_be_bytes());
+        header[0x30..0x32].copy_from_slice(&3u16.to_be_bytes());
+
+        assert_eq!(elf_end(&header), Some(1120));
+    }
+
+    #[test]
+    fn test_detect_pyinstaller_cookie_in_tail() {
+        let temp = TempDir::new().unwrap();
+        let mut content = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0];
+        content.resize(10_000, 0);
+        content.extend(PYINSTALLER_COOKIE);
+        content.extend([0u8; 80]);
+        let file = temp.child("bundle");
+        file.write_binary(&content).unwrap();
+
+        assert_eq!(
+            detect_elf(file.path(), &content[..512]),
+            Some(SelfExtractingKind::PyInstaller)
+        );
+    }
+
+    #[test]
+    fn test_detect_makeself_requires_header_and_helpers() {
+        let temp = TempDir::new().unwrap();
+        let header = "#!/bin/sh\n# This script was generated using Makeself 2.5.0\n";
+        let archive = format!("{header}{}MS_Printf()\n{{\n}}\n", "#\n".repeat(400));
+        let cases = [
+            (archive.as_str(), Some(SelfExtractingKind::Makeself)),
+            (header, None),
+            (
+                "#!/bin/sh\n# Repackage the Makeself archive\nMS_Printf()\n",
+                None,
+            ),
+        ];
+
+        for (idx, (script, expected)) in cases.into_iter().enumerate() {
+            let file = temp.child(format!("script{idx}"));
+            file.write_str(script).unwrap();
+            assert_eq!(
+                detect_script(file.path(), &script.as_bytes()[..script.len().min(512)]),
+                expected,
+                "{script:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,485 @@
+//! Minimal read-only squashfs 4.0 reader, enough to list the root directory
+//! of an image embedded in a larger file such as an AppImage.
+
+use crate::error::{Result, SymseekError};
+use flate2::read::ZlibDecoder;
+use log::trace;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const SQUASHFS_MAGIC: &[u8] = b"hsqs";
+
+const SUPERBLOCK_SIZE: usize = 96;
+const METADATA_BLOCK_SIZE: usize = 8192;
+const METADATA_UNCOMPRESSED: u16 = 0x8000;
+const INODE_HEADER_SIZE: usize = 16;
+/// Extended directory inode up to the fields needed to find its listing
+const EXTENDED_DIR_INODE_SIZE: usize = INODE_HEADER_SIZE + 20;
+/// Symlink inode up to the start of the target
+const SYMLINK_INODE_SIZE: usize = INODE_HEADER_SIZE + 8;
+/// Directory sizes include three bytes for the implicit `.` and `..`
+const DIRECTORY_SIZE_OFFSET: usize = 3;
+/// Upper bound on the metadata read for one directory listing
+const MAX_METADATA_READ: usize = 1_048_576;
+
+const COMPRESSION_GZIP: u16 = 1;
+const COMPRESSION_ZSTD: u16 = 6;
+
+const INODE_BASIC_DIR: u16 = 1;
+const INODE_BASIC_SYMLINK: u16 = 3;
+const INODE_EXTENDED_DIR: u16 = 8;
+const INODE_EXTENDED_SYMLINK: u16 = 10;
+
+/// An entry of the root directory of a squashfs image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SquashfsEntry {
+    pub name: String,
+    /// Target of the entry, if it is a symlink
+    pub symlink_target: Option<String>,
+}
+
+struct Superblock {
+    compression: u16,
+    root_inode: u64,
+    inode_table: u64,
+    directory_table: u64,
+}
+
+/// Reader over an image starting at `offset` bytes into `file`.
+struct Image<'a> {
+    file: &'a mut File,
+    offset: u64,
+    compression: u16,
+}
+
+/// List the root directory of the squashfs image at `offset` in `path`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, if there is no squashfs
+/// image at `offset`, or if it uses a compression other than gzip or zstd.
+pub fn root_entries(path: &Path, offset: u64) -> Result<Vec<SquashfsEntry>> {
+    let mut file = File::open(path).map_err(|e| SymseekError::Io {
+        context: format!("Failed to open {}", path.display()),
+        source: e,
+    })?;
+
+    let mut raw = [0u8; SUPERBLOCK_SIZE];
+    file.seek(SeekFrom::Start(offset))
+        .and_then(|_| file.read_exact(&mut raw))
+        .map_err(|e| read_error(path, e))?;
+    let superblock =
+        parse_superblock(&raw).ok_or_else(|| invalid(path, "no squashfs superblock"))?;
+    if !matches!(superblock.compression, COMPRESSION_GZIP | COMPRESSION_ZSTD) {
+        return Err(invalid(
+            path,
+            &format!("unsupported compression {}", superblock.compression),
+        ));
+    }
+
+    let mut image = Image {
+        file: &mut file,
+        offset,
+        compression: superblock.compression,
+    };
+    image
+        .root_entries(&superblock)
+        .map_err(|e| read_error(path, e))?
+        .ok_or_else(|| invalid(path, "malformed root directory"))
+}
+
+fn parse_superblock(raw: &[u8; SUPERBLOCK_SIZE]) -> Option<Superblock> {
+    if &raw[0..4] != SQUASHFS_MAGIC || u16_at(raw, 28)? != 4 {
+        return None;
+    }
+    Some(Superblock {
+        compression: u16_at(raw, 20)?,
+        root_inode: u64_at(raw, 32)?,
+        inode_table: u64_at(raw, 64)?,
+        directory_table: u64_at(raw, 72)?,
+    })
+}
+
+impl Image<'_> {
+    fn root_entries(
+        &mut self,
+        superblock: &Superblock,
+    ) -> std::io::Result<Option<Vec<SquashfsEntry>>> {
+        let root = self.read_inode(
+            superblock.inode_table,
+            superblock.root_inode,
+            EXTENDED_DIR_INODE_SIZE,
+        )?;
+        let Some((block_start, block_offset, size)) = directory_location(&root) else {
+            return Ok(None);
+        };
+        trace!("squashfs root directory: block {block_start}, offset {block_offset}, size {size}");
+
+        let listing_size = size
+            .saturating_sub(DIRECTORY_SIZE_OFFSET)
+            .min(MAX_METADATA_READ);
+        let listing = self.read_metadata(
+            checked_offset(superblock.directory_table, block_start)?,
+            block_offset,
+            listing_size,
+        )?;
+
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while pos + 12 <= listing.len() {
+            let (Some(count), Some(inode_block)) =
+                (u32_at(&listing, pos), u32_at(&listing, pos + 4))
+            else {
+                return Ok(None);
+            };
+            pos += 12;
+
+            for _ in 0..=count {
+                let (Some(inode_offset), Some(name_size)) =
+                    (u16_at(&listing, pos), u16_at(&listing, pos + 6))
+                else {
+                    return Ok(None);
+                };
+                let name_start = pos + 8;
+                let name_end = name_start + usize::from(name_size) + 1;
+                let Some(name) = listing.get(name_start..name_end) else {
+                    return Ok(None);
+                };
+                pos = name_end;
+
+                let inode_ref = (u64::from(inode_block) << 16) | u64::from(inode_offset);
+                entries.push(SquashfsEntry {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    symlink_target: self.read_symlink(superblock.inode_table, inode_ref)?,
+                });
+            }
+        }
+
+        Ok(Some(entries))
+    }
+
+    fn read_symlink(
+        &mut self,
+        inode_table: u64,
+        inode_ref: u64,
+    ) -> std::io::Result<Option<String>> {
+        let inode = self.read_inode(inode_table, inode_ref, SYMLINK_INODE_SIZE)?;
+        if !matches!(
+            u16_at(&inode, 0),
+            Some(INODE_BASIC_SYMLINK | INODE_EXTENDED_SYMLINK)
+        ) {
+            return Ok(None);
+        }
+        let Some(size) =
+            u32_at(&inode, SYMLINK_INODE_SIZE - 4).and_then(|s| usize::try_from(s).ok())
+        else {
+            return Ok(None);
+        };
+
+        let inode = self.read_inode(inode_table, inode_ref, SYMLINK_INODE_SIZE + size)?;
+        Ok(inode
+            .get(SYMLINK_INODE_SIZE..)
+            .map(|target| String::from_utf8_lossy(target).into_owned()))
+    }
+
+    /// Read up to `len` bytes of the inode referenced by `inode_ref`, which
+    /// packs the metadata block position above the offset inside it.
+    fn read_inode(
+        &mut self,
+        inode_table: u64,
+        inode_ref: u64,
+        len: usize,
+    ) -> std::io::Result<Vec<u8>> {
+        let block = inode_ref >> 16;
+        let offset = usize::try_from(inode_ref & 0xffff).unwrap_or(0);
+        self.read_metadata(checked_offset(inode_table, block)?, offset, len)
+    }
+
+    /// Read `len` bytes starting `offset` bytes into the metadata block at
+    /// `start`, continuing into the following blocks as needed.
+    ///
+    /// Fewer bytes are returned if the image ends first.
+    fn read_metadata(&mut self, start: u64, offset: usize, len: usize) -> std::io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        let mut pos = checked_offset(self.offset, start)?;
+
+        while data.len() < offset + len {
+            let mut header = [0u8; 2];
+            self.file.seek(SeekFrom::Start(pos))?;
+            if self.file.read_exact(&mut header).is_err() {
+                break;
+            }
+            let header = u16::from_le_bytes(header);
+            let size = usize::from(header & !METADATA_UNCOMPRESSED);
+            if size == 0 || size > METADATA_BLOCK_SIZE {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("invalid metadata block size {size}"),
+                ));
+            }
+
+            let mut block = vec![0u8; size];
+            self.file.read_exact(&mut block)?;
+            if header & METADATA_UNCOMPRESSED == 0 {
+                block = self.decompress(&block)?;
+            }
+            data.extend_from_slice(&block);
+            pos = checked_offset(pos, 2 + size as u64)?;
+        }
+
+        let end = data.len().min(offset + len);
+        Ok(data
+            .get(offset..end)
+            .map(<[u8]>::to_vec)
+            .unwrap_or_default())
+    }
+
+    fn decompress(&self, block: &[u8]) -> std::io::Result<Vec<u8>> {
+        let mut out = Vec::with_capacity(METADATA_BLOCK_SIZE);
+        match self.compression {
+            COMPRESSION_GZIP => {
+                ZlibDecoder::new(block).read_to_end(&mut out)?;
+            }
+            _ => {
+                ruzstd::decoding::StreamingDecoder::new(block)
+                    .map_err(std::io::Error::other)?
+                    .read_to_end(&mut out)?;
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Get the directory table block, offset and size of a directory inode.
+fn directory_location(inode: &[u8]) -> Option<(u64, usize, usize)> {
+    let body = INODE_HEADER_SIZE;
+    match u16_at(inode, 0)? {
+        INODE_BASIC_DIR => Some((
+            u64::from(u32_at(inode, body)?),
+            usize::from(u16_at(inode, body + 10)?),
+            usize::from(u16_at(inode, body + 8)?),
+        )),
+        INODE_EXTENDED_DIR => Some((
+            u64::from(u32_at(inode, body + 8)?),
+            usize::from(u16_at(inode, body + 18)?),
+            usize::try_from(u32_at(inode, body + 4)?).ok()?,
+        )),
+        _ => None,
+    }
+}
+
+fn u16_at(data: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+fn u64_at(data: &[u8], pos: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(data.get(pos..pos + 8)?.try_into().ok()?))
+}
+
+/// Add an offset read from the image to a position, rejecting images whose
+/// offsets overflow instead of wrapping around.
+fn checked_offset(base: u64, offset: u64) -> std::io::Result<u64> {
+    base.checked_add(offset).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("metadata offset {offset} overflows from {base}"),
+        )
+    })
+}
+
+fn read_error(path: &Path, e: std::io::Error) -> SymseekError {
+    SymseekError::Io {
+        context: format!("Failed to read squashfs image in {}", path.display()),
+        source: e,
+    }
+}
+
+fn invalid(path: &Path, reason: &str) -> SymseekError {
+    SymseekError::InvalidInput {
+        message: format!("Invalid squashfs image in {}: {reason}", path.display()),
+    }
+}
+
+/// Builders for small squashfs images, shared with the AppImage tests.
+#[cfg(test)]
+pub mod test_image {
+    use super::*;
+    use flate2::Compression;
+    use flate2::write::ZlibEncoder;
+    use std::io::Write;
+
+    /// A root directory entry: name and symlink target, `None` for a file.
+    pub type Entry<'a> = (&'a str, Option<&'a str>);
+
+    fn metadata_block(data: &[u8], compress: bool) -> Vec<u8> {
+        let (header, payload) = if compress {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(data).unwrap();
+            let payload = encoder.finish().unwrap();
+            (u16::try_from(payload.len()).unwrap(), payload)
+        } else {
+            (
+                u16::try_from(data.len()).unwrap() | METADATA_UNCOMPRESSED,
+                data.to_vec(),
+            )
+        };
+        let mut block = header.to_le_bytes().to_vec();
+        block.extend(payload);
+        block
+    }
+
+    fn inode_header(inode_type: u16, number: u32) -> Vec<u8> {
+        let mut header = Vec::new();
+        header.extend(inode_type.to_le_bytes());
+        header.extend(0o755u16.to_le_bytes());
+        header.extend([0u8; 8]);
+        header.extend(number.to_le_bytes());
+        header
+    }
+
+    /// Build a gzip squashfs image whose root directory holds `entries`,
+    /// all stored in a single metadata block per table.
+    #[must_use]
+    pub fn build(entries: &[Entry<'_>], compress: bool) -> Vec<u8> {
+        let mut inodes = Vec::new();
+        let mut listing = Vec::new();
+
+        // Directory header for all entries, inodes in block 0
+        listing.extend(u32::try_from(entries.len() - 1).unwrap().to_le_bytes());
+        listing.extend(0u32.to_le_bytes());
+        listing.extend(2u32.to_le_bytes());
+
+        for (idx, (name, target)) in entries.iter().enumerate() {
+            let number = u32::try_from(idx).unwrap() + 2;
+            let offset = u16::try_from(inodes.len()).unwrap();
+            match target {
+                Some(target) => {
+                    inodes.extend(inode_header(INODE_BASIC_SYMLINK, number));
+                    inodes.extend(1u32.to_le_bytes());
+                    inodes.extend(u32::try_from(target.len()).unwrap().to_le_bytes());
+                    inodes.extend(target.as_bytes());
+                }
+                None => {
+                    inodes.extend(inode_header(2, number));
+                    inodes.extend([0u8; 16]);
+                }
+            }
+            listing.extend(offset.to_le_bytes());
+            listing.extend(0i16.to_le_bytes());
+            listing.extend(if target.is_some() { 3u16 } else { 2u16 }.to_le_bytes());
+            listing.extend(u16::try_from(name.len() - 1).unwrap().to_le_bytes());
+            listing.extend(name.as_bytes());
+        }
+
+        let root_offset = u16::try_from(inodes.len()).unwrap();
+        inodes.extend(inode_header(INODE_BASIC_DIR, 1));
+        inodes.extend(0u32.to_le_bytes());
+        inodes.extend(2u32.to_le_bytes());
+        inodes.extend(
+            u16::try_from(listing.len() + DIRECTORY_SIZE_OFFSET)
+                .unwrap()
+                .to_le_bytes(),
+        );
+        inodes.extend(0u16.to_le_bytes());
+        inodes.extend(1u32.to_le_bytes());
+
+        let inode_table = metadata_block(&inodes, compress);
+        let directory_table = metadata_block(&listing, compress);
+        let inode_start = SUPERBLOCK_SIZE as u64;
+        let directory_start = inode_start + inode_table.len() as u64;
+
+        let mut image = vec![0u8; SUPERBLOCK_SIZE];
+        image[0..4].copy_from_slice(SQUASHFS_MAGIC);
+        image[20..22].copy_from_slice(&COMPRESSION_GZIP.to_le_bytes());
+        image[28..30].copy_from_slice(&4u16.to_le_bytes());
+        image[32..40].copy_from_slice(&u64::from(root_offset).to_le_bytes());
+        image[64..72].copy_from_slice(&inode_start.to_le_bytes());
+        image[72..80].copy_from_slice(&directory_start.to_le_bytes());
+        image.extend(inode_table);
+        image.extend(directory_table);
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    const ENTRIES: &[test_image::Entry<'_>] = &[
+        ("AppRun", Some("usr/bin/hello")),
+        ("hello.desktop", None),
+        ("usr", None),
+    ];
+
+    fn expected() -> Vec<SquashfsEntry> {
+        vec![
+            SquashfsEntry {
+                name: "AppRun".to_string(),
+                symlink_target: Some("usr/bin/hello".to_string()),
+            },
+            SquashfsEntry {
+                name: "hello.desktop".to_string(),
+                symlink_target: None,
+            },
+            SquashfsEntry {
+                name: "usr".to_string(),
+                symlink_target: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_root_entries_at_offset() {
+        let temp = TempDir::new().unwrap();
+        let mut content = vec![0xaa; 1000];
+        content.extend(test_image::build(ENTRIES, false));
+        let file = temp.child("image");
+        file.write_binary(&content).unwrap();
+
+        assert_eq!(root_entries(file.path(), 1000).unwrap(), expected());
+    }
+
+    #[test]
+    fn test_root_entries_compressed() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.child("image");
+        file.write_binary(&test_image::build(ENTRIES, true))
+            .unwrap();
+
+        assert_eq!(root_entries(file.path(), 0).unwrap(), expected());
+    }
+
+    #[test]
+    fn test_root_entries_rejects_missing_image() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.child("image");
+        file.write_binary(&[0u8; 200]).unwrap();
+
+        assert!(matches!(
+            root_entries(file.path(), 0),
+            Err(SymseekError::InvalidInput { .. })
+        ));
+    }
+
+    #[test]
+    fn test_root_entries_rejects_overflowing_offset() {
+        let temp = TempDir::new().unwrap();
+        let mut image = vec![0xaa];
+        image.extend(test_image::build(ENTRIES, false));
+        image[65..73].copy_from_slice(&u64::MAX.to_le_bytes());
+        let file = temp.child("image");
+        file.write_binary(&image).unwrap();
+
+        let Err(SymseekError::Io { source, .. }) = root_entries(file.path(), 1) else {
+            panic!("expected an I/O error");
+        };
+        assert_eq!(source.kind(), std::io::ErrorKind::InvalidData);
+    }
+}
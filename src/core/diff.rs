@@ -0,0 +1,292 @@
+use crate::core::store::Stores;
+use crate::core::types::{FileKind, LinkType, SymlinkChain, WrapperKind};
+use std::path::{Path, PathBuf};
+
+/// A single difference between two resolutions of the same target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// A hop only present in the newer resolution
+    HopAdded(PathBuf),
+    /// A hop only present in the older resolution
+    HopRemoved(PathBuf),
+    /// The same file is now provided by a different store path
+    StorePathChanged { before: PathBuf, after: PathBuf },
+    /// A hop is still a wrapper, but of a different kind
+    WrapperKindChanged {
+        path: PathBuf,
+        before: WrapperKind,
+        after: WrapperKind,
+    },
+    /// The final file of the chain changed kind
+    TerminalKindChanged { before: FileKind, after: FileKind },
+}
+
+/// Differences found for one target present in both resolutions.
+#[derive(Debug, Clone)]
+pub struct ChainDiff {
+    pub origin: PathBuf,
+    pub changes: Vec<Change>,
+}
+
+/// Differences between two sets of resolutions.
+#[derive(Debug, Clone, Default)]
+pub struct DiffReport {
+    /// Targets only resolved in the newer set
+    pub added: Vec<PathBuf>,
+    /// Targets only resolved in the older set
+    pub removed: Vec<PathBuf>,
+    /// Targets that now resolve differently
+    pub changed: Vec<ChainDiff>,
+}
+
+impl DiffReport {
+    /// Check whether both sets resolve identically.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare two sets of resolutions, matching chains by their origin. Hops
+/// are recognized as store paths of the given stores.
+#[must_use]
+pub fn diff_chains(before: &[SymlinkChain], after: &[SymlinkChain], stores: &Stores) -> DiffReport {
+    let mut report = DiffReport::default();
+
+    for old in before {
+        match after.iter().find(|new| new.origin == old.origin) {
+            Some(new) => {
+                let changes = diff_chain(old, new, stores);
+                if !changes.is_empty() {
+                    report.changed.push(ChainDiff {
+                        origin: old.origin.clone(),
+                        changes,
+                    });
+                }
+            }
+            None => report.removed.push(old.origin.clone()),
+        }
+    }
+
+    for new in after {
+        if !before.iter().any(|old| old.origin == new.origin) {
+            report.added.push(new.origin.clone());
+        }
+    }
+
+    report
+}
+
+/// Compare two resolutions of the same target.
+///
+/// Hops are paired by path, except for hops inside the Nix store which are
+/// paired by package name and path relative to the store entry, ignoring
+/// hash and version, so that an upgraded package shows up as a changed store
+/// path rather than a removed and an added hop.
+#[must_use]
+pub fn diff_chain(before: &SymlinkChain, after: &SymlinkChain, stores: &Stores) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut unmatched: Vec<_> = after.links.iter().collect();
+
+    for old in &before.links {
+        let key = hop_key(&old.target, stores);
+        let Some(idx) = unmatched
+            .iter()
+            .position(|new| hop_key(&new.target, stores) == key)
+        else {
+            changes.push(Change::HopRemoved(old.target.clone()));
+            continue;
+        };
+        let new = unmatched.remove(idx);
+
+        if old.target != new.target {
+            changes.push(Change::StorePathChanged {
+                before: old.target.clone(),
+                after: new.target.clone(),
+            });
+        }
+
+        if let (LinkType::Wrapper(old_kind), LinkType::Wrapper(new_kind)) =
+            (&old.link_type, &new.link_type)
+            && old_kind != new_kind
+        {
+            changes.push(Change::WrapperKindChanged {
+                path: new.target.clone(),
+                before: old_kind.clone(),
+                after: new_kind.clone(),
+            });
+        }
+    }
+
+    changes.extend(
+        unmatched
+            .into_iter()
+            .map(|new| Change::HopAdded(new.target.clone())),
+    );
+
+    if let (Some(LinkType::Terminal(old_kind)), Some(LinkType::Terminal(new_kind))) = (
+        before.links.last().map(|node| &node.link_type),
+        after.links.last().map(|node| &node.link_type),
+    ) && old_kind != new_kind
+    {
+        changes.push(Change::TerminalKindChanged {
+            before: old_kind.clone(),
+            after: new_kind.clone(),
+        });
+    }
+
+    changes
+}
+
+fn hop_key(path: &Path, stores: &Stores) -> String {
+    stores.parse(path).map_or_else(
+        || path.to_string_lossy().into_owned(),
+        |store_path| {
+            format!(
+                "{}/*-{}/{}",
+                store_path.store_dir.display(),
+                store_path.name,
+                store_path.relative.display()
+            )
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::ScriptType;
+
+    fn chain(origin: &str, links: &[(&str, LinkType)]) -> SymlinkChain {
+        let mut chain = SymlinkChain::new(PathBuf::from(origin));
+        for (idx, (path, link_type)) in links.iter().enumerate() {
+            chain.add_link(
+                PathBuf::from(path),
+                idx == links.len() - 1,
+                link_type.clone(),
+            );
+        }
+        chain
+    }
+
+    #[test]
+    fn test_diff_identical_chains() {
+        let before = chain(
+            "/usr/bin/nvim",
+            &[(
+                "/nix/store/aaa-nvim/bin/nvim",
+                LinkType::Terminal(FileKind::Binary),
+            )],
+        );
+
+        let chains = vec![before];
+        let report = diff_chains(&chains, &chains, &Stores::default());
+        assert!(report.is_empty());
+    }
+
+    #[test]
+    fn test_diff_added_and_removed_targets() {
+        let report = diff_chains(
+            &[chain("/bin/a", &[])],
+            &[chain("/bin/b", &[])],
+            &Stores::default(),
+        );
+
+        assert_eq!(report.removed, vec![PathBuf::from("/bin/a")]);
+        assert_eq!(report.added, vec![PathBuf::from("/bin/b")]);
+        assert!(report.changed.is_empty());
+    }
+
+    #[test]
+    fn test_diff_store_path_changed() {
+        let before = chain(
+            "/run/current-system/sw/bin/qs",
+            &[(
+                "/nix/store/aaa-quickshell-0.2.0/bin/qs",
+                LinkType::Terminal(FileKind::Binary),
+            )],
+        );
+        let after = chain(
+            "/run/current-system/sw/bin/qs",
+            &[(
+                "/nix/store/bbb-quickshell-0.2.1/bin/qs",
+                LinkType::Terminal(FileKind::Binary),
+            )],
+        );
+
+        let changes = diff_chain(&before, &after, &Stores::default());
+        assert_eq!(
+            changes,
+            vec![Change::StorePathChanged {
+                before: PathBuf::from("/nix/store/aaa-quickshell-0.2.0/bin/qs"),
+                after: PathBuf::from("/nix/store/bbb-quickshell-0.2.1/bin/qs"),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_diff_store_path_changed_in_relocated_store() {
+        let before = chain(
+            "/bin/qs",
+            &[(
+                "/home/u/nix/store/aaa-quickshell-0.2.0/bin/qs",
+                LinkType::Terminal(FileKind::Binary),
+            )],
+        );
+        let after = chain(
+            "/bin/qs",
+            &[(
+                "/home/u/nix/store/bbb-quickshell-0.2.1/bin/qs",
+                LinkType::Terminal(FileKind::Binary),
+            )],
+        );
+
+        assert_eq!(diff_chain(&before, &after, &Stores::default()).len(), 2);
+        let stores = Stores::default().with_nix_store_dir("/home/u/nix/store");
+        assert!(matches!(
+            diff_chain(&before, &after, &stores)[..],
+            [Change::StorePathChanged { .. }]
+        ));
+    }
+
+    #[test]
+    fn test_diff_hops_and_kinds() {
+        let before = chain(
+            "/bin/tool",
+            &[
+                (
+                    "/opt/tool-wrapper",
+                    LinkType::Wrapper(WrapperKind::Text(ScriptType::Shell)),
+                ),
+                ("/opt/old-link", LinkType::Symlink),
+                ("/opt/tool", LinkType::Terminal(FileKind::Text)),
+            ],
+        );
+        let after = chain(
+            "/bin/tool",
+            &[
+                ("/opt/tool-wrapper", LinkType::Wrapper(WrapperKind::Binary)),
+                ("/opt/new-link", LinkType::Symlink),
+                ("/opt/tool", LinkType::Terminal(FileKind::Binary)),
+            ],
+        );
+
+        let changes = diff_chain(&before, &after, &Stores::default());
+        assert_eq!(
+            changes,
+            vec![
+                Change::WrapperKindChanged {
+                    path: PathBuf::from("/opt/tool-wrapper"),
+                    before: WrapperKind::Text(ScriptType::Shell),
+                    after: WrapperKind::Binary,
+                },
+                Change::HopRemoved(PathBuf::from("/opt/old-link")),
+                Change::HopAdded(PathBuf::from("/opt/new-link")),
+                Change::TerminalKindChanged {
+                    before: FileKind::Text,
+                    after: FileKind::Binary,
+                },
+            ]
+        );
+    }
+}
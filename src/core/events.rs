@@ -0,0 +1,157 @@
+use crate::core::resolver::Detector;
+use crate::core::types::LinkType;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Filesystem access performed by the resolver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Syscall {
+    /// `readlink(2)` on the current path
+    ReadLink,
+    /// `lstat(2)` and a read of the first bytes to classify the file
+    ReadHeader,
+}
+
+impl Syscall {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::ReadLink => "readlink",
+            Self::ReadHeader => "read header",
+        }
+    }
+}
+
+/// What a detector concluded about a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DetectorOutcome {
+    /// The detector recognized the file, with a short description of what
+    /// it found
+    Matched(String),
+    NotMatched,
+    /// The detector is switched off in the resolver options
+    Disabled,
+    /// The detector could not read the file
+    Failed(String),
+}
+
+/// A step of a resolution, reported to a [`ResolveObserver`] as it happens.
+#[derive(Debug, Clone)]
+pub enum ResolveEvent {
+    /// Resolution of a path started
+    Started { path: PathBuf },
+    /// A path is about to be examined, `depth` hops into the chain
+    Visited { path: PathBuf, depth: usize },
+    Syscall {
+        call: Syscall,
+        path: PathBuf,
+        /// Result in words, e.g. the link target or the detected file type
+        outcome: String,
+        elapsed: Duration,
+    },
+    DetectorRan {
+        detector: Detector,
+        path: PathBuf,
+        outcome: DetectorOutcome,
+        /// The outcome was taken from the detector cache
+        cached: bool,
+        elapsed: Duration,
+    },
+    /// A hop was appended to the chain
+    HopAdded {
+        index: usize,
+        path: PathBuf,
+        link_type: LinkType,
+    },
+    /// Resolution succeeded
+    Finished {
+        links: usize,
+        truncated: bool,
+        elapsed: Duration,
+    },
+    /// Resolution ended with an error
+    Failed { error: String, elapsed: Duration },
+}
+
+/// Receives the events of every resolution run by a resolver.
+///
+/// Observers are shared between threads, so they must be `Send + Sync`.
+/// Closures taking a `&ResolveEvent` implement this trait.
+pub trait ResolveObserver: Send + Sync {
+    fn on_event(&self, event: &ResolveEvent);
+}
+
+impl<F> ResolveObserver for F
+where
+    F: Fn(&ResolveEvent) + Send + Sync,
+{
+    fn on_event(&self, event: &ResolveEvent) {
+        self(event);
+    }
+}
+
+/// Observer handle stored in the resolver options.
+#[derive(Clone)]
+pub struct SharedObserver(pub Arc<dyn ResolveObserver>);
+
+impl fmt::Debug for SharedObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SharedObserver")
+    }
+}
+
+/// Observer that records every event, e.g. to explain a resolution after
+/// the fact.
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: Mutex<Vec<ResolveEvent>>,
+}
+
+impl EventLog {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove and return the events recorded so far.
+    #[must_use]
+    pub fn take(&self) -> Vec<ResolveEvent> {
+        self.events
+            .lock()
+            .map(|mut events| std::mem::take(&mut *events))
+            .unwrap_or_default()
+    }
+
+    /// Forget the events recorded so far.
+    pub fn clear(&self) {
+        if let Ok(mut events) = self.events.lock() {
+            events.clear();
+        }
+    }
+}
+
+impl ResolveObserver for EventLog {
+    fn on_event(&self, event: &ResolveEvent) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_take() {
+        let log = EventLog::new();
+        log.on_event(&ResolveEvent::Started {
+            path: PathBuf::from("/bin/a"),
+        });
+
+        assert_eq!(log.take().len(), 1);
+        assert!(log.take().is_empty());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::core::types::SymlinkChain;
+use log::{debug, trace};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Prefixes Homebrew installs to by default on Apple Silicon, Intel macOS
+/// and Linux.
+const DEFAULT_PREFIXES: [&str; 3] = ["/opt/homebrew", "/usr/local", "/home/linuxbrew/.linuxbrew"];
+/// Prefix of an unprivileged Linuxbrew install, below `$HOME`
+const USER_PREFIX: &str = ".linuxbrew";
+
+const CELLAR_DIR: &str = "Cellar";
+const OPT_DIR: &str = "opt";
+const RECEIPT_FILE: &str = "INSTALL_RECEIPT.json";
+
+/// Directory below the prefix in which `brew link` records linked kegs.
+const LINKED_KEGS_DIR: &str = "var/homebrew/linked";
+
+/// Directory inside a keg holding a copy of the formula it was built from.
+const FORMULA_COPY_DIR: &str = ".brew";
+
+/// A path inside a Homebrew or Linuxbrew keg.
+///
+/// `<prefix>/Cellar/<formula>/<version>/<relative>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomebrewKeg {
+    /// Homebrew prefix, e.g. `/home/linuxbrew/.linuxbrew`
+    pub prefix: PathBuf,
+    pub formula: String,
+    pub version: String,
+    /// Path below the keg, empty for the keg itself
+    pub relative: PathBuf,
+    /// Whether the keg is linked into the prefix
+    pub linked: bool,
+    /// Whether the formula is declared `keg_only`
+    pub keg_only: bool,
+    /// Contents of the keg's `INSTALL_RECEIPT.json`, if readable
+    pub receipt: Option<InstallReceipt>,
+}
+
+/// The parts of a keg's `INSTALL_RECEIPT.json` shown for a hop.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct InstallReceipt {
+    #[serde(default)]
+    pub installed_on_request: bool,
+    #[serde(default)]
+    pub poured_from_bottle: bool,
+    /// Install time in seconds since the Unix epoch
+    #[serde(default)]
+    pub time: Option<i64>,
+}
+
+impl HomebrewKeg {
+    /// Find the keg a path lies in, below one of the given prefixes.
+    ///
+    /// Paths are recognized both directly inside the Cellar and through the
+    /// `opt/<formula>` links Homebrew keeps pointing at the current keg.
+    #[must_use]
+    pub fn locate(path: &Path, prefixes: &[PathBuf]) -> Option<Self> {
+        let (prefix, formula, version, relative) = prefixes.iter().find_map(|prefix| {
+            let cellar_path = follow_opt_link(path, prefix).unwrap_or_else(|| path.to_path_buf());
+            split_cellar_path(&cellar_path, prefix)
+        })?;
+
+        let mut keg = Self {
+            prefix,
+            formula,
+            version,
+            relative,
+            linked: false,
+            keg_only: false,
+            receipt: None,
+        };
+        keg.linked = fs::symlink_metadata(keg.prefix.join(LINKED_KEGS_DIR).join(&keg.formula))
+            .is_ok_and(|m| m.file_type().is_symlink());
+        keg.keg_only = is_keg_only(&keg.keg_dir(), &keg.formula);
+        keg.receipt = read_receipt(&keg.keg_dir());
+
+        trace!("Homebrew keg for {}: {keg:?}", path.display());
+        Some(keg)
+    }
+
+    /// Get the keg directory, e.g. `<prefix>/Cellar/hello/2.12`.
+    #[must_use]
+    pub fn keg_dir(&self) -> PathBuf {
+        self.prefix
+            .join(CELLAR_DIR)
+            .join(&self.formula)
+            .join(&self.version)
+    }
+}
+
+/// Split `<prefix>/Cellar/<formula>/<version>/<relative>` into its parts.
+fn split_cellar_path(path: &Path, prefix: &Path) -> Option<(PathBuf, String, String, PathBuf)> {
+    let mut components = path
+        .strip_prefix(prefix.join(CELLAR_DIR))
+        .ok()?
+        .components();
+    let formula = components.next()?.as_os_str().to_str()?;
+    let version = components.next()?.as_os_str().to_str()?;
+
+    Some((
+        prefix.to_path_buf(),
+        formula.to_string(),
+        version.to_string(),
+        components.as_path().to_path_buf(),
+    ))
+}
+
+/// Translate a path below `<prefix>/opt/<formula>` into the Cellar path the
+/// opt link points to.
+fn follow_opt_link(path: &Path, prefix: &Path) -> Option<PathBuf> {
+    let opt_dir = prefix.join(OPT_DIR);
+    let mut components = path.strip_prefix(&opt_dir).ok()?.components();
+    let opt_link = opt_dir.join(components.next()?);
+
+    let keg_dir = path_clean::clean(opt_dir.join(fs::read_link(&opt_link).ok()?));
+    let relative = components.as_path();
+    Some(if relative.as_os_str().is_empty() {
+        keg_dir
+    } else {
+        keg_dir.join(relative)
+    })
+}
+
+/// Get the prefixes Homebrew is looked for in: `$HOMEBREW_PREFIX`, the
+/// default prefixes and `~/.linuxbrew`.
+#[must_use]
+pub fn default_prefixes() -> Vec<PathBuf> {
+    let mut prefixes: Vec<PathBuf> = env::var_os("HOMEBREW_PREFIX")
+        .filter(|prefix| !prefix.is_empty())
+        .map(PathBuf::from)
+        .into_iter()
+        .chain(DEFAULT_PREFIXES.iter().map(PathBuf::from))
+        .chain(env::var_os("HOME").map(|home| PathBuf::from(home).join(USER_PREFIX)))
+        .collect();
+    let mut seen = HashSet::new();
+    prefixes.retain(|prefix| seen.insert(prefix.clone()));
+    prefixes
+}
+
+/// The receipt does not record whether a formula is keg-only, so check the
+/// copy of the formula installed alongside the keg.
+fn is_keg_only(keg_dir: &Path, formula: &str) -> bool {
+    let formula_file = keg_dir.join(FORMULA_COPY_DIR).join(format!("{formula}.rb"));
+    fs::read_to_string(formula_file).is_ok_and(|source| {
+        source
+            .lines()
+            .any(|line| line.trim_start().starts_with("keg_only"))
+    })
+}
+
+fn read_receipt(keg_dir: &Path) -> Option<InstallReceipt> {
+    let path = keg_dir.join(RECEIPT_FILE);
+    let content = fs::read_to_string(&path).ok()?;
+    serde_json::from_str(&content)
+        .inspect_err(|e| debug!("Invalid install receipt {}: {e}", path.display()))
+        .ok()
+}
+
+/// Attach keg information to every hop inside the Cellar of one of the
+/// given prefixes.
+pub fn annotate(chain: &mut SymlinkChain, prefixes: &[PathBuf]) {
+    for node in &mut chain.links {
+        node.keg = HomebrewKeg::locate(&node.target, prefixes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    /// Build a Linuxbrew prefix with `hello` 2.12 linked and `openssl@3` 3.3.0
+    /// installed keg-only.
+    fn create_prefix(temp: &TempDir) -> PathBuf {
+        let hello = temp.child("Cellar/hello/2.12");
+        hello.child("bin/hello").write_str("#!/bin/sh\n").unwrap();
+        hello
+            .child(RECEIPT_FILE)
+            .write_str(
+                r#"{"installed_on_request": true, "poured_from_bottle": true, "time": 1700000000}"#,
+            )
+            .unwrap();
+        hello
+            .child(".brew/hello.rb")
+            .write_str("class Hello < Formula\n  desc \"Hello\"\nend\n")
+            .unwrap();
+        temp.child("bin").create_dir_all().unwrap();
+        temp.child("bin/hello")
+            .symlink_to_file("../Cellar/hello/2.12/bin/hello")
+            .unwrap();
+        temp.child("var/homebrew/linked").create_dir_all().unwrap();
+        temp.child("var/homebrew/linked/hello")
+            .symlink_to_dir("../../../Cellar/hello/2.12")
+            .unwrap();
+
+        let openssl = temp.child("Cellar/openssl@3/3.3.0");
+        openssl
+            .child("bin/openssl")
+            .write_str("#!/bin/sh\n")
+            .unwrap();
+        openssl
+            .child(".brew/openssl@3.rb")
+            .write_str("class OpensslAT3 < Formula\n  keg_only :shadowed_by_macos\nend\n")
+            .unwrap();
+        temp.child("opt").create_dir_all().unwrap();
+        temp.child("opt/openssl@3")
+            .symlink_to_dir("../Cellar/openssl@3/3.3.0")
+            .unwrap();
+
+        temp.path().to_path_buf()
+    }
+
+    #[test]
+    fn test_locate_cellar_path() {
+        let temp = TempDir::new().unwrap();
+        let prefix = create_prefix(&temp);
+
+        let keg = HomebrewKeg::locate(
+            &prefix.join("Cellar/hello/2.12/bin/hello"),
+            std::slice::from_ref(&prefix),
+        )
+        .unwrap();
+
+        assert_eq!(keg.prefix, prefix);
+        assert_eq!(keg.formula, "hello");
+        assert_eq!(keg.version, "2.12");
+        assert_eq!(keg.relative, PathBuf::from("bin/hello"));
+        assert!(keg.linked);
+        assert!(!keg.keg_only);
+        assert_eq!(
+            keg.receipt,
+            Some(InstallReceipt {
+                installed_on_request: true,
+                poured_from_bottle: true,
+                time: Some(1_700_000_000),
+            })
+        );
+    }
+
+    #[test]
+    fn test_locate_through_opt_link() {
+        let temp = TempDir::new().unwrap();
+        let prefix = create_prefix(&temp);
+
+        let keg =
+            HomebrewKeg::locate(&prefix.join("opt/openssl@3/bin/openssl"), &[prefix]).unwrap();
+
+        assert_eq!(keg.formula, "openssl@3");
+        assert_eq!(keg.version, "3.3.0");
+        assert_eq!(keg.relative, PathBuf::from("bin/openssl"));
+        assert!(!keg.linked);
+        assert!(keg.keg_only);
+        assert_eq!(keg.receipt, None);
+    }
+
+    #[test]
+    fn test_locate_outside_cellar() {
+        let prefixes = [PathBuf::from("/opt/homebrew")];
+        assert!(HomebrewKeg::locate(Path::new("/usr/bin/python3"), &prefixes).is_none());
+        assert!(HomebrewKeg::locate(Path::new("/opt/homebrew/Cellar"), &prefixes).is_none());
+    }
+
+    #[test]
+    fn test_locate_requires_known_prefix() {
+        let temp = TempDir::new().unwrap();
+        let prefix = create_prefix(&temp);
+        let path = prefix.join("Cellar/hello/2.12/bin/hello");
+
+        assert!(HomebrewKeg::locate(&path, &[PathBuf::from("/opt/homebrew")]).is_none());
+        assert!(HomebrewKeg::locate(&path, &[prefix.join("Cellar")]).is_none());
+        assert!(HomebrewKeg::locate(&path, &[prefix]).is_some());
+    }
+
+    #[test]
+    fn test_annotate_resolved_chain() {
+        let temp = TempDir::new().unwrap();
+        let prefix = create_prefix(&temp);
+
+        let chain = crate::core::resolver::ResolverOptions::new()
+            .with_homebrew_prefix(&prefix)
+            .build()
+            .resolve(&prefix.join("bin/hello"))
+            .unwrap();
+
+        let keg = chain.links[0].keg.as_ref().unwrap();
+        assert_eq!(keg.formula, "hello");
+        assert_eq!(keg.keg_dir(), prefix.join("Cellar/hello/2.12"));
+    }
+}
@@ -0,0 +1,255 @@
+use crate::core::homebrew::HomebrewKeg;
+use crate::core::resolver::Resolver;
+use crate::core::shadow::{self, MatchStatus};
+use crate::core::types::SymlinkChain;
+use log::debug;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Outcome of resolving one PATH match.
+#[derive(Debug, Clone)]
+pub enum Resolution {
+    Resolved(SymlinkChain),
+    /// The match could not be resolved, e.g. a dangling symlink
+    Broken(String),
+}
+
+/// A single PATH match of a command.
+#[derive(Debug, Clone)]
+pub struct InventoryMatch {
+    pub path: PathBuf,
+    pub resolution: Resolution,
+}
+
+/// Every PATH match of one command name, in PATH order.
+#[derive(Debug, Clone)]
+pub struct InventoryEntry {
+    pub name: String,
+    pub matches: Vec<InventoryMatch>,
+}
+
+/// Totals over a whole inventory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InventorySummary {
+    pub commands: usize,
+    pub shadowed: usize,
+    /// Shadowed commands whose matches resolve to different files
+    pub conflicts: usize,
+    pub wrappers: usize,
+    pub broken: usize,
+}
+
+/// Resolved snapshot of every command available in PATH.
+#[derive(Debug, Clone, Default)]
+pub struct Inventory {
+    pub entries: Vec<InventoryEntry>,
+}
+
+impl InventoryMatch {
+    /// Get the resolved chain, if resolution succeeded.
+    #[must_use]
+    pub const fn chain(&self) -> Option<&SymlinkChain> {
+        match &self.resolution {
+            Resolution::Resolved(chain) => Some(chain),
+            Resolution::Broken(_) => None,
+        }
+    }
+
+    /// Get the package this match comes from.
+    ///
+    /// For files inside a store or a Homebrew keg this is the store entry or
+    /// keg, otherwise it is the final target of the chain.
+    #[must_use]
+    pub fn source(&self) -> Option<PathBuf> {
+        self.chain().map(source_of)
+    }
+}
+
+impl InventoryEntry {
+    /// Get the match that wins PATH lookup.
+    #[must_use]
+    pub fn winner(&self) -> &InventoryMatch {
+        &self.matches[0]
+    }
+
+    /// Check whether more than one PATH directory provides this command.
+    #[must_use]
+    pub const fn is_shadowed(&self) -> bool {
+        self.matches.len() > 1
+    }
+
+    /// Check whether a shadowed match resolves to a different file than
+    /// the winner.
+    ///
+    /// Broken matches are not compared.
+    #[must_use]
+    pub fn has_conflict(&self) -> bool {
+        if self.winner().chain().is_none() {
+            return false;
+        }
+        let chains: Vec<_> = self
+            .matches
+            .iter()
+            .filter_map(|m| m.chain().cloned())
+            .collect();
+        shadow::match_statuses(&chains).contains(&MatchStatus::Shadowed)
+    }
+}
+
+impl Inventory {
+    /// Compute totals over all entries.
+    ///
+    /// Wrappers are counted along the winning chain of each command, broken
+    /// entries over all matches.
+    #[must_use]
+    pub fn summary(&self) -> InventorySummary {
+        let mut summary = InventorySummary {
+            commands: self.entries.len(),
+            ..InventorySummary::default()
+        };
+
+        for entry in &self.entries {
+            if entry.is_shadowed() {
+                summary.shadowed += 1;
+            }
+            if entry.has_conflict() {
+                summary.conflicts += 1;
+            }
+            if let Some(chain) = entry.winner().chain() {
+                summary.wrappers += chain.wrapper_count();
+            }
+            summary.broken += entry
+                .matches
+                .iter()
+                .filter(|m| matches!(m.resolution, Resolution::Broken(_)))
+                .count();
+        }
+
+        summary
+    }
+}
+
+/// Resolve every match of every command, in parallel.
+///
+/// Failures are recorded as broken entries instead of aborting the run.
+#[must_use]
+pub fn build_inventory(commands: BTreeMap<String, Vec<PathBuf>>, resolver: &Resolver) -> Inventory {
+    let paths: Vec<&PathBuf> = commands.values().flatten().collect();
+    let mut resolutions =
+        resolver
+            .resolve_all(&paths)
+            .into_iter()
+            .zip(&paths)
+            .map(|(result, path)| match result {
+                Ok(chain) => Resolution::Resolved(chain),
+                Err(e) => {
+                    debug!("Failed to resolve {}: {e}", path.display());
+                    Resolution::Broken(e.to_string())
+                }
+            });
+
+    let entries = commands
+        .iter()
+        .map(|(name, paths)| {
+            let matches = paths
+                .iter()
+                .zip(resolutions.by_ref())
+                .map(|(path, resolution)| InventoryMatch {
+                    path: path.clone(),
+                    resolution,
+                })
+                .collect();
+            InventoryEntry {
+                name: name.clone(),
+                matches,
+            }
+        })
+        .collect();
+
+    Inventory { entries }
+}
+
+fn source_of(chain: &SymlinkChain) -> PathBuf {
+    let Some(node) = chain.links.last() else {
+        return chain.origin.clone();
+    };
+    if let Some(store_path) = &node.store_path {
+        return store_path.entry();
+    }
+    node.keg
+        .as_ref()
+        .map_or_else(|| node.target.clone(), HomebrewKeg::keg_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::store::Stores;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::Path;
+
+    fn create_executable(dir: &TempDir, name: &str, content: &[u8]) -> PathBuf {
+        let file = dir.child(name);
+        file.write_binary(content).unwrap();
+        let mut perms = std::fs::metadata(file.path()).unwrap().permissions();
+        perms.set_mode(0o755);
+        std::fs::set_permissions(file.path(), perms).unwrap();
+        file.to_path_buf()
+    }
+
+    fn chain_to(target: &str) -> SymlinkChain {
+        let mut chain = SymlinkChain::new(PathBuf::from("/usr/bin/cmd"));
+        chain.add_link(
+            PathBuf::from(target),
+            true,
+            crate::core::types::LinkType::Terminal(crate::core::types::FileKind::Binary),
+        );
+        chain.links[0].store_path = Stores::default().parse(Path::new(target));
+        chain
+    }
+
+    #[test]
+    fn test_source_of_store_path() {
+        assert_eq!(
+            source_of(&chain_to("/nix/store/abc123-quickshell-0.2.1/bin/qs")),
+            PathBuf::from("/nix/store/abc123-quickshell-0.2.1")
+        );
+        assert_eq!(
+            source_of(&chain_to("/usr/bin/python3.12")),
+            PathBuf::from("/usr/bin/python3.12")
+        );
+    }
+
+    #[test]
+    fn test_build_inventory_summary() {
+        let temp = TempDir::new().unwrap();
+        let first = create_executable(&temp, "first", b"#!/bin/sh\n");
+        let second = create_executable(&temp, "second", b"#!/bin/sh\n");
+        let dangling = temp.child("dangling");
+        dangling
+            .symlink_to_file(temp.child("missing").path())
+            .unwrap();
+
+        let mut commands = BTreeMap::new();
+        commands.insert("cmd".to_string(), vec![first.clone(), second]);
+        commands.insert("gone".to_string(), vec![dangling.to_path_buf()]);
+
+        let inventory = build_inventory(commands, &Resolver::default());
+        let summary = inventory.summary();
+
+        assert_eq!(
+            summary,
+            InventorySummary {
+                commands: 2,
+                shadowed: 1,
+                conflicts: 1,
+                wrappers: 0,
+                broken: 1,
+            }
+        );
+        assert_eq!(inventory.entries[0].winner().path, first);
+        assert_eq!(inventory.entries[0].winner().source(), Some(first));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::core::types::SymlinkChain;
+use log::{debug, trace};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+/// Separates the optional fields of a mountinfo line from the rest
+const OPTIONAL_FIELDS_END: &str = "-";
+const OVERLAY_FS_TYPE: &str = "overlay";
+
+/// A mount from `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfo {
+    pub mount_id: u32,
+    /// Where the mount is attached
+    pub mount_point: PathBuf,
+    /// Directory of the mounted filesystem that appears at `mount_point`
+    pub root: PathBuf,
+    pub fs_type: String,
+    /// Device or other source of the filesystem, e.g. `/dev/sda1`
+    pub source: String,
+    pub read_only: bool,
+}
+
+impl MountInfo {
+    /// Whether a subdirectory of a filesystem is mounted rather than its
+    /// root, as with `mount --bind /nix/store /nix/store`-style setups.
+    ///
+    /// Bind mounts of a filesystem's root cannot be told apart from the
+    /// original mount.
+    #[must_use]
+    pub fn is_bind(&self) -> bool {
+        self.root != Path::new("/")
+    }
+
+    #[must_use]
+    pub fn is_overlay(&self) -> bool {
+        self.fs_type == OVERLAY_FS_TYPE
+    }
+}
+
+/// The mounts visible to this process, in mount order.
+#[derive(Debug, Clone, Default)]
+pub struct MountTable {
+    mounts: Vec<MountInfo>,
+}
+
+impl MountTable {
+    /// Read the mount table of the current process.
+    ///
+    /// Returns `None` where `/proc` is not available.
+    #[must_use]
+    pub fn read() -> Option<Self> {
+        let content = fs::read_to_string(MOUNTINFO_PATH)
+            .inspect_err(|e| debug!("Failed to read {MOUNTINFO_PATH}: {e}"))
+            .ok()?;
+        Some(Self::parse(&content))
+    }
+
+    /// Parse mountinfo content, skipping malformed lines.
+    #[must_use]
+    pub fn parse(content: &str) -> Self {
+        let mounts = content
+            .lines()
+            .filter_map(|line| {
+                let mount = parse_line(line);
+                if mount.is_none() {
+                    trace!("Skipping malformed mountinfo line: {line}");
+                }
+                mount
+            })
+            .collect();
+        Self { mounts }
+    }
+
+    /// Find the mount a path itself lives on.
+    ///
+    /// Only the parent directory is canonicalized, so a symlink is looked
+    /// up where the link is rather than where it points. Of several mounts
+    /// stacked on the same point, the last one is visible.
+    #[must_use]
+    pub fn find(&self, path: &Path) -> Option<&MountInfo> {
+        let location = match (path.parent(), path.file_name()) {
+            (Some(parent), Some(name)) => fs::canonicalize(parent)
+                .map_or_else(|_| path.to_path_buf(), |parent| parent.join(name)),
+            _ => path.to_path_buf(),
+        };
+
+        self.mounts
+            .iter()
+            .filter(|mount| location.starts_with(&mount.mount_point))
+            .max_by_key(|mount| mount.mount_point.components().count())
+    }
+}
+
+/// Parse a line such as
+/// `36 35 98:0 /mnt1 /mnt2 rw,noatime master:1 - ext3 /dev/root rw,errors=continue`.
+fn parse_line(line: &str) -> Option<MountInfo> {
+    let mut fields = line.split(' ');
+    let mount_id = fields.next()?.parse().ok()?;
+    let _parent_id = fields.next()?;
+    let _device = fields.next()?;
+    let root = unescape(fields.next()?);
+    let mount_point = unescape(fields.next()?);
+    let mount_options = fields.next()?;
+    fields.find(|field| *field == OPTIONAL_FIELDS_END)?;
+    let fs_type = fields.next()?.to_string();
+    let source = unescape(fields.next()?);
+    let super_options = fields.next().unwrap_or_default();
+
+    let read_only = [mount_options, super_options]
+        .iter()
+        .any(|options| options.split(',').any(|option| option == "ro"));
+
+    Some(MountInfo {
+        mount_id,
+        mount_point: PathBuf::from(mount_point),
+        root: PathBuf::from(root),
+        fs_type,
+        source,
+        read_only,
+    })
+}
+
+/// Undo the octal escapes (`\040` for space) mountinfo uses in paths.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+
+    while idx < bytes.len() {
+        if bytes[idx] == b'\\'
+            && let Some(digits) = field.get(idx + 1..idx + 4)
+            && let Ok(byte) = u8::from_str_radix(digits, 8)
+        {
+            result.push(byte);
+            idx += 4;
+        } else {
+            result.push(bytes[idx]);
+            idx += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&result).into_owned()
+}
+
+/// Attach the mount of the origin and of every hop, as found in `table`.
+pub fn annotate_with(chain: &mut SymlinkChain, table: &MountTable) {
+    chain.origin_mount = table.find(&chain.origin).cloned();
+    for node in &mut chain.links {
+        node.mount = table.find(&node.target).cloned();
+    }
+}
+
+/// For every hop, check whether it lands on a different mount than the
+/// hop before it, or than the origin for the first hop.
+#[must_use]
+pub fn crossings(chain: &SymlinkChain) -> Vec<bool> {
+    let mut previous = chain.origin_mount.as_ref();
+    chain
+        .links
+        .iter()
+        .map(|node| {
+            let current = node.mount.as_ref();
+            let crosses = matches!(
+                (previous, current),
+                (Some(previous), Some(current)) if previous.mount_id != current.mount_id
+            );
+            previous = current;
+            crosses
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::{FileKind, LinkType};
+
+    const MOUNTINFO: &str = "\
+22 1 259:2 / / rw,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+23 22 0:21 / /proc rw,nosuid shared:5 - proc proc rw
+30 22 259:2 /nix/store /nix/store ro,relatime shared:1 - ext4 /dev/nvme0n1p2 rw
+31 22 0:45 / /mnt/my\\040disk rw - vfat /dev/sdb1 rw
+32 22 0:50 / /var/lib/docker/overlay2/merged rw - overlay overlay rw,lowerdir=/a
+";
+
+    #[test]
+    fn test_parse_mountinfo() {
+        let table = MountTable::parse(MOUNTINFO);
+
+        assert_eq!(table.mounts.len(), 5);
+        let store = &table.mounts[2];
+        assert_eq!(store.mount_id, 30);
+        assert_eq!(store.mount_point, PathBuf::from("/nix/store"));
+        assert_eq!(store.fs_type, "ext4");
+        assert_eq!(store.source, "/dev/nvme0n1p2");
+        assert!(store.read_only);
+        assert!(store.is_bind());
+
+        assert_eq!(table.mounts[3].mount_point, PathBuf::from("/mnt/my disk"));
+        assert!(table.mounts[4].is_overlay());
+        assert!(!table.mounts[0].read_only);
+    }
+
+    #[test]
+    fn test_parse_skips_malformed_lines() {
+        let table = MountTable::parse("garbage\n22 1 259:2 / / rw - ext4 /dev/root rw\n");
+        assert_eq!(table.mounts.len(), 1);
+    }
+
+    #[test]
+    fn test_find_longest_mount_point() {
+        let table = MountTable::parse(MOUNTINFO);
+
+        let mount = table
+            .find(Path::new("/nix/store/abc-hello/bin/hello"))
+            .unwrap();
+        assert_eq!(mount.mount_id, 30);
+        assert_eq!(table.find(Path::new("/nix/var")).unwrap().mount_id, 22);
+        assert_eq!(table.find(Path::new("/nixos")).unwrap().mount_id, 22);
+    }
+
+    #[test]
+    fn test_crossings() {
+        let table = MountTable::parse(MOUNTINFO);
+        let mut chain = SymlinkChain::new(PathBuf::from("/run/current-system/sw/bin/hello"));
+        chain.add_link(
+            PathBuf::from("/nix/store/abc-system/sw/bin/hello"),
+            false,
+            LinkType::Symlink,
+        );
+        chain.add_link(
+            PathBuf::from("/nix/store/def-hello/bin/hello"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+
+        annotate_with(&mut chain, &table);
+
+        assert_eq!(chain.origin_mount.as_ref().unwrap().mount_id, 22);
+        assert_eq!(crossings(&chain), vec![true, false]);
+    }
+}
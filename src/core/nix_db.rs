@@ -0,0 +1,402 @@
+use crate::core::store::Stores;
+use crate::core::types::SymlinkChain;
+use crate::error::{Result, SymseekError};
+use log::{debug, trace};
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+use std::collections::HashSet;
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_NIX_DB_PATH: &str = "/nix/var/nix/db/db.sqlite";
+
+/// Information the local Nix database holds about a store entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorePathInfo {
+    pub deriver: Option<PathBuf>,
+    pub nar_size: Option<u64>,
+    /// Seconds since the Unix epoch
+    pub registration_time: Option<i64>,
+    pub references: Vec<PathBuf>,
+    pub is_gc_root: bool,
+}
+
+/// Read-only view of the local Nix store database.
+///
+/// GC roots are not recorded in the database; they are collected from the
+/// `gcroots` directory next to it (`/nix/var/nix/gcroots`).
+pub struct NixDatabase {
+    conn: Connection,
+    gc_roots: HashSet<PathBuf>,
+}
+
+impl NixDatabase {
+    /// Open the database at `path` without write access. GC roots are
+    /// recognized as entries of the given stores.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or is not a Nix
+    /// database.
+    pub fn open(path: &Path, stores: &Stores) -> Result<Self> {
+        debug!("Opening Nix database: {}", path.display());
+        let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .and_then(check_schema)
+            .map_err(|e| db_error(path, &e))?;
+        Ok(Self::with_connection(path, conn, stores))
+    }
+
+    /// Open the database at `path` as immutable, for unprivileged users who
+    /// cannot create the WAL index next to it.
+    ///
+    /// SQLite then assumes the file never changes, so reads can be torn if
+    /// Nix writes to the database at the same time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or is not a Nix
+    /// database.
+    pub fn open_immutable(path: &Path, stores: &Stores) -> Result<Self> {
+        debug!("Opening Nix database as immutable: {}", path.display());
+        let conn = Connection::open_with_flags(
+            format!("file:{}?mode=ro&immutable=1", uri_path(path)),
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_URI,
+        )
+        .and_then(check_schema)
+        .map_err(|e| db_error(path, &e))?;
+        Ok(Self::with_connection(path, conn, stores))
+    }
+
+    fn with_connection(path: &Path, conn: Connection, stores: &Stores) -> Self {
+        let gc_roots = path
+            .parent()
+            .and_then(Path::parent)
+            .map(|state_dir| collect_gc_roots(&state_dir.join("gcroots"), stores))
+            .unwrap_or_default();
+        debug!("Found {} GC roots", gc_roots.len());
+
+        Self { conn, gc_roots }
+    }
+
+    /// Look up a store entry such as `/nix/store/<hash>-hello-2.12`.
+    ///
+    /// Returns `None` if the path is not registered as valid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be queried.
+    pub fn query(&self, entry: &Path) -> Result<Option<StorePathInfo>> {
+        trace!("Querying Nix database for {}", entry.display());
+        let entry_str = entry.to_string_lossy();
+
+        let row = self
+            .conn
+            .query_row(
+                "SELECT id, deriver, narSize, registrationTime FROM ValidPaths WHERE path = ?1",
+                [entry_str.as_ref()],
+                |row| {
+                    Ok((
+                        row.get::<_, i64>(0)?,
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<i64>>(2)?,
+                        row.get::<_, Option<i64>>(3)?,
+                    ))
+                },
+            )
+            .optional()
+            .map_err(|e| db_error(entry, &e))?;
+
+        let Some((id, deriver, nar_size, registration_time)) = row else {
+            trace!("Not a valid path: {}", entry.display());
+            return Ok(None);
+        };
+
+        let mut statement = self
+            .conn
+            .prepare(
+                "SELECT path FROM Refs JOIN ValidPaths ON reference = id \
+                 WHERE referrer = ?1 ORDER BY path",
+            )
+            .map_err(|e| db_error(entry, &e))?;
+        let references = statement
+            .query_map([id], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.map(|r| r.map(PathBuf::from)).collect())
+            .map_err(|e| db_error(entry, &e))?;
+
+        Ok(Some(StorePathInfo {
+            deriver: deriver.filter(|d| !d.is_empty()).map(PathBuf::from),
+            nar_size: nar_size.and_then(|size| u64::try_from(size).ok()),
+            registration_time,
+            references,
+            is_gc_root: self.gc_roots.contains(entry),
+        }))
+    }
+
+    /// Attach database information to every store node of a chain.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be queried.
+    pub fn annotate(&self, chain: &mut SymlinkChain) -> Result<()> {
+        for node in &mut chain.links {
+            if let Some(store_path) = &node.store_path {
+                node.store_info = self.query(&store_path.entry())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Opening is lazy, so touch the schema to surface errors when opening.
+fn check_schema(conn: Connection) -> rusqlite::Result<Connection> {
+    conn.query_row("SELECT count(*) FROM ValidPaths LIMIT 1", [], |_| Ok(()))?;
+    Ok(conn)
+}
+
+/// Percent-encode a path for an SQLite URI, in which `?`, `#` and `%` have
+/// a meaning of their own.
+fn uri_path(path: &Path) -> String {
+    path.as_os_str()
+        .as_bytes()
+        .iter()
+        .map(|&byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                char::from(byte).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn db_error(path: &Path, e: &rusqlite::Error) -> SymseekError {
+    SymseekError::NixDatabase {
+        path: path.to_path_buf(),
+        reason: e.to_string(),
+    }
+}
+
+/// Collect the store entries kept alive by the roots below `gcroots_dir`.
+///
+/// Handles direct roots and indirect ones (such as `auto/` links to a
+/// `result` symlink). Roots pointing at a directory of profile links, like
+/// `gcroots/profiles`, are expanded one level.
+fn collect_gc_roots(gcroots_dir: &Path, stores: &Stores) -> HashSet<PathBuf> {
+    let mut roots = HashSet::new();
+    let mut pending = vec![gcroots_dir.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = fs::read_dir(&dir) else {
+            trace!("Cannot read GC roots directory {}", dir.display());
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+
+            if file_type.is_dir() {
+                pending.push(path);
+            } else if file_type.is_symlink() {
+                // Direct roots point straight into the store
+                if let Some(store_path) = fs::read_link(&path)
+                    .ok()
+                    .and_then(|target| stores.parse(&target))
+                {
+                    roots.insert(store_path.entry());
+                    continue;
+                }
+
+                let Ok(target) = fs::canonicalize(&path) else {
+                    continue;
+                };
+                if let Some(store_path) = stores.parse(&target) {
+                    roots.insert(store_path.entry());
+                } else if target.is_dir() {
+                    roots.extend(profile_roots(&target, stores));
+                }
+            }
+        }
+    }
+
+    roots
+}
+
+fn profile_roots(dir: &Path, stores: &Stores) -> Vec<PathBuf> {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| fs::canonicalize(entry.path()).ok())
+                .filter_map(|target| stores.parse(&target))
+                .map(|store_path| store_path.entry())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    const HELLO: &str = "/nix/store/aaa-hello-2.12";
+    const GLIBC: &str = "/nix/store/bbb-glibc-2.40";
+
+    fn create_fixture_db(temp: &TempDir) -> PathBuf {
+        let db_dir = temp.child("var/nix/db");
+        db_dir.create_dir_all().unwrap();
+        let db_path = db_dir.child("db.sqlite").to_path_buf();
+
+        let conn = Connection::open(&db_path).unwrap();
+        conn.execute_batch(&format!(
+            "CREATE TABLE ValidPaths (
+                id integer primary key autoincrement not null,
+                path text unique not null,
+                hash text not null,
+                registrationTime integer not null,
+                deriver text,
+                narSize integer,
+                ultimate integer,
+                sigs text,
+                ca text
+            );
+            CREATE TABLE Refs (
+                referrer integer not null,
+                reference integer not null,
+                primary key (referrer, reference)
+            );
+            INSERT INTO ValidPaths (id, path, hash, registrationTime, deriver, narSize)
+                VALUES (1, '{HELLO}', 'sha256:x', 1700000000,
+                        '/nix/store/ccc-hello-2.12.drv', 274432);
+            INSERT INTO ValidPaths (id, path, hash, registrationTime, deriver, narSize)
+                VALUES (2, '{GLIBC}', 'sha256:y', 1600000000, '', 31457280);
+            INSERT INTO Refs VALUES (1, 1), (1, 2);"
+        ))
+        .unwrap();
+
+        db_path
+    }
+
+    #[test]
+    fn test_query_valid_path() {
+        let temp = TempDir::new().unwrap();
+        let db = NixDatabase::open(&create_fixture_db(&temp), &Stores::default()).unwrap();
+
+        let info = db.query(Path::new(HELLO)).unwrap().unwrap();
+        assert_eq!(
+            info,
+            StorePathInfo {
+                deriver: Some(PathBuf::from("/nix/store/ccc-hello-2.12.drv")),
+                nar_size: Some(274_432),
+                registration_time: Some(1_700_000_000),
+                references: vec![PathBuf::from(HELLO), PathBuf::from(GLIBC)],
+                is_gc_root: false,
+            }
+        );
+
+        let glibc = db.query(Path::new(GLIBC)).unwrap().unwrap();
+        assert_eq!(glibc.deriver, None);
+        assert!(glibc.references.is_empty());
+    }
+
+    #[test]
+    fn test_query_unknown_path() {
+        let temp = TempDir::new().unwrap();
+        let db = NixDatabase::open(&create_fixture_db(&temp), &Stores::default()).unwrap();
+
+        assert!(
+            db.query(Path::new("/nix/store/zzz-missing"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_gc_roots() {
+        let temp = TempDir::new().unwrap();
+        let db_path = create_fixture_db(&temp);
+        let gcroots = temp.child("var/nix/gcroots/auto");
+        gcroots.create_dir_all().unwrap();
+        gcroots
+            .child("result")
+            .symlink_to_file(format!("{HELLO}/bin/hello"))
+            .unwrap();
+
+        let db = NixDatabase::open(&db_path, &Stores::default()).unwrap();
+
+        assert!(db.query(Path::new(HELLO)).unwrap().unwrap().is_gc_root);
+        assert!(!db.query(Path::new(GLIBC)).unwrap().unwrap().is_gc_root);
+    }
+
+    #[test]
+    fn test_gc_roots_in_relocated_store() {
+        let temp = TempDir::new().unwrap();
+        let gcroots = temp.child("gcroots");
+        gcroots.create_dir_all().unwrap();
+        gcroots
+            .child("result")
+            .symlink_to_file("/home/u/nix/store/aaa-hello-2.12/bin/hello")
+            .unwrap();
+
+        assert!(collect_gc_roots(gcroots.path(), &Stores::default()).is_empty());
+        let stores = Stores::default().with_nix_store_dir("/home/u/nix/store");
+        assert_eq!(
+            collect_gc_roots(gcroots.path(), &stores),
+            HashSet::from([PathBuf::from("/home/u/nix/store/aaa-hello-2.12")])
+        );
+    }
+
+    #[test]
+    fn test_open_rejects_non_nix_database() {
+        let temp = TempDir::new().unwrap();
+        let db_path = temp.child("empty.sqlite").to_path_buf();
+        Connection::open(&db_path).unwrap();
+
+        assert!(matches!(
+            NixDatabase::open(&db_path, &Stores::default()),
+            Err(SymseekError::NixDatabase { .. })
+        ));
+    }
+
+    #[test]
+    fn test_open_path_with_uri_characters() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.child("db?mode=rw#50%");
+        dir.create_dir_all().unwrap();
+        let db_path = dir.child("db.sqlite").to_path_buf();
+        fs::copy(create_fixture_db(&temp), &db_path).unwrap();
+
+        assert!(NixDatabase::open(&db_path, &Stores::default()).is_ok());
+        let db = NixDatabase::open_immutable(&db_path, &Stores::default()).unwrap();
+        assert!(db.query(Path::new(HELLO)).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_uri_path() {
+        assert_eq!(
+            uri_path(Path::new("/var/db?x#y%z/a b")),
+            "/var/db%3Fx%23y%25z/a%20b"
+        );
+    }
+
+    #[test]
+    fn test_annotate_chain() {
+        let temp = TempDir::new().unwrap();
+        let db = NixDatabase::open(&create_fixture_db(&temp), &Stores::default()).unwrap();
+
+        let mut chain = SymlinkChain::new(PathBuf::from("/usr/bin/hello"));
+        chain.add_link(
+            PathBuf::from(format!("{HELLO}/bin/hello")),
+            true,
+            crate::core::types::LinkType::Symlink,
+        );
+        chain.links[0].store_path = Stores::default().parse(&chain.links[0].target);
+        db.annotate(&mut chain).unwrap();
+
+        let info = chain.links[0].store_info.as_ref().unwrap();
+        assert_eq!(info.nar_size, Some(274_432));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::core::types::SymlinkChain;
+use crate::error::{Result, SymseekError};
+use log::{debug, trace};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+const PROC_DIR: &str = "/proc";
+/// Suffix the kernel appends to `/proc/<pid>/exe` once the file is unlinked
+const DELETED_SUFFIX: &str = " (deleted)";
+
+/// The executable a running process was started from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Executable the kernel loaded, without the ` (deleted)` marker
+    pub exe: PathBuf,
+    /// Whether the executable has been unlinked since, e.g. by an upgrade
+    pub deleted: bool,
+    /// First element of the command line, as passed to `execve`
+    pub argv0: Option<String>,
+    /// Working directory, against which a relative `argv0` is resolved
+    pub cwd: Option<PathBuf>,
+    /// `PATH` from the process's environment, if readable
+    pub path_var: Option<OsString>,
+    /// Device and inode of the loaded executable, readable even once deleted
+    exe_inode: Option<(u64, u64)>,
+}
+
+impl ProcessInfo {
+    /// Read a process from `/proc`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the process does not exist or its executable
+    /// link cannot be read, e.g. for another user's process.
+    pub fn read(pid: u32) -> Result<Self> {
+        Self::read_from(Path::new(PROC_DIR), pid)
+    }
+
+    fn read_from(proc_dir: &Path, pid: u32) -> Result<Self> {
+        let dir = proc_dir.join(pid.to_string());
+        let exe_link = dir.join("exe");
+        let target = fs::read_link(&exe_link).map_err(|e| SymseekError::Io {
+            context: format!("Failed to read {}", exe_link.display()),
+            source: e,
+        })?;
+        let (exe, deleted) = split_deleted(&target);
+
+        let argv0 = fs::read(dir.join("cmdline")).ok().and_then(|cmdline| {
+            let first = cmdline.split(|&b| b == 0).next()?;
+            (!first.is_empty()).then(|| String::from_utf8_lossy(first).into_owned())
+        });
+        let path_var = fs::read(dir.join("environ"))
+            .inspect_err(|e| debug!("Cannot read environment of {pid}: {e}"))
+            .ok()
+            .and_then(|environ| {
+                environ
+                    .split(|&b| b == 0)
+                    .find_map(|var| var.strip_prefix(b"PATH="))
+                    .map(|value| OsStr::from_bytes(value).to_os_string())
+            });
+
+        let process = Self {
+            pid,
+            exe,
+            deleted,
+            argv0,
+            cwd: fs::read_link(dir.join("cwd")).ok(),
+            path_var,
+            exe_inode: fs::metadata(&exe_link)
+                .ok()
+                .map(|metadata| (metadata.dev(), metadata.ino())),
+        };
+        trace!("Process {pid}: {process:?}");
+        Ok(process)
+    }
+
+    /// Find the path the process was most likely started through.
+    ///
+    /// A bare `argv0` name is looked up in the process's own `PATH` and a
+    /// relative one against its working directory. When `argv0` cannot be
+    /// found, the loaded executable is used instead.
+    #[must_use]
+    pub fn entry_point(&self) -> PathBuf {
+        self.argv0_path().unwrap_or_else(|| self.exe.clone())
+    }
+
+    fn argv0_path(&self) -> Option<PathBuf> {
+        let argv0 = Path::new(self.argv0.as_deref()?);
+
+        let candidate = if argv0.is_absolute() {
+            argv0.to_path_buf()
+        } else if argv0.components().count() > 1 {
+            self.cwd.as_ref()?.join(argv0)
+        } else {
+            let path_var = self.path_var.clone().or_else(|| env::var_os("PATH"))?;
+            return env::split_paths(&path_var)
+                .map(|dir| dir.join(argv0))
+                .find(|path| path.is_file());
+        };
+
+        candidate.is_file().then_some(candidate)
+    }
+
+    /// Check whether a chain ends at the executable the process loaded.
+    #[must_use]
+    pub fn loads(&self, chain: &SymlinkChain) -> bool {
+        let Some(exe_inode) = self.exe_inode else {
+            return false;
+        };
+        fs::metadata(chain.final_target())
+            .is_ok_and(|metadata| (metadata.dev(), metadata.ino()) == exe_inode)
+    }
+}
+
+fn split_deleted(target: &Path) -> (PathBuf, bool) {
+    let bytes = target.as_os_str().as_bytes();
+    match bytes.strip_suffix(DELETED_SUFFIX.as_bytes()) {
+        Some(path) => (PathBuf::from(OsStr::from_bytes(path)), true),
+        None => (target.to_path_buf(), false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn process(argv0: Option<&str>, cwd: &Path, path_var: &OsStr) -> ProcessInfo {
+        ProcessInfo {
+            pid: 1234,
+            exe: PathBuf::from("/usr/libexec/real"),
+            deleted: false,
+            argv0: argv0.map(str::to_string),
+            cwd: Some(cwd.to_path_buf()),
+            path_var: Some(path_var.to_os_string()),
+            exe_inode: None,
+        }
+    }
+
+    #[test]
+    fn test_split_deleted() {
+        assert_eq!(
+            split_deleted(Path::new("/usr/bin/foo (deleted)")),
+            (PathBuf::from("/usr/bin/foo"), true)
+        );
+        assert_eq!(
+            split_deleted(Path::new("/usr/bin/foo")),
+            (PathBuf::from("/usr/bin/foo"), false)
+        );
+    }
+
+    #[test]
+    fn test_entry_point_searches_process_path() {
+        let temp = TempDir::new().unwrap();
+        temp.child("bin1").create_dir_all().unwrap();
+        temp.child("bin2/tool").touch().unwrap();
+        let path_var =
+            env::join_paths([temp.path().join("bin1"), temp.path().join("bin2")]).unwrap();
+
+        let process = process(Some("tool"), temp.path(), &path_var);
+        assert_eq!(process.entry_point(), temp.path().join("bin2/tool"));
+
+        let process = ProcessInfo {
+            argv0: Some("bin2/tool".to_string()),
+            ..process
+        };
+        assert_eq!(process.entry_point(), temp.path().join("bin2/tool"));
+    }
+
+    #[test]
+    fn test_entry_point_falls_back_to_exe() {
+        let temp = TempDir::new().unwrap();
+
+        let process = process(Some("missing"), temp.path(), OsStr::new(""));
+        assert_eq!(process.entry_point(), PathBuf::from("/usr/libexec/real"));
+
+        let process = ProcessInfo {
+            argv0: None,
+            ..process
+        };
+        assert_eq!(process.entry_point(), PathBuf::from("/usr/libexec/real"));
+    }
+
+    #[test]
+    fn test_read_own_process() {
+        let process = ProcessInfo::read(std::process::id()).unwrap();
+
+        assert!(!process.deleted);
+        assert_eq!(process.exe, env::current_exe().unwrap());
+        let chain = crate::core::resolver::resolve(&process.exe).unwrap();
+        assert!(process.loads(&chain));
+    }
+
+    #[test]
+    fn test_read_missing_process() {
+        let temp = TempDir::new().unwrap();
+        assert!(matches!(
+            ProcessInfo::read_from(temp.path(), 42),
+            Err(SymseekError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,517 @@
+use crate::core::detector::{root_path, unroot_path};
+use crate::core::resolver::Resolver;
+use crate::core::types::SymlinkChain;
+use log::{debug, trace};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+
+/// Directories holding the `system-N-link` generations of Nix and Guix.
+const SYSTEM_PROFILES_DIRS: [&str; 2] = ["/nix/var/nix/profiles", "/var/guix/profiles"];
+const PER_USER_PROFILES_PREFIX: &str = "/etc/profiles/per-user";
+
+/// Upper bound on links followed for a single profile prefix, well above
+/// the usual `~/.nix-profile -> profile -> profile-N-link -> store` depth.
+const MAX_PROFILE_LINKS: usize = 16;
+
+/// Matches generation links such as `system-42-link`.
+static GENERATION_LINK_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.+)-(\d+)-link$").unwrap());
+
+/// A Nix or Guix profile traversed while following a path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileLayer {
+    /// Profile name, e.g. `system`, `profile`, `home-manager` or
+    /// `guix-profile`
+    pub name: String,
+    /// Generation number, if the profile resolves through a `*-N-link`
+    pub generation: Option<u32>,
+    /// Generation link if known, otherwise the profile link itself
+    pub link: PathBuf,
+    /// Remainder of the path below the profile, e.g. `sw/bin/foo`
+    pub relative: PathBuf,
+    /// Other generations of the profile in which the same path resolves
+    /// differently, filled in only on request
+    pub siblings: Vec<SiblingGeneration>,
+}
+
+/// How a path resolves in another generation of the same profile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SiblingGeneration {
+    pub generation: u32,
+    /// Final target in that generation, `None` if the path does not exist there
+    pub target: Option<PathBuf>,
+}
+
+/// Find the profiles traversed when opening `path`.
+///
+/// Every symlinked prefix of the path is followed; generation links along
+/// the way give the generation number, and well-known locations such as
+/// `/run/current-system`, `~/.nix-profile` or `~/.guix-profile` give the
+/// profile name.
+#[must_use]
+pub fn profile_layers(path: &Path) -> Vec<ProfileLayer> {
+    layers_with(path, |prefix| layer_at(prefix, None))
+}
+
+/// Profiles found at path prefixes, remembered so that hops and chains
+/// sharing a prefix such as `/run/current-system` only follow it once.
+#[derive(Debug, Default)]
+pub struct ProfileCache {
+    prefixes: Mutex<HashMap<PathBuf, Option<ProfileLayer>>>,
+    /// Directory the looked up paths are below, standing for `/`
+    root: Option<PathBuf>,
+}
+
+impl ProfileCache {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Look up profiles of paths below `root`, which profile links and
+    /// well-known locations such as `/run/current-system` are relative to.
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Same as [`profile_layers`], reusing what earlier calls found.
+    #[must_use]
+    pub fn layers(&self, path: &Path) -> Vec<ProfileLayer> {
+        layers_with(path, |prefix| {
+            if let Some(layer) = self.prefixes.lock().ok()?.get(prefix) {
+                return layer.clone();
+            }
+            let layer = layer_at(prefix, self.root.as_deref());
+            if let Ok(mut prefixes) = self.prefixes.lock() {
+                prefixes.insert(prefix.to_path_buf(), layer.clone());
+            }
+            layer
+        })
+    }
+
+    /// Forget everything found so far, e.g. after a profile switch.
+    pub fn clear(&self) {
+        if let Ok(mut prefixes) = self.prefixes.lock() {
+            prefixes.clear();
+        }
+    }
+}
+
+fn layers_with(
+    path: &Path,
+    mut layer_at: impl FnMut(&Path) -> Option<ProfileLayer>,
+) -> Vec<ProfileLayer> {
+    let ancestors: Vec<_> = path.ancestors().collect();
+    ancestors
+        .into_iter()
+        .rev()
+        .filter_map(|prefix| {
+            let mut layer = layer_at(prefix)?;
+            layer.relative = path.strip_prefix(prefix).unwrap_or(path).to_path_buf();
+            trace!("Profile layer at {}: {layer:?}", prefix.display());
+            Some(layer)
+        })
+        .collect()
+}
+
+/// Find the profile a symlinked prefix leads through, leaving `relative`
+/// empty.
+///
+/// Profile names are matched on paths inside `root`, while links are read
+/// and reported on the host.
+fn layer_at(prefix: &Path, root: Option<&Path>) -> Option<ProfileLayer> {
+    if !fs::symlink_metadata(prefix).is_ok_and(|m| m.file_type().is_symlink()) {
+        return None;
+    }
+
+    let mut name = known_profile_name(&unroot_path(root, prefix));
+    let mut generation = None;
+    let mut current = prefix.to_path_buf();
+
+    for _ in 0..MAX_PROFILE_LINKS {
+        if let Some((link_name, link_generation)) = parse_generation_link(&current) {
+            name.get_or_insert(link_name);
+            generation = Some((link_generation, current.clone()));
+            break;
+        }
+        if name.is_none() && is_in_profiles_dir(&unroot_path(root, &current)) {
+            name = current
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned());
+        }
+
+        let Some(target) = read_link_within(&current, root) else {
+            break;
+        };
+        current = target;
+    }
+
+    let name = name?;
+    if generation.is_none() && matches!(name.as_str(), "system" | "booted system") {
+        generation = find_system_generation(prefix, root);
+    }
+
+    let (generation, link) = generation.map_or((None, prefix.to_path_buf()), |(number, link)| {
+        (Some(number), link)
+    });
+
+    Some(ProfileLayer {
+        name,
+        generation,
+        link,
+        relative: PathBuf::new(),
+        siblings: Vec::new(),
+    })
+}
+
+fn known_profile_name(path: &Path) -> Option<String> {
+    if path == Path::new("/run/current-system") {
+        return Some("system".to_string());
+    }
+    if path == Path::new("/run/booted-system") {
+        return Some("booted system".to_string());
+    }
+    if path.parent() == Some(Path::new(PER_USER_PROFILES_PREFIX)) {
+        let user = path.file_name()?.to_string_lossy();
+        return Some(format!("per-user {user}"));
+    }
+
+    match path.file_name()?.to_str()? {
+        ".nix-profile" => Some("profile".to_string()),
+        "current-home" => Some("home-manager".to_string()),
+        ".guix-profile" => Some("guix-profile".to_string()),
+        ".guix-home" => Some("guix-home".to_string()),
+        _ => None,
+    }
+}
+
+fn is_in_profiles_dir(path: &Path) -> bool {
+    path.parent().is_some_and(|parent| {
+        parent.ends_with("profiles")
+            || SYSTEM_PROFILES_DIRS
+                .iter()
+                .any(|dir| parent.starts_with(dir))
+    })
+}
+
+fn parse_generation_link(path: &Path) -> Option<(String, u32)> {
+    let file_name = path.file_name()?.to_str()?;
+    let caps = GENERATION_LINK_REGEX.captures(file_name)?;
+    Some((caps[1].to_string(), caps[2].parse().ok()?))
+}
+
+/// Read the link at host path `link`, taking absolute targets to be inside
+/// `root`.
+fn read_link_within(link: &Path, root: Option<&Path>) -> Option<PathBuf> {
+    let target = fs::read_link(link).ok()?;
+    Some(match link.parent() {
+        Some(parent) if target.is_relative() => path_clean::clean(parent.join(target)),
+        _ => root_path(root, &target),
+    })
+}
+
+/// Follow the links at `path` itself within `root`, then canonicalize the
+/// result for comparison.
+fn final_target_within(path: &Path, root: Option<&Path>) -> Option<PathBuf> {
+    let mut current = path.to_path_buf();
+    for _ in 0..MAX_PROFILE_LINKS {
+        match read_link_within(&current, root) {
+            Some(target) => current = target,
+            None => break,
+        }
+    }
+    fs::canonicalize(current).ok()
+}
+
+/// `/run/current-system` points straight into the store, so find the system
+/// generation link that points to the same place, in either the Nix or the
+/// Guix profiles directory.
+fn find_system_generation(link: &Path, root: Option<&Path>) -> Option<(u32, PathBuf)> {
+    let target = final_target_within(link, root)?;
+    SYSTEM_PROFILES_DIRS
+        .iter()
+        .flat_map(|dir| generation_links(&root_path(root, Path::new(dir)), "system"))
+        .find(|(_, sibling)| final_target_within(sibling, root).is_some_and(|t| t == target))
+}
+
+/// List the generation links of profile `name` in `dir`, sorted by
+/// generation.
+fn generation_links(dir: &Path, name: &str) -> Vec<(u32, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut links: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter_map(|path| match parse_generation_link(&path) {
+            Some((sibling_name, number)) if sibling_name == name => Some((number, path)),
+            _ => None,
+        })
+        .collect();
+    links.sort();
+    links
+}
+
+/// Label the origin and every hop with the profiles traversed when opening
+/// its path.
+///
+/// The origin is left unlabelled when it is not a link itself, since the
+/// first hop then repeats the same path.
+pub fn annotate(chain: &mut SymlinkChain, cache: &ProfileCache) {
+    if chain
+        .links
+        .first()
+        .is_none_or(|node| node.target != chain.origin)
+    {
+        chain.origin_profiles = cache.layers(&chain.origin);
+    }
+    for node in &mut chain.links {
+        node.profiles = cache.layers(&node.target);
+    }
+}
+
+/// Fill in the generations in which each profiled hop resolves differently
+/// from the chain's final target.
+pub fn annotate_siblings(chain: &mut SymlinkChain, resolver: &Resolver) {
+    let final_target = canonical_final_target(chain);
+
+    let layers = chain
+        .origin_profiles
+        .iter_mut()
+        .chain(chain.links.iter_mut().flat_map(|node| &mut node.profiles));
+    for layer in layers {
+        layer.siblings = sibling_generations(layer, &final_target, resolver);
+    }
+}
+
+fn sibling_generations(
+    layer: &ProfileLayer,
+    final_target: &Path,
+    resolver: &Resolver,
+) -> Vec<SiblingGeneration> {
+    let (Some(current), Some(dir), Some((name, _))) = (
+        layer.generation,
+        layer.link.parent(),
+        parse_generation_link(&layer.link),
+    ) else {
+        return Vec::new();
+    };
+
+    generation_links(dir, &name)
+        .into_iter()
+        .filter(|(number, _)| *number != current)
+        .filter_map(|(number, link)| {
+            // Generation links are on the host, the resolver reroots itself
+            let path = unroot_path(resolver.options().root(), &link).join(&layer.relative);
+            let target = resolver
+                .resolve(&path)
+                .ok()
+                .map(|chain| canonical_final_target(&chain));
+            debug!("Generation {number}: {} -> {target:?}", path.display());
+            (target.as_deref() != Some(final_target)).then_some(SiblingGeneration {
+                generation: number,
+                target,
+            })
+        })
+        .collect()
+}
+
+/// Final targets may still contain symlinked directories, such as the
+/// profile itself, so compare them canonicalized.
+fn canonical_final_target(chain: &SymlinkChain) -> PathBuf {
+    let target = chain.final_target();
+    fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::os::unix::fs::symlink;
+
+    /// Build `profiles/{name}-{N}-link -> store/gen{N}` with `bin/tool` in each
+    /// generation and `profiles/{name} -> {name}-{current}-link`.
+    fn create_profile(temp: &TempDir, name: &str, generations: &[u32], current: u32) -> PathBuf {
+        let profiles = temp.child("profiles");
+        profiles.create_dir_all().unwrap();
+
+        for generation in generations {
+            let bin = temp.child(format!("store/gen{generation}/bin"));
+            bin.create_dir_all().unwrap();
+            bin.child("tool").write_str("#!/bin/sh\n").unwrap();
+            symlink(
+                temp.child(format!("store/gen{generation}")).path(),
+                profiles.child(format!("{name}-{generation}-link")).path(),
+            )
+            .unwrap();
+        }
+        symlink(
+            format!("{name}-{current}-link"),
+            profiles.child(name).path(),
+        )
+        .unwrap();
+
+        profiles.child(name).to_path_buf()
+    }
+
+    #[test]
+    fn test_parse_generation_link() {
+        assert_eq!(
+            parse_generation_link(Path::new("/nix/var/nix/profiles/system-42-link")),
+            Some(("system".to_string(), 42))
+        );
+        assert_eq!(
+            parse_generation_link(Path::new(
+                "/home/u/.local/state/nix/profiles/home-manager-7-link"
+            )),
+            Some(("home-manager".to_string(), 7))
+        );
+        assert_eq!(
+            parse_generation_link(Path::new("/nix/var/nix/profiles/system")),
+            None
+        );
+    }
+
+    #[test]
+    fn test_profile_layers_through_generation_link() {
+        let temp = TempDir::new().unwrap();
+        let profile = create_profile(&temp, "profile", &[1, 2], 2);
+        let nix_profile = temp.child(".nix-profile");
+        nix_profile.symlink_to_dir(&profile).unwrap();
+
+        let layers = profile_layers(&nix_profile.path().join("bin/tool"));
+
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].name, "profile");
+        assert_eq!(layers[0].generation, Some(2));
+        assert_eq!(layers[0].link, temp.child("profiles/profile-2-link").path());
+        assert_eq!(layers[0].relative, PathBuf::from("bin/tool"));
+    }
+
+    #[test]
+    fn test_profile_layers_guix_profile() {
+        let temp = TempDir::new().unwrap();
+        let profile = create_profile(&temp, "guix-profile", &[3, 4], 4);
+        let guix_profile = temp.child(".guix-profile");
+        guix_profile.symlink_to_dir(&profile).unwrap();
+
+        let layers = profile_layers(&guix_profile.path().join("bin/tool"));
+
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].name, "guix-profile");
+        assert_eq!(layers[0].generation, Some(4));
+        assert_eq!(
+            layers[0].link,
+            temp.child("profiles/guix-profile-4-link").path()
+        );
+    }
+
+    #[test]
+    fn test_profile_cache_reuses_prefix() {
+        let temp = TempDir::new().unwrap();
+        let profile = create_profile(&temp, "profile", &[1, 2], 2);
+        let nix_profile = temp.child(".nix-profile");
+        nix_profile.symlink_to_dir(&profile).unwrap();
+
+        let cache = ProfileCache::new();
+        let layers = cache.layers(&nix_profile.path().join("bin/tool"));
+        assert_eq!(layers[0].generation, Some(2));
+
+        // Switching generations is only seen once the cache is cleared
+        std::fs::remove_file(temp.child("profiles/profile")).unwrap();
+        symlink("profile-1-link", temp.child("profiles/profile")).unwrap();
+        let layers = cache.layers(&nix_profile.path().join("bin/other"));
+        assert_eq!(layers[0].generation, Some(2));
+        assert_eq!(layers[0].relative, PathBuf::from("bin/other"));
+
+        cache.clear();
+        let layers = cache.layers(&nix_profile.path().join("bin/tool"));
+        assert_eq!(layers[0].generation, Some(1));
+    }
+
+    #[test]
+    fn test_profile_layers_plain_path() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.child("tool");
+        file.touch().unwrap();
+
+        assert!(profile_layers(file.path()).is_empty());
+    }
+
+    #[test]
+    fn test_sibling_generations_resolving_differently() {
+        let temp = TempDir::new().unwrap();
+        let profile = create_profile(&temp, "home-manager", &[1, 2, 3], 3);
+        // Generation 2 points at the same store path as the current one
+        fs::remove_file(temp.child("profiles/home-manager-2-link").path()).unwrap();
+        symlink(
+            temp.child("store/gen3").path(),
+            temp.child("profiles/home-manager-2-link").path(),
+        )
+        .unwrap();
+
+        let origin = profile.join("bin/tool");
+        let mut chain = crate::core::resolver::resolve(&origin).unwrap();
+        annotate(&mut chain, &ProfileCache::new());
+        annotate_siblings(&mut chain, &Resolver::default());
+
+        assert!(chain.origin_profiles.is_empty());
+        let layer = &chain.links[0].profiles[0];
+        assert_eq!(layer.generation, Some(3));
+        assert_eq!(
+            layer.siblings,
+            vec![SiblingGeneration {
+                generation: 1,
+                target: Some(temp.child("store/gen1/bin/tool").to_path_buf()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_system_profile_in_root() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let profiles = temp.child("nix/var/nix/profiles");
+        profiles.create_dir_all().unwrap();
+        temp.child("run").create_dir_all().unwrap();
+        for generation in [1, 2] {
+            temp.child(format!("nix/store/gen{generation}-system/bin/tool"))
+                .write_str("#!/bin/sh\n")
+                .unwrap();
+            symlink(
+                format!("../../../store/gen{generation}-system"),
+                profiles.child(format!("system-{generation}-link")),
+            )
+            .unwrap();
+        }
+        symlink("../nix/store/gen2-system", temp.child("run/current-system")).unwrap();
+
+        let resolver = crate::core::resolver::ResolverOptions::new()
+            .with_root(&root)
+            .with_metadata(true)
+            .build();
+        let mut chain = resolver
+            .resolve(Path::new("/run/current-system/bin/tool"))
+            .unwrap();
+        annotate_siblings(&mut chain, &resolver);
+
+        let layer = &chain.links[0].profiles[0];
+        assert_eq!(layer.name, "system");
+        assert_eq!(layer.generation, Some(2));
+        assert_eq!(layer.link, root.join("nix/var/nix/profiles/system-2-link"));
+        assert_eq!(
+            layer.siblings,
+            vec![SiblingGeneration {
+                generation: 1,
+                target: Some(root.join("nix/store/gen1-system/bin/tool")),
+            }]
+        );
+    }
+}
@@ -1,150 +1,886 @@
+use crate::core::cache::{CacheKey, DetectorCache, FileKey};
 use crate::core::detector::nix_binary_wrapper::NixBinaryWrapperDetector;
 use crate::core::detector::nix_program_name::NixProgramNameDetector;
-use crate::core::detector::{self, FileType, WrapperDetector};
-use crate::core::types::{FileKind, LinkType, ScriptType, SymlinkChain, WrapperKind};
+use crate::core::detector::self_extracting::{self, SelfExtractingKind};
+use crate::core::detector::{
+    self, FileType, WrapperDetector, compiler_cache, launcher, multi_call,
+};
+use crate::core::events::{
+    DetectorOutcome, ResolveEvent, ResolveObserver, SharedObserver, Syscall,
+};
+use crate::core::mount::MountTable;
+use crate::core::profile::ProfileCache;
+use crate::core::store::Stores;
+use crate::core::types::{FileId, FileKind, LinkType, ScriptType, SymlinkChain, WrapperKind};
+use crate::core::{homebrew, mount, profile};
 use crate::error::{Result, SymseekError};
 use log::{debug, trace};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::io::Read;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
-/// Resolve a path by following symlinks and detecting wrappers.
+const SHEBANG_PREFIX: &str = "#!";
+/// Longest shebang line the kernel reads
+const MAX_SHEBANG_LEN: u64 = 256;
+const ENV_PROGRAM: &str = "env";
+
+/// A detector that can be switched off with [`ResolverOptions::with_detector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Detector {
+    /// Snap, Flatpak and other application launchers
+    Launcher,
+    /// ccache, sccache, distcc and icecc masquerade links
+    CompilerCache,
+    /// Wrappers generated by Nix's `makeCWrapper`
+    NixBinaryWrapper,
+    /// Store wrappers referencing a program of the same name
+    NixProgramName,
+    /// Applets of multi-call binaries such as BusyBox
+    MultiCall,
+    /// Payloads of AppImages and other self-extracting files
+    SelfExtracting,
+}
+
+impl Detector {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Launcher => "launcher",
+            Self::CompilerCache => "compiler cache",
+            Self::NixBinaryWrapper => "Nix binary wrapper",
+            Self::NixProgramName => "Nix program name",
+            Self::MultiCall => "multi-call binary",
+            Self::SelfExtracting => "self-extracting payload",
+        }
+    }
+}
+
+/// Options controlling how a [`Resolver`] follows a path.
+#[derive(Debug, Clone)]
+pub struct ResolverOptions {
+    max_depth: Option<usize>,
+    max_symlinks: Option<usize>,
+    follow_wrappers: bool,
+    follow_interpreters: bool,
+    disabled_detectors: HashSet<Detector>,
+    stores: Stores,
+    root: Option<PathBuf>,
+    homebrew_prefixes: Vec<PathBuf>,
+    collect_metadata: bool,
+    observer: Option<SharedObserver>,
+    cache: Option<Arc<DetectorCache>>,
+}
+
+impl Default for ResolverOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: None,
+            max_symlinks: None,
+            follow_wrappers: true,
+            follow_interpreters: false,
+            disabled_detectors: HashSet::new(),
+            stores: Stores::defaults().clone(),
+            root: None,
+            homebrew_prefixes: homebrew::default_prefixes(),
+            collect_metadata: true,
+            observer: None,
+            cache: None,
+        }
+    }
+}
+
+impl ResolverOptions {
+    /// Default options: wrappers are followed, interpreters are not, and
+    /// every detector and annotation is enabled.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail with [`SymseekError::MaxDepthExceeded`] instead of following a
+    /// chain past this many hops.
+    #[must_use]
+    pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    /// Stop after following this many symlinks and mark the chain as
+    /// truncated. Wrappers and launchers are still detected along the way
+    /// and do not count towards the limit.
+    #[must_use]
+    pub const fn with_max_symlinks(mut self, max_symlinks: usize) -> Self {
+        self.max_symlinks = Some(max_symlinks);
+        self
+    }
+
+    /// Whether to continue at the program a wrapper or launcher runs, or
+    /// stop at the wrapper.
+    #[must_use]
+    pub const fn with_follow_wrappers(mut self, follow: bool) -> Self {
+        self.follow_wrappers = follow;
+        self
+    }
+
+    /// Whether to continue from a script to the interpreter named in its
+    /// shebang, looking up `#!/usr/bin/env` programs in `PATH`.
+    #[must_use]
+    pub const fn with_follow_interpreters(mut self, follow: bool) -> Self {
+        self.follow_interpreters = follow;
+        self
+    }
+
+    /// Enable or disable a single detector.
+    #[must_use]
+    pub fn with_detector(mut self, detector: Detector, enabled: bool) -> Self {
+        if enabled {
+            self.disabled_detectors.remove(&detector);
+        } else {
+            self.disabled_detectors.insert(detector);
+        }
+        self
+    }
+
+    /// Recognize wrappers and store paths in these stores.
+    #[must_use]
+    pub fn with_stores(mut self, stores: Stores) -> Self {
+        self.stores = stores;
+        self
+    }
+
+    /// Replace the directory of the Nix store.
+    #[must_use]
+    pub fn with_nix_store_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.stores = self.stores.with_nix_store_dir(dir);
+        self
+    }
+
+    /// Resolve inside a directory as if it were `/`, e.g. a mounted system
+    /// image. Paths passed to [`Resolver::resolve`], absolute symlink
+    /// targets and wrapper targets are all taken relative to it; the chain
+    /// reports the resulting paths on the host.
+    #[must_use]
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.root = Some(root.into());
+        self
+    }
+
+    /// Also recognize Homebrew kegs below this prefix, in addition to
+    /// `$HOMEBREW_PREFIX` and the default prefixes.
+    #[must_use]
+    pub fn with_homebrew_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.homebrew_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Whether to annotate hops with inode, mount, profile and Homebrew
+    /// information, which costs extra filesystem reads.
+    #[must_use]
+    pub const fn with_metadata(mut self, collect: bool) -> Self {
+        self.collect_metadata = collect;
+        self
+    }
+
+    /// Report every step of every resolution to an observer, e.g. an
+    /// [`EventLog`](crate::core::events::EventLog).
+    #[must_use]
+    pub fn with_observer(mut self, observer: Arc<dyn ResolveObserver>) -> Self {
+        self.observer = Some(SharedObserver(observer));
+        self
+    }
+
+    /// Share wrapper detector results with other resolvers, e.g. a cache
+    /// opened with [`DetectorCache::open_default`] that outlives the run.
+    /// Without one, each resolver keeps its own in-memory cache.
+    #[must_use]
+    pub fn with_cache(mut self, cache: Arc<DetectorCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Resolver {
+        Resolver::new(self)
+    }
+
+    #[must_use]
+    pub fn detector_enabled(&self, detector: Detector) -> bool {
+        !self.disabled_detectors.contains(&detector)
+    }
+
+    /// Directory paths are resolved below, if any.
+    #[must_use]
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+}
+
+/// Follows symlinks, wrappers and launchers from a path to the file that
+/// finally runs.
 ///
-/// Starting from the given path, follows all symlinks and detects wrapper
-/// scripts/binaries, building a chain of all links found.
+/// A resolver is `Send + Sync`, so one can be shared between threads, with
+/// the detector cache shared along with it.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    options: ResolverOptions,
+    cache: Arc<DetectorCache>,
+    profiles: Arc<ProfileCache>,
+    /// Mount table read by the first resolution that needed it
+    mounts: Arc<Mutex<Option<Arc<MountTable>>>>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new(ResolverOptions::default())
+    }
+}
+
+/// Resolve a path with the default options.
 ///
 /// # Errors
 ///
-/// Returns an error if:
-/// - The path is not absolute
-/// - A symlink cannot be read
-/// - A cycle is detected in symlinks
-/// - File metadata or content cannot be read
+/// Returns the same errors as [`Resolver::resolve`].
 pub fn resolve(path: &Path) -> Result<SymlinkChain> {
-    debug!("resolve called for: {}", path.display());
+    Resolver::default().resolve(path)
+}
 
-    if !path.is_absolute() {
-        return Err(SymseekError::InvalidInput {
-            message: "Path must be absolute".to_string(),
-        });
+impl Resolver {
+    #[must_use]
+    pub fn new(options: ResolverOptions) -> Self {
+        let cache = options.cache.clone().unwrap_or_default();
+        let profiles = match &options.root {
+            Some(root) => ProfileCache::new().with_root(root),
+            None => ProfileCache::new(),
+        };
+        Self {
+            options,
+            cache,
+            profiles: Arc::new(profiles),
+            mounts: Arc::default(),
+        }
     }
 
-    let mut chain = SymlinkChain::new(path.to_path_buf());
-    let mut current = path.to_path_buf();
-    let mut visited = HashSet::new();
-    let mut iteration = 0;
+    #[must_use]
+    pub const fn options(&self) -> &ResolverOptions {
+        &self.options
+    }
 
-    loop {
-        iteration += 1;
-        trace!("Iteration {iteration}: processing {}", current.display());
+    #[must_use]
+    pub const fn stores(&self) -> &Stores {
+        &self.options.stores
+    }
 
-        if visited.contains(&current) {
-            debug!("Cycle detected at: {}", current.display());
-            return Err(SymseekError::CycleDetected { path: current });
+    #[must_use]
+    pub fn cache(&self) -> &DetectorCache {
+        &self.cache
+    }
+
+    /// Forget the profile information and mount table remembered from
+    /// earlier resolutions, e.g. after a profile switch was seen.
+    pub fn refresh(&self) {
+        self.profiles.clear();
+        if let Ok(mut mounts) = self.mounts.lock() {
+            *mounts = None;
         }
-        visited.insert(current.clone());
+    }
+
+    /// Get the mount table, reading it on first use.
+    fn mount_table(&self) -> Arc<MountTable> {
+        let Ok(mut mounts) = self.mounts.lock() else {
+            return Arc::new(MountTable::read().unwrap_or_default());
+        };
+        Arc::clone(mounts.get_or_insert_with(|| Arc::new(MountTable::read().unwrap_or_default())))
+    }
+
+    /// Resolve a path by following symlinks and detecting wrappers.
+    ///
+    /// Starting from the given path, follows all symlinks and detects wrapper
+    /// scripts/binaries, building a chain of all links found.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The path is not absolute
+    /// - A symlink cannot be read
+    /// - A cycle is detected in symlinks
+    /// - The chain is longer than the maximum depth
+    /// - File metadata or content cannot be read
+    pub fn resolve(&self, path: &Path) -> Result<SymlinkChain> {
+        let started = Instant::now();
+        self.emit(|| ResolveEvent::Started {
+            path: path.to_path_buf(),
+        });
 
-        let is_symlink = process_symlink(&mut current)?;
+        let result = self.resolve_chain(path);
+        self.emit(|| match &result {
+            Ok(chain) => ResolveEvent::Finished {
+                links: chain.links.len(),
+                truncated: chain.truncated,
+                elapsed: started.elapsed(),
+            },
+            Err(e) => ResolveEvent::Failed {
+                error: e.to_string(),
+                elapsed: started.elapsed(),
+            },
+        });
+        result
+    }
 
-        let file_type = detector::detect_file_type(&current)?;
-        debug!("File type detected: {file_type:?}");
+    /// Resolve many paths in parallel on the rayon thread pool.
+    ///
+    /// Results are in the order of `paths`. Events of concurrent
+    /// resolutions reach the observer interleaved.
+    pub fn resolve_all<P>(&self, paths: &[P]) -> Vec<Result<SymlinkChain>>
+    where
+        P: AsRef<Path> + Sync,
+    {
+        paths
+            .par_iter()
+            .map(|path| self.resolve(path.as_ref()))
+            .collect()
+    }
 
-        if let Some((target, link_type)) = detect_wrapper(&current, &file_type)? {
-            debug!("Found wrapper, following to: {target}");
-            chain.add_link(current.clone(), false, link_type);
-            current = PathBuf::from(target);
-            continue;
+    fn resolve_chain(&self, path: &Path) -> Result<SymlinkChain> {
+        debug!("resolve called for: {}", path.display());
+
+        if !path.is_absolute() {
+            return Err(SymseekError::InvalidInput {
+                message: "Path must be absolute".to_string(),
+            });
         }
 
-        if is_symlink {
-            add_symlink_to_chain(&mut chain, &current, &file_type);
-            if file_type == FileType::Symlink {
+        let path = self.reroot(path);
+        let mut chain = SymlinkChain::new(path.clone());
+        let mut current = path.clone();
+        // Path the current program is executed through, whose name becomes argv[0]
+        let mut executed = path;
+        let mut visited = HashSet::new();
+        let mut iteration = 0;
+        let mut symlinks = 0;
+
+        loop {
+            iteration += 1;
+            trace!("Iteration {iteration}: processing {}", current.display());
+
+            if visited.contains(&current) {
+                debug!("Cycle detected at: {}", current.display());
+                return Err(SymseekError::CycleDetected { path: current });
+            }
+            visited.insert(current.clone());
+            self.emit(|| ResolveEvent::Visited {
+                path: current.clone(),
+                depth: chain.links.len(),
+            });
+
+            if let Some(max_depth) = self.options.max_depth
+                && chain.links.len() >= max_depth
+            {
+                debug!(
+                    "Maximum depth {max_depth} exceeded at: {}",
+                    current.display()
+                );
+                return Err(SymseekError::MaxDepthExceeded {
+                    path: current,
+                    max_depth,
+                });
+            }
+
+            if let Some(max_symlinks) = self.options.max_symlinks
+                && symlinks >= max_symlinks
+                && current.is_symlink()
+            {
+                debug!(
+                    "Stopping after {symlinks} symlinks at: {}",
+                    current.display()
+                );
+                chain.truncated = true;
+                break;
+            }
+
+            let invoked = current.clone();
+            let is_symlink = self.process_symlink(&mut current)?;
+            if is_symlink {
+                symlinks += 1;
+            }
+
+            let file_type = self.detect_file_type(&current)?;
+            debug!("File type detected: {file_type:?}");
+
+            if let Some(app) = self.run_detector(
+                Detector::Launcher,
+                &current,
+                self.follows(Detector::Launcher),
+                || {
+                    Ok(launcher::detect(
+                        &invoked,
+                        &current,
+                        &file_type,
+                        self.options.root.as_deref(),
+                    ))
+                },
+                |app| {
+                    format!(
+                        "{}, entry point {}",
+                        app.app_id,
+                        app.entry_point().display()
+                    )
+                },
+            )? {
+                let entry_point = self.reroot(&app.entry_point());
+                debug!("Found launcher, following to: {}", entry_point.display());
+                let link_type = if file_type == FileType::ElfBinary {
+                    LinkType::Wrapper(WrapperKind::Binary)
+                } else {
+                    LinkType::Wrapper(WrapperKind::Text(ScriptType::Shell))
+                };
+                self.add_link(&mut chain, &current, false, link_type);
+                if let Some(node) = chain.links.last_mut() {
+                    node.launcher = Some(app);
+                }
+                current = entry_point;
+                executed.clone_from(&current);
+                continue;
+            }
+
+            if let Some(cache) = self.run_detector(
+                Detector::CompilerCache,
+                &current,
+                self.follows(Detector::CompilerCache),
+                || {
+                    Ok(compiler_cache::detect(
+                        &executed,
+                        &current,
+                        &file_type,
+                        self.options.root.as_deref(),
+                    ))
+                },
+                |cache| format!("{} for {}", cache.kind.as_str(), cache.compiler.display()),
+            )? {
+                debug!(
+                    "Found compiler cache, following to: {}",
+                    cache.compiler.display()
+                );
+                self.add_link(
+                    &mut chain,
+                    &current,
+                    false,
+                    LinkType::Wrapper(WrapperKind::Binary),
+                );
+                current = self.reroot(&cache.compiler);
+                if let Some(node) = chain.links.last_mut() {
+                    node.compiler_cache = Some(cache);
+                }
+                executed.clone_from(&current);
                 continue;
             }
+
+            if let Some((target, link_type)) = self.detect_wrapper(&current, &file_type)? {
+                debug!("Found wrapper, following to: {target}");
+                self.add_link(&mut chain, &current, false, link_type);
+                current = self.reroot(Path::new(&target));
+                executed.clone_from(&current);
+                continue;
+            }
+
+            if self.options.follow_interpreters
+                && let Some(script_type) = script_type(&file_type)
+                && let Some(interpreter) = self.interpreter(&current)
+            {
+                debug!("Following script to interpreter: {}", interpreter.display());
+                self.add_link(
+                    &mut chain,
+                    &current,
+                    false,
+                    LinkType::Wrapper(WrapperKind::Text(script_type)),
+                );
+                current = interpreter;
+                executed.clone_from(&current);
+                continue;
+            }
+
+            if is_symlink {
+                self.add_symlink_to_chain(&mut chain, &current, &file_type);
+                if file_type == FileType::Symlink {
+                    continue;
+                }
+                break;
+            }
+
+            self.add_terminal_node(&mut chain, &current, &file_type);
             break;
         }
 
-        add_terminal_node(&mut chain, &current, &file_type);
-        break;
+        if let Some(node) = chain.links.last_mut()
+            && node.link_type == LinkType::Terminal(FileKind::Binary)
+        {
+            node.applet = self.run_detector(
+                Detector::MultiCall,
+                &node.target,
+                self.options.detector_enabled(Detector::MultiCall),
+                || Ok(multi_call::detect(&executed, &node.target)),
+                |applet| format!("{} applet {}", applet.kind.as_str(), applet.name),
+            )?;
+        }
+
+        for node in &mut chain.links {
+            node.store_path = self.options.stores.parse(&self.unroot(&node.target));
+        }
+        if self.options.collect_metadata {
+            for node in &mut chain.links {
+                node.file_id = FileId::read(&node.target);
+            }
+            profile::annotate(&mut chain, &self.profiles);
+            let prefixes: Vec<_> = self
+                .options
+                .homebrew_prefixes
+                .iter()
+                .map(|prefix| self.reroot(prefix))
+                .collect();
+            homebrew::annotate(&mut chain, &prefixes);
+            mount::annotate_with(&mut chain, &self.mount_table());
+        }
+
+        debug!(
+            "Resolution complete: {} link(s) in chain",
+            chain.links.len()
+        );
+        Ok(chain)
     }
 
-    debug!(
-        "Resolution complete: {} link(s) in chain",
-        chain.links.len()
-    );
-    Ok(chain)
-}
+    fn emit(&self, event: impl FnOnce() -> ResolveEvent) {
+        if let Some(SharedObserver(observer)) = &self.options.observer {
+            observer.on_event(&event());
+        }
+    }
 
-fn process_symlink(current: &mut PathBuf) -> Result<bool> {
-    match current.read_link() {
-        Ok(target) => {
-            debug!(
-                "Found symlink: {} -> {}",
-                current.display(),
-                target.display()
-            );
-            let resolved = resolve_target(current, &target);
-            current.clone_from(&resolved);
-            Ok(true)
+    /// Run a detector, reporting its outcome and how long it took.
+    fn run_detector<T>(
+        &self,
+        detector: Detector,
+        path: &Path,
+        enabled: bool,
+        detect: impl FnOnce() -> Result<Option<T>>,
+        describe: impl FnOnce(&T) -> String,
+    ) -> Result<Option<T>> {
+        if !enabled {
+            self.emit(|| ResolveEvent::DetectorRan {
+                detector,
+                path: path.to_path_buf(),
+                outcome: DetectorOutcome::Disabled,
+                cached: false,
+                elapsed: std::time::Duration::ZERO,
+            });
+            return Ok(None);
         }
-        Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
-            trace!("Not a symlink: {}", current.display());
-            Ok(false)
+
+        let started = Instant::now();
+        let result = detect();
+        self.emit(|| ResolveEvent::DetectorRan {
+            detector,
+            path: path.to_path_buf(),
+            outcome: match &result {
+                Ok(Some(found)) => DetectorOutcome::Matched(describe(found)),
+                Ok(None) => DetectorOutcome::NotMatched,
+                Err(e) => DetectorOutcome::Failed(e.to_string()),
+            },
+            cached: false,
+            elapsed: started.elapsed(),
+        });
+        result
+    }
+
+    /// Run a wrapper detector through the detector cache.
+    fn run_cached_detector(
+        &self,
+        detector: Detector,
+        path: &Path,
+        detect: impl FnOnce() -> Result<Option<String>>,
+    ) -> Result<Option<String>> {
+        let enabled = self.options.detector_enabled(detector);
+        let key = FileKey::of(path).map(|file| CacheKey {
+            file,
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            root: self.options.root.clone(),
+            stores: self
+                .options
+                .stores
+                .iter()
+                .map(|store| store.dir.clone())
+                .collect(),
+            detector,
+        });
+
+        if enabled
+            && let Some(key) = &key
+            && let Some(target) = self.cache.get(key)
+            // A persisted target may have been garbage collected since
+            && target
+                .as_ref()
+                .is_none_or(|target| self.reroot(Path::new(target)).exists())
+        {
+            self.emit(|| ResolveEvent::DetectorRan {
+                detector,
+                path: path.to_path_buf(),
+                outcome: target
+                    .clone()
+                    .map_or(DetectorOutcome::NotMatched, DetectorOutcome::Matched),
+                cached: true,
+                elapsed: std::time::Duration::ZERO,
+            });
+            return Ok(target);
         }
-        Err(e) => {
-            debug!("Error reading symlink {}: {}", current.display(), e);
-            Err(SymseekError::SymlinkResolution {
-                path: current.clone(),
-                reason: e.to_string(),
-            })
+
+        let target = self.run_detector(detector, path, enabled, detect, Clone::clone)?;
+        if enabled && let Some(key) = key {
+            let persist = self.options.stores.contains(&self.unroot(path));
+            self.cache.insert(key, path, target.clone(), persist);
         }
+        Ok(target)
     }
-}
 
-fn detect_wrapper(current: &Path, file_type: &FileType) -> Result<Option<(String, LinkType)>> {
-    match file_type {
-        FileType::ShellScript => {
-            if let Some(target) = NixBinaryWrapperDetector.detect(current)? {
-                return Ok(Some((
-                    target,
-                    LinkType::Wrapper(WrapperKind::Text(ScriptType::Shell)),
-                )));
+    fn detect_file_type(&self, path: &Path) -> Result<FileType> {
+        let started = Instant::now();
+        let result = detector::detect_file_type(path);
+        self.emit(|| ResolveEvent::Syscall {
+            call: Syscall::ReadHeader,
+            path: path.to_path_buf(),
+            outcome: match &result {
+                Ok(file_type) => format!("{file_type:?}"),
+                Err(e) => e.to_string(),
+            },
+            elapsed: started.elapsed(),
+        });
+        result
+    }
+
+    fn add_link(&self, chain: &mut SymlinkChain, path: &Path, is_final: bool, link_type: LinkType) {
+        self.emit(|| ResolveEvent::HopAdded {
+            index: chain.links.len(),
+            path: path.to_path_buf(),
+            link_type: link_type.clone(),
+        });
+        chain.add_link(path.to_path_buf(), is_final, link_type);
+    }
+
+    /// Whether a following detector is enabled.
+    fn follows(&self, detector: Detector) -> bool {
+        self.options.follow_wrappers && self.options.detector_enabled(detector)
+    }
+
+    /// Map a path inside the root to the host.
+    fn reroot(&self, path: &Path) -> PathBuf {
+        detector::root_path(self.options.root.as_deref(), path)
+    }
+
+    /// Map a host path to the path it has inside the root.
+    fn unroot(&self, path: &Path) -> PathBuf {
+        detector::unroot_path(self.options.root.as_deref(), path)
+    }
+
+    fn process_symlink(&self, current: &mut PathBuf) -> Result<bool> {
+        let started = Instant::now();
+        let result = current.read_link();
+        self.emit(|| ResolveEvent::Syscall {
+            call: Syscall::ReadLink,
+            path: current.clone(),
+            outcome: match &result {
+                Ok(target) => format!("-> {}", target.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                    "not a symlink".to_string()
+                }
+                Err(e) => e.to_string(),
+            },
+            elapsed: started.elapsed(),
+        });
+
+        match result {
+            Ok(target) => {
+                debug!(
+                    "Found symlink: {} -> {}",
+                    current.display(),
+                    target.display()
+                );
+                // Resolve inside the root, so `..` cannot climb out of it
+                let resolved = match &self.options.root {
+                    Some(root) if current.starts_with(root) => {
+                        self.reroot(&resolve_target(&self.unroot(current), &target))
+                    }
+                    Some(_) => resolve_target(current, &target),
+                    None => resolve_target_physically(current, &target),
+                };
+                current.clone_from(&resolved);
+                Ok(true)
             }
-            Ok(NixProgramNameDetector.detect(current)?.map(|target| {
-                (
-                    target,
-                    LinkType::Wrapper(WrapperKind::Text(ScriptType::Shell)),
-                )
-            }))
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidInput => {
+                trace!("Not a symlink: {}", current.display());
+                Ok(false)
+            }
+            Err(e) => {
+                debug!("Error reading symlink {}: {}", current.display(), e);
+                Err(SymseekError::SymlinkResolution {
+                    path: current.clone(),
+                    reason: e.to_string(),
+                })
+            }
+        }
+    }
+
+    fn detect_wrapper(
+        &self,
+        current: &Path,
+        file_type: &FileType,
+    ) -> Result<Option<(String, LinkType)>> {
+        if !self.options.follow_wrappers {
+            return Ok(None);
         }
-        FileType::ElfBinary => {
-            if let Some(target) = NixBinaryWrapperDetector.detect(current)? {
-                return Ok(Some((target, LinkType::Wrapper(WrapperKind::Binary))));
+        let link_type = match file_type {
+            FileType::ShellScript => LinkType::Wrapper(WrapperKind::Text(ScriptType::Shell)),
+            FileType::ElfBinary => LinkType::Wrapper(WrapperKind::Binary),
+            _ => return Ok(None),
+        };
+
+        // Both detectors scan the same content, so read it at most once
+        let mut content = None;
+        let mut detect = |detector: &dyn WrapperDetector| -> Result<Option<String>> {
+            if !detector.applies_to(current) {
+                return Ok(None);
+            }
+            if content.is_none() {
+                content = Some(detector::read_content(current)?);
             }
-            Ok(NixProgramNameDetector
-                .detect(current)?
-                .map(|target| (target, LinkType::Wrapper(WrapperKind::Binary))))
+            Ok(content
+                .as_ref()
+                .and_then(Option::as_deref)
+                .and_then(|content| detector.detect_in(current, content)))
+        };
+
+        let root = self.options.root.as_deref();
+        if let Some(target) =
+            self.run_cached_detector(Detector::NixBinaryWrapper, current, || {
+                detect(&NixBinaryWrapperDetector::new(&self.options.stores).with_root(root))
+            })?
+        {
+            return Ok(Some((target, link_type)));
+        }
+        let target = self.run_cached_detector(Detector::NixProgramName, current, || {
+            detect(&NixProgramNameDetector::new(&self.options.stores).with_root(root))
+        })?;
+        Ok(target.map(|target| (target, link_type)))
+    }
+
+    /// Find the interpreter a script's shebang runs, if it exists.
+    fn interpreter(&self, script: &Path) -> Option<PathBuf> {
+        let mut line = String::new();
+        fs::File::open(script)
+            .ok()?
+            .take(MAX_SHEBANG_LEN)
+            .read_to_string(&mut line)
+            .ok()?;
+        let line = line.lines().next()?.strip_prefix(SHEBANG_PREFIX)?;
+
+        let mut words = line.split_whitespace();
+        let interpreter = self.reroot(Path::new(words.next()?));
+        if interpreter
+            .file_name()
+            .is_none_or(|name| name != ENV_PROGRAM)
+        {
+            return interpreter.is_file().then_some(interpreter);
+        }
+
+        // Skip options such as `-S` and variable assignments
+        let program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+        if program.contains('/') {
+            let program = self.reroot(Path::new(program));
+            return program.is_file().then_some(program);
         }
-        _ => Ok(None),
+        env::split_paths(&env::var_os("PATH")?)
+            .map(|dir| self.reroot(&dir.join(program)))
+            .find(|candidate| candidate.is_file())
+    }
+
+    fn add_symlink_to_chain(&self, chain: &mut SymlinkChain, path: &Path, file_type: &FileType) {
+        let link_type = match file_type {
+            FileType::Symlink => LinkType::Symlink,
+            FileType::ElfBinary => LinkType::Terminal(FileKind::Binary),
+            FileType::SelfExtracting(kind) => LinkType::Terminal(self_extracting_kind(*kind)),
+            _ => LinkType::Terminal(FileKind::Text),
+        };
+        let is_final = *file_type != FileType::Symlink;
+        self.add_link(chain, path, is_final, link_type);
+        self.inspect_bundle(chain, path, file_type);
+    }
+
+    fn add_terminal_node(&self, chain: &mut SymlinkChain, path: &Path, file_type: &FileType) {
+        trace!("Reached terminal node: {}", path.display());
+        let terminal_link_type = match file_type {
+            FileType::ElfBinary | FileType::OtherBinary => LinkType::Terminal(FileKind::Binary),
+            FileType::SelfExtracting(kind) => LinkType::Terminal(self_extracting_kind(*kind)),
+            _ => LinkType::Terminal(FileKind::Text),
+        };
+        self.add_link(chain, path, true, terminal_link_type);
+        self.inspect_bundle(chain, path, file_type);
+    }
+
+    fn inspect_bundle(&self, chain: &mut SymlinkChain, path: &Path, file_type: &FileType) {
+        if let FileType::SelfExtracting(kind) = file_type
+            && let Some(node) = chain.links.last_mut()
+        {
+            // Inspection cannot fail; an unreadable payload leaves fields unset
+            node.bundle = self
+                .run_detector(
+                    Detector::SelfExtracting,
+                    path,
+                    self.options.detector_enabled(Detector::SelfExtracting),
+                    || Ok(Some(self_extracting::inspect(path, *kind))),
+                    |bundle| format!("{:?}", bundle.kind),
+                )
+                .unwrap_or_default();
+        }
+    }
+}
+
+const fn script_type(file_type: &FileType) -> Option<ScriptType> {
+    match file_type {
+        FileType::ShellScript => Some(ScriptType::Shell),
+        FileType::PythonScript => Some(ScriptType::Python),
+        FileType::PerlScript => Some(ScriptType::Perl),
+        FileType::OtherScript => Some(ScriptType::Unknown),
+        _ => None,
     }
 }
 
-fn add_symlink_to_chain(chain: &mut SymlinkChain, path: &Path, file_type: &FileType) {
-    let link_type = match file_type {
-        FileType::Symlink => LinkType::Symlink,
-        FileType::ElfBinary => LinkType::Terminal(FileKind::Binary),
-        _ => LinkType::Terminal(FileKind::Text),
-    };
-    let is_final = *file_type != FileType::Symlink;
-    chain.add_link(path.to_path_buf(), is_final, link_type);
+const fn self_extracting_kind(kind: SelfExtractingKind) -> FileKind {
+    match kind {
+        SelfExtractingKind::Makeself => FileKind::Text,
+        _ => FileKind::Binary,
+    }
 }
 
-fn add_terminal_node(chain: &mut SymlinkChain, path: &Path, file_type: &FileType) {
-    trace!("Reached terminal node: {}", path.display());
-    let terminal_link_type = match file_type {
-        FileType::ElfBinary | FileType::OtherBinary => LinkType::Terminal(FileKind::Binary),
-        _ => LinkType::Terminal(FileKind::Text),
-    };
-    chain.add_link(path.to_path_buf(), true, terminal_link_type);
+/// Like [`resolve_target`], but take `..` from where the link's directory
+/// really is, as the kernel does: `/bin/x -> ../share/x` leads to
+/// `/usr/share/x` when `/bin` links to `usr/bin`.
+fn resolve_target_physically(current: &Path, target: &Path) -> PathBuf {
+    let climbs = target
+        .components()
+        .any(|component| component == std::path::Component::ParentDir);
+    if climbs
+        && !target.is_absolute()
+        && let Some(parent) = current.parent()
+        && let Ok(parent) = fs::canonicalize(parent)
+    {
+        return path_clean::clean(parent.join(target));
+    }
+    resolve_target(current, target)
 }
 
 fn resolve_target(current: &Path, target: &Path) -> PathBuf {
@@ -356,4 +1092,452 @@ mod tests {
         assert_eq!(chain.links[0].target, link2.path());
         assert_eq!(chain.links[1].target, binary);
     }
+
+    #[test]
+    fn test_resolve_guix_wrapper_in_custom_store() {
+        use crate::core::store::{Store, StoreFlavor};
+
+        let temp = TempDir::new().unwrap();
+        temp.child("store/abc123-hello-2.12/bin")
+            .create_dir_all()
+            .unwrap();
+        let real = create_executable(
+            &temp,
+            "store/abc123-hello-2.12/bin/.hello-real",
+            &[0x7f, b'E', b'L', b'F', 0x02, 0x01, 0x01, 0x00],
+        );
+        let wrapper = create_executable(
+            &temp,
+            "store/abc123-hello-2.12/bin/hello",
+            format!("#!/bin/sh\nexec -a \"$0\" \"{}\" \"$@\"\n", real.display()).as_bytes(),
+        );
+        let link = temp.child("hello");
+        link.symlink_to_file(&wrapper).unwrap();
+
+        let stores = Stores::new(vec![Store::new(
+            temp.child("store").path(),
+            StoreFlavor::Guix,
+        )]);
+        let chain = ResolverOptions::new()
+            .with_stores(stores)
+            .build()
+            .resolve(link.path())
+            .unwrap();
+
+        assert_eq!(chain.links.len(), 2);
+        assert!(matches!(
+            chain.links[0].link_type,
+            LinkType::Wrapper(WrapperKind::Text(ScriptType::Shell))
+        ));
+        assert_eq!(chain.links[1].target, real);
+        let store_path = chain.links[1].store_path.as_ref().unwrap();
+        assert_eq!(store_path.flavor, StoreFlavor::Guix);
+        assert_eq!(store_path.name, "hello");
+    }
+
+    #[test]
+    fn test_resolve_snap_launcher() {
+        let temp = TempDir::new().unwrap();
+        temp.child("snap/hello/42/meta").create_dir_all().unwrap();
+        temp.child("snap/hello/42/meta/snap.yaml")
+            .write_str("name: hello\napps:\n  hello:\n    command: bin/hello\n")
+            .unwrap();
+        temp.child("snap/hello/42/bin").create_dir_all().unwrap();
+        let entry_point = create_executable(&temp, "snap/hello/42/bin/hello", b"#!/bin/sh\n");
+        temp.child("snap/hello/current")
+            .symlink_to_dir("42")
+            .unwrap();
+        temp.child("usr/bin").create_dir_all().unwrap();
+        let snap = create_executable(
+            &temp,
+            "usr/bin/snap",
+            &[0x7f, b'E', b'L', b'F', 0x02, 0x01, 0x01, 0x00],
+        );
+        temp.child("snap/bin").create_dir_all().unwrap();
+        let link = temp.child("snap/bin/hello");
+        link.symlink_to_file(&snap).unwrap();
+
+        let chain = resolve(link.path()).unwrap();
+
+        assert_eq!(chain.links.len(), 2);
+        assert_eq!(chain.links[0].target, snap);
+        assert!(matches!(
+            chain.links[0].link_type,
+            LinkType::Wrapper(WrapperKind::Binary)
+        ));
+        let app = chain.links[0].launcher.as_ref().unwrap();
+        assert_eq!(app.app_id, "hello.hello");
+        assert_eq!(
+            std::fs::canonicalize(&chain.links[1].target).unwrap(),
+            std::fs::canonicalize(entry_point).unwrap()
+        );
+        assert!(chain.links[1].is_final);
+    }
+
+    #[test]
+    fn test_resolve_appimage_bundle() {
+        use crate::core::detector::self_extracting::test_appimage;
+
+        let temp = TempDir::new().unwrap();
+        let appimage = create_executable(
+            &temp,
+            "Hello.AppImage",
+            &test_appimage::build(2048, &[("AppRun", None), ("hello.desktop", None)]),
+        );
+        let link = temp.child("hello");
+        link.symlink_to_file(&appimage).unwrap();
+
+        let chain = resolve(link.path()).unwrap();
+
+        assert_eq!(chain.links.len(), 1);
+        assert!(matches!(
+            chain.links[0].link_type,
+            LinkType::Terminal(FileKind::Binary)
+        ));
+        let bundle = chain.links[0].bundle.as_ref().unwrap();
+        assert_eq!(bundle.payload_offset, Some(2048));
+        assert_eq!(bundle.entry_point.as_deref(), Some("AppRun"));
+        assert_eq!(bundle.desktop_file.as_deref(), Some("hello.desktop"));
+    }
+
+    #[test]
+    fn test_resolve_busybox_applet() {
+        let temp = TempDir::new().unwrap();
+        let busybox = create_executable(
+            &temp,
+            "busybox",
+            b"\x7fELF\x02\x01\x01\x00\0BusyBox v1.36.1\0[\0[[\0cat\0ls\0\x01",
+        );
+        let link = temp.child("ls");
+        link.symlink_to_file(&busybox).unwrap();
+
+        let chain = resolve(link.path()).unwrap();
+
+        let applet = chain.links[0].applet.as_ref().unwrap();
+        assert_eq!(applet.kind, multi_call::MultiCallKind::Busybox);
+        assert_eq!(applet.name, "ls");
+        assert!(applet.compiled_in);
+    }
+
+    #[test]
+    fn test_resolver_max_depth() {
+        let temp = TempDir::new().unwrap();
+        let target = create_executable(&temp, "target", b"#!/bin/sh\n");
+        temp.child("link2").symlink_to_file(&target).unwrap();
+        temp.child("link1")
+            .symlink_to_file(temp.child("link2").path())
+            .unwrap();
+
+        let resolver = ResolverOptions::new().with_max_depth(1).build();
+        assert_eq!(
+            resolver
+                .resolve(temp.child("link2").path())
+                .unwrap()
+                .links
+                .len(),
+            1
+        );
+        assert!(matches!(
+            resolver.resolve(temp.child("link1").path()),
+            Err(SymseekError::MaxDepthExceeded { max_depth: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolver_follow_interpreters() {
+        let temp = TempDir::new().unwrap();
+        let interpreter = create_executable(&temp, "interp", b"\x7fELF\x02\x01\x01\x00");
+        let script = create_executable(
+            &temp,
+            "script",
+            format!("#!{} -e\nexit 0\n", interpreter.display()).as_bytes(),
+        );
+
+        let chain = resolve(&script).unwrap();
+        assert_eq!(chain.links.len(), 1);
+
+        let chain = ResolverOptions::new()
+            .with_follow_interpreters(true)
+            .with_metadata(false)
+            .build()
+            .resolve(&script)
+            .unwrap();
+        assert_eq!(chain.links.len(), 2);
+        assert!(matches!(
+            chain.links[0].link_type,
+            LinkType::Wrapper(WrapperKind::Text(_))
+        ));
+        assert_eq!(chain.links[1].target, interpreter);
+        assert!(chain.links[1].file_id.is_none());
+    }
+
+    #[test]
+    fn test_resolver_follow_missing_interpreter() {
+        let temp = TempDir::new().unwrap();
+        let script = create_executable(&temp, "script", b"#!/nonexistent/sh\nexit 0\n");
+
+        let chain = ResolverOptions::new()
+            .with_follow_interpreters(true)
+            .with_metadata(false)
+            .build()
+            .resolve(&script)
+            .unwrap();
+        assert_eq!(chain.links.len(), 1);
+        assert_eq!(chain.final_target(), script);
+    }
+
+    #[test]
+    fn test_resolver_without_wrappers() {
+        let temp = TempDir::new().unwrap();
+        let ccache = create_executable(&temp, "bin/ccache", b"\x7fELF\x02\x01\x01\x00");
+        temp.child("lib/ccache").create_dir_all().unwrap();
+        let link = temp.child("lib/ccache/gcc");
+        link.symlink_to_file(&ccache).unwrap();
+
+        for options in [
+            ResolverOptions::new().with_follow_wrappers(false),
+            ResolverOptions::new().with_detector(Detector::CompilerCache, false),
+        ] {
+            let chain = options.build().resolve(link.path()).unwrap();
+            assert_eq!(chain.links.len(), 1);
+            assert!(chain.links[0].compiler_cache.is_none());
+            assert!(chain.links[0].is_final);
+        }
+    }
+
+    #[test]
+    fn test_resolver_root_prefix() {
+        let temp = TempDir::new().unwrap();
+        temp.child("usr/bin").create_dir_all().unwrap();
+        let target = create_executable(&temp, "usr/bin/python3.12", b"#!/bin/sh\n");
+        temp.child("etc/alternatives").create_dir_all().unwrap();
+        // Resolved inside the root, `..` stops at its top
+        std::os::unix::fs::symlink(
+            "../../../../usr/bin/python3.12",
+            temp.child("etc/alternatives/python").path(),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(
+            "/etc/alternatives/python",
+            temp.child("usr/bin/python").path(),
+        )
+        .unwrap();
+
+        let chain = ResolverOptions::new()
+            .with_root(temp.path())
+            .build()
+            .resolve(Path::new("/usr/bin/python"))
+            .unwrap();
+
+        assert_eq!(chain.origin, temp.child("usr/bin/python").path());
+        assert_eq!(chain.links.len(), 2);
+        assert_eq!(
+            chain.links[0].target,
+            temp.child("etc/alternatives/python").path()
+        );
+        assert_eq!(chain.links[1].target, target);
+    }
+
+    #[test]
+    fn test_resolver_root_flatpak_launcher() {
+        let temp = TempDir::new().unwrap();
+        let app = temp.child("var/lib/flatpak/app/org.foo.App/x86_64/stable");
+        app.child("abc123/metadata")
+            .write_str("[Application]\nname=org.foo.App\ncommand=foo\n")
+            .unwrap();
+        let entry_point = create_executable(
+            &temp,
+            "var/lib/flatpak/app/org.foo.App/x86_64/stable/abc123/files/bin/foo",
+            b"#!/bin/sh\n",
+        );
+        app.child("active").symlink_to_dir("abc123").unwrap();
+        create_executable(
+            &temp,
+            "usr/bin/foo",
+            b"#!/bin/sh\nexec /usr/bin/flatpak run --branch=stable --arch=x86_64 org.foo.App \"$@\"\n",
+        );
+
+        let chain = ResolverOptions::new()
+            .with_root(temp.path())
+            .build()
+            .resolve(Path::new("/usr/bin/foo"))
+            .unwrap();
+
+        assert_eq!(chain.links.len(), 2);
+        let launcher = chain.links[0].launcher.as_ref().unwrap();
+        assert_eq!(
+            launcher.deployment,
+            Path::new("/var/lib/flatpak/app/org.foo.App/x86_64/stable/abc123")
+        );
+        assert_eq!(chain.links[1].target, entry_point);
+    }
+
+    #[test]
+    fn test_resolve_relative_link_in_symlinked_directory() {
+        let temp = TempDir::new().unwrap();
+        let target = create_executable(&temp, "usr/share/tool/run", b"#!/bin/sh\n");
+        temp.child("usr/bin").create_dir_all().unwrap();
+        temp.child("usr/bin/tool")
+            .symlink_to_file("../share/tool/run")
+            .unwrap();
+        temp.child("bin").symlink_to_dir("usr/bin").unwrap();
+
+        let chain = resolve(temp.child("bin/tool").path()).unwrap();
+
+        assert_eq!(
+            chain.final_target(),
+            fs::canonicalize(&target).unwrap().as_path()
+        );
+    }
+
+    #[test]
+    fn test_resolver_max_symlinks_truncates() {
+        let temp = TempDir::new().unwrap();
+        let target = create_executable(&temp, "target", b"#!/bin/sh\n");
+        temp.child("link3").symlink_to_file(&target).unwrap();
+        temp.child("link2")
+            .symlink_to_file(temp.child("link3").path())
+            .unwrap();
+        temp.child("link1")
+            .symlink_to_file(temp.child("link2").path())
+            .unwrap();
+
+        let resolver = ResolverOptions::new().with_max_symlinks(2).build();
+        let chain = resolver.resolve(temp.child("link1").path()).unwrap();
+        assert!(chain.truncated);
+        assert_eq!(chain.links.len(), 2);
+        assert_eq!(chain.final_target(), temp.child("link3").path());
+        assert!(!chain.links[1].is_final);
+
+        let chain = resolver.resolve(temp.child("link2").path()).unwrap();
+        assert!(!chain.truncated);
+        assert_eq!(chain.final_target(), target);
+    }
+
+    #[test]
+    fn test_resolver_reports_events() {
+        use crate::core::events::{EventLog, ResolveEvent};
+
+        let temp = TempDir::new().unwrap();
+        let target = create_executable(&temp, "target", b"\x7fELF\x02\x01\x01\x00");
+        let link = temp.child("link");
+        link.symlink_to_file(&target).unwrap();
+
+        let log = Arc::new(EventLog::new());
+        let resolver = ResolverOptions::new()
+            .with_observer(log.clone())
+            .with_detector(Detector::Launcher, false)
+            .build();
+        resolver.resolve(link.path()).unwrap();
+        let events = log.take();
+
+        assert!(matches!(events.first(), Some(ResolveEvent::Started { .. })));
+        assert!(matches!(
+            events.last(),
+            Some(ResolveEvent::Finished { links: 1, .. })
+        ));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ResolveEvent::Syscall { call: Syscall::ReadLink, outcome, .. } if outcome.starts_with("->")
+        )));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ResolveEvent::DetectorRan {
+                detector: Detector::Launcher,
+                outcome: DetectorOutcome::Disabled,
+                ..
+            }
+        )));
+        assert!(events.iter().any(|event| matches!(
+            event,
+            ResolveEvent::HopAdded { index: 0, path, .. } if *path == target
+        )));
+
+        assert!(resolver.resolve(&temp.path().join("missing")).is_err());
+        assert!(matches!(
+            log.take().last(),
+            Some(ResolveEvent::Failed { .. })
+        ));
+    }
+
+    #[test]
+    fn test_resolver_caches_wrapper_detection() {
+        use crate::core::events::{EventLog, ResolveEvent};
+        use crate::core::store::{Store, StoreFlavor};
+
+        let temp = TempDir::new().unwrap();
+        temp.child("store/abc123-hello-2.12/bin")
+            .create_dir_all()
+            .unwrap();
+        let real = create_executable(
+            &temp,
+            "store/abc123-hello-2.12/bin/.hello-real",
+            b"\x7fELF\x02\x01\x01\x00",
+        );
+        let wrapper = create_executable(
+            &temp,
+            "store/abc123-hello-2.12/bin/hello",
+            format!("#!/bin/sh\nexec \"{}\" \"$@\"\n", real.display()).as_bytes(),
+        );
+
+        let cache = Arc::new(DetectorCache::new());
+        let log = Arc::new(EventLog::new());
+        let resolver = ResolverOptions::new()
+            .with_stores(Stores::new(vec![Store::new(
+                temp.child("store").path(),
+                StoreFlavor::Guix,
+            )]))
+            .with_cache(cache.clone())
+            .with_observer(log.clone())
+            .build();
+        let cached_runs = |events: Vec<ResolveEvent>| {
+            events
+                .iter()
+                .filter(|event| matches!(event, ResolveEvent::DetectorRan { cached: true, .. }))
+                .count()
+        };
+
+        resolver.resolve(&wrapper).unwrap();
+        assert_eq!(cached_runs(log.take()), 0);
+
+        let chain = resolver.resolve(&wrapper).unwrap();
+        assert_eq!(chain.final_target(), real);
+        // Both Nix detectors, on the wrapper and on the program it runs
+        assert_eq!(cached_runs(log.take()), 4);
+    }
+
+    #[test]
+    fn test_resolver_reads_mount_table_once() {
+        let resolver = Resolver::default();
+        let table = resolver.mount_table();
+        assert!(Arc::ptr_eq(&table, &resolver.clone().mount_table()));
+
+        resolver.refresh();
+        assert!(!Arc::ptr_eq(&table, &resolver.mount_table()));
+    }
+
+    #[test]
+    fn test_resolver_resolve_all_keeps_order() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Resolver>();
+        assert_send_sync::<Result<SymlinkChain>>();
+
+        let temp = TempDir::new().unwrap();
+        let target = create_executable(&temp, "target", b"\x7fELF\x02\x01\x01\x00");
+        let links: Vec<PathBuf> = (0..16)
+            .map(|i| {
+                let link = temp.child(format!("link{i}"));
+                link.symlink_to_file(&target).unwrap();
+                link.to_path_buf()
+            })
+            .chain([temp.path().join("missing")])
+            .collect();
+
+        let results = Resolver::default().resolve_all(&links);
+        assert_eq!(results.len(), links.len());
+        for (link, result) in links.iter().zip(&results[..16]) {
+            assert_eq!(&result.as_ref().unwrap().origin, link);
+        }
+        assert!(results[16].is_err());
+    }
 }
@@ -1,7 +1,10 @@
 use crate::core::types::FileLocation;
 use crate::error::{Result, SymseekError};
 use log::{debug, trace};
-use std::{env, path};
+use std::collections::{BTreeMap, HashSet};
+use std::ffi::OsStr;
+use std::os::unix::fs::PermissionsExt;
+use std::{env, fs, path};
 
 /// Find a file by name in the current directory or PATH.
 ///
@@ -49,6 +52,30 @@ pub fn find_file(name: &str) -> Result<FileLocation> {
     })
 }
 
+/// List every location where `name` would be looked up, whether or not it
+/// currently exists.
+///
+/// Used to notice a target appearing in a location that did not have it yet.
+///
+/// # Errors
+///
+/// Returns an error if the current directory cannot be determined or the
+/// PATH environment variable is not set.
+pub fn candidate_paths(name: &str) -> Result<Vec<path::PathBuf>> {
+    if name.contains(path::MAIN_SEPARATOR) {
+        let cwd = env::current_dir().map_err(|e| SymseekError::Io {
+            context: "Failed to get current directory".to_string(),
+            source: e,
+        })?;
+        return Ok(vec![cwd.join(name)]);
+    }
+
+    let paths = env::var("PATH").map_err(|_| SymseekError::InvalidInput {
+        message: "PATH environment variable not found".to_string(),
+    })?;
+    Ok(env::split_paths(&paths).map(|dir| dir.join(name)).collect())
+}
+
 fn search_in_cwd(name: &str) -> Result<Option<path::PathBuf>> {
     let cwd = env::current_dir().map_err(|e| SymseekError::Io {
         context: "Failed to get current directory".to_string(),
@@ -104,13 +131,79 @@ fn search_in_path(name: &str) -> Result<Vec<path::PathBuf>> {
     Ok(found_paths)
 }
 
+/// List every executable in every PATH directory.
+///
+/// Returns each command name with all of its matches in PATH order, so the
+/// first match is the one a shell would run. Symlinks whose target does not
+/// exist are included, since they are exactly the broken entries worth
+/// reporting. Directories that cannot be read are skipped.
+///
+/// # Errors
+///
+/// Returns an error if the PATH environment variable is not set.
+pub fn list_path_executables() -> Result<BTreeMap<String, Vec<path::PathBuf>>> {
+    let paths = env::var_os("PATH").ok_or_else(|| SymseekError::InvalidInput {
+        message: "PATH environment variable not found".to_string(),
+    })?;
+    Ok(list_executables_in(&paths))
+}
+
+/// Same as [`list_path_executables`] for the directories of `paths`.
+///
+/// Directories are told apart by their canonical path, so one reached
+/// again through a symlink, like `/bin` on merged-usr systems, is only
+/// listed the first time.
+fn list_executables_in(paths: &OsStr) -> BTreeMap<String, Vec<path::PathBuf>> {
+    let mut commands: BTreeMap<String, Vec<path::PathBuf>> = BTreeMap::new();
+    let mut seen_dirs = HashSet::new();
+
+    for dir in env::split_paths(paths) {
+        let canonical = fs::canonicalize(&dir).unwrap_or_else(|_| dir.clone());
+        if !seen_dirs.insert(canonical) {
+            trace!("Skipping duplicate PATH entry: {}", dir.display());
+            continue;
+        }
+
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("Skipping unreadable PATH entry {}: {e}", dir.display());
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let full_path = entry.path();
+            if !is_executable_entry(&full_path) {
+                continue;
+            }
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                debug!("Skipping non UTF-8 name: {}", full_path.display());
+                continue;
+            };
+            trace!("Found executable: {}", full_path.display());
+            commands.entry(name).or_default().push(full_path);
+        }
+    }
+
+    debug!("Found {} commands in PATH", commands.len());
+    commands
+}
+
+fn is_executable_entry(path: &path::Path) -> bool {
+    match fs::metadata(path) {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        // Dangling symlinks are kept so they can be reported as broken
+        Err(_) => fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_symlink()),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::types::FileLocation;
     use assert_fs::TempDir;
     use assert_fs::prelude::*;
-    use std::os::unix::fs::PermissionsExt;
 
     fn create_executable(path: &std::path::PathBuf) {
         std::fs::File::create(path).unwrap();
@@ -250,4 +343,50 @@ mod tests {
             FileLocation::CurrentDirectory(_) => panic!("Expected PathEnvironment for binary name"),
         }
     }
+
+    #[test]
+    fn test_list_path_executables() {
+        let temp = TempDir::new().unwrap();
+
+        let bin1 = temp.child("bin1");
+        bin1.create_dir_all().unwrap();
+        let bin2 = temp.child("bin2");
+        bin2.create_dir_all().unwrap();
+
+        create_executable(&bin1.child("cmd").to_path_buf());
+        create_executable(&bin2.child("cmd").to_path_buf());
+        create_executable(&bin2.child("other").to_path_buf());
+        bin2.child("not-executable").touch().unwrap();
+        bin2.child("dangling")
+            .symlink_to_file(temp.child("missing").path())
+            .unwrap();
+
+        let path_value = format!(
+            "{}:{}:{}",
+            bin1.path().display(),
+            bin2.path().display(),
+            temp.child("nonexistent").path().display()
+        );
+        let commands = list_executables_in(OsStr::new(&path_value));
+        assert_eq!(
+            commands.keys().collect::<Vec<_>>(),
+            vec!["cmd", "dangling", "other"]
+        );
+        assert_eq!(commands["cmd"].len(), 2);
+        assert!(commands["cmd"][0].starts_with(bin1.path()));
+    }
+
+    #[test]
+    fn test_list_skips_symlinked_path_directory() {
+        let temp = TempDir::new().unwrap();
+        let usr_bin = temp.child("usr/bin");
+        usr_bin.create_dir_all().unwrap();
+        create_executable(&usr_bin.child("cmd").to_path_buf());
+        temp.child("bin").symlink_to_dir("usr/bin").unwrap();
+
+        let path_value = env::join_paths([usr_bin.path(), temp.child("bin").path()]).unwrap();
+        let commands = list_executables_in(&path_value);
+
+        assert_eq!(commands["cmd"], vec![usr_bin.child("cmd").to_path_buf()]);
+    }
 }
@@ -0,0 +1,149 @@
+use crate::core::types::{FileId, SymlinkChain};
+use std::fs;
+use std::path::PathBuf;
+
+/// Role of a PATH match among all matches of the same name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchStatus {
+    /// First match in PATH, the one that actually runs
+    Active,
+    /// Shadowed, but resolves to the same file as the active match
+    Duplicate,
+    /// Shadowed and resolves to a different file than the active match
+    Shadowed,
+}
+
+impl MatchStatus {
+    /// Check whether this match is hidden behind the active one.
+    #[must_use]
+    pub const fn is_shadowed(self) -> bool {
+        !matches!(self, Self::Active)
+    }
+}
+
+/// Classify PATH matches given in PATH order.
+///
+/// Final targets are compared after canonicalization, so that e.g. `/bin/ls`
+/// and `/usr/bin/ls` on a merged-`/usr` system count as duplicates. Hard
+/// links to the active target count as duplicates as well.
+#[must_use]
+pub fn match_statuses(chains: &[SymlinkChain]) -> Vec<MatchStatus> {
+    let Some((active, rest)) = chains.split_first() else {
+        return Vec::new();
+    };
+    let active_target = canonical_target(active);
+
+    std::iter::once(MatchStatus::Active)
+        .chain(rest.iter().map(|chain| {
+            if canonical_target(chain) == active_target || same_final_file(chain, active) {
+                MatchStatus::Duplicate
+            } else {
+                MatchStatus::Shadowed
+            }
+        }))
+        .collect()
+}
+
+/// For every PATH match, find the first earlier match whose final target
+/// is the same file on disk, even when reached through a hard link or bind
+/// mount rather than a symlink.
+#[must_use]
+pub fn same_file_matches(chains: &[SymlinkChain]) -> Vec<Option<usize>> {
+    chains
+        .iter()
+        .enumerate()
+        .map(|(idx, chain)| {
+            chains[..idx]
+                .iter()
+                .position(|earlier| same_final_file(chain, earlier))
+        })
+        .collect()
+}
+
+fn same_final_file(a: &SymlinkChain, b: &SymlinkChain) -> bool {
+    match (final_file_id(a), final_file_id(b)) {
+        (Some(a), Some(b)) => a.same_file(&b),
+        _ => false,
+    }
+}
+
+fn final_file_id(chain: &SymlinkChain) -> Option<FileId> {
+    chain.links.last().and_then(|node| node.file_id)
+}
+
+fn canonical_target(chain: &SymlinkChain) -> PathBuf {
+    let target = chain.final_target();
+    fs::canonicalize(target).unwrap_or_else(|_| target.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::{FileKind, LinkType};
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    fn chain_to(origin: &str, target: PathBuf) -> SymlinkChain {
+        let mut chain = SymlinkChain::new(PathBuf::from(origin));
+        chain.add_link(target, true, LinkType::Terminal(FileKind::Binary));
+        chain
+    }
+
+    #[test]
+    fn test_match_statuses_empty() {
+        assert!(match_statuses(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_match_statuses_duplicate_and_shadowed() {
+        let temp = TempDir::new().unwrap();
+        let real = temp.child("real");
+        real.touch().unwrap();
+        let other = temp.child("other");
+        other.touch().unwrap();
+        let dir_link = temp.child("dir-link");
+        dir_link.symlink_to_dir(temp.path()).unwrap();
+
+        let chains = vec![
+            chain_to("/bin/cmd", real.to_path_buf()),
+            chain_to("/usr/bin/cmd", dir_link.path().join("real")),
+            chain_to("/usr/local/bin/cmd", other.to_path_buf()),
+        ];
+
+        assert_eq!(
+            match_statuses(&chains),
+            vec![
+                MatchStatus::Active,
+                MatchStatus::Duplicate,
+                MatchStatus::Shadowed
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hard_links_are_the_same_file() {
+        let temp = TempDir::new().unwrap();
+        let bin = temp.child("bin/cmd");
+        bin.touch().unwrap();
+        temp.child("local/bin").create_dir_all().unwrap();
+        let hard_link = temp.child("local/bin/cmd");
+        std::fs::hard_link(bin.path(), hard_link.path()).unwrap();
+        let other = temp.child("other/cmd");
+        other.touch().unwrap();
+
+        let chains: Vec<_> = [bin.path(), other.path(), hard_link.path()]
+            .into_iter()
+            .map(|path| crate::core::resolver::resolve(path).unwrap())
+            .collect();
+
+        assert_eq!(same_file_matches(&chains), vec![None, None, Some(0)]);
+        assert_eq!(
+            match_statuses(&chains),
+            vec![
+                MatchStatus::Active,
+                MatchStatus::Shadowed,
+                MatchStatus::Duplicate
+            ]
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use log::debug;
+use regex::Regex;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+pub const NIX_STORE_DIR: &str = "/nix/store";
+pub const GUIX_STORE_DIR: &str = "/gnu/store";
+
+/// Environment variable Nix reads a relocated store directory from.
+pub const NIX_STORE_DIR_ENV: &str = "NIX_STORE_DIR";
+
+/// Number of hash characters shown when a store path is abbreviated.
+const SHORT_HASH_LEN: usize = 7;
+
+/// Splits the entry directly below the store into hash and name, anchored
+/// counterpart of the per-store path regex built by `Stores`.
+static STORE_ENTRY_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([a-z0-9]+)-([^/\s]+)$").unwrap());
+
+/// The default Nix and Guix stores, honouring `NIX_STORE_DIR`.
+static DEFAULT_STORES: LazyLock<Stores> = LazyLock::new(Stores::from_env);
+
+/// Package manager owning a store directory.
+///
+/// Guix stores use the same `<hash>-<name>-<version>` layout as Nix, so the
+/// flavor only matters for flavor-specific conventions such as wrapper names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreFlavor {
+    Nix,
+    Guix,
+}
+
+impl StoreFlavor {
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Nix => "nix",
+            Self::Guix => "guix",
+        }
+    }
+}
+
+/// A store directory such as `/nix/store` or `/gnu/store`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Store {
+    pub dir: PathBuf,
+    pub flavor: StoreFlavor,
+}
+
+impl Store {
+    /// Trailing and repeated separators are dropped from `dir`, since store
+    /// paths are matched against it textually.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>, flavor: StoreFlavor) -> Self {
+        Self {
+            dir: dir.into().components().collect(),
+            flavor,
+        }
+    }
+
+    /// The default Nix store at `/nix/store`.
+    #[must_use]
+    pub fn nix() -> Self {
+        Self::new(NIX_STORE_DIR, StoreFlavor::Nix)
+    }
+
+    /// The default Guix store at `/gnu/store`.
+    #[must_use]
+    pub fn guix() -> Self {
+        Self::new(GUIX_STORE_DIR, StoreFlavor::Guix)
+    }
+
+    /// Decompose a path inside this store.
+    ///
+    /// Returns `None` for paths outside the store or not named like a store
+    /// entry.
+    #[must_use]
+    pub fn parse(&self, path: &Path) -> Option<StorePath> {
+        let mut components = path.strip_prefix(&self.dir).ok()?.components();
+        let entry = components.next()?.as_os_str().to_str()?;
+        let caps = STORE_ENTRY_REGEX.captures(entry)?;
+        let (name, version) = split_name_version(&caps[2]);
+
+        Some(StorePath {
+            store_dir: self.dir.clone(),
+            flavor: self.flavor,
+            hash: caps[1].to_string(),
+            name: name.to_string(),
+            version: version.map(str::to_string),
+            relative: components.as_path().to_path_buf(),
+        })
+    }
+}
+
+/// The set of stores paths are checked against.
+#[derive(Debug, Clone)]
+pub struct Stores {
+    stores: Vec<Store>,
+    /// Unanchored regex matching paths inside any of the stores, used to
+    /// find store references in file contents
+    path_regex: Regex,
+}
+
+impl Default for Stores {
+    fn default() -> Self {
+        Self::new(vec![Store::nix(), Store::guix()])
+    }
+}
+
+impl Stores {
+    #[must_use]
+    pub fn new(stores: Vec<Store>) -> Self {
+        let dirs = stores
+            .iter()
+            .map(|store| regex::escape(&store.dir.to_string_lossy()))
+            .collect::<Vec<_>>()
+            .join("|");
+        let path_regex = Regex::new(&format!(r"(?:{dirs})/[a-z0-9]+-[^/\s]+(?:/[^/\s]+)*"))
+            .expect("escaped store directories form a valid regex");
+
+        Self { stores, path_regex }
+    }
+
+    /// The stores from [`Stores::from_env`], shared for the whole run.
+    #[must_use]
+    pub fn defaults() -> &'static Self {
+        &DEFAULT_STORES
+    }
+
+    /// The default stores, with the Nix store moved to `NIX_STORE_DIR` when
+    /// that is set.
+    #[must_use]
+    pub fn from_env() -> Self {
+        match env::var_os(NIX_STORE_DIR_ENV).filter(|dir| !dir.is_empty()) {
+            Some(dir) => Self::default().with_nix_store_dir(dir),
+            None => Self::default(),
+        }
+    }
+
+    /// Replace the directory of the Nix store, e.g. for a store relocated
+    /// with nix-user-chroot.
+    ///
+    /// A relative directory is rejected and the stores are left unchanged,
+    /// as store paths are always absolute.
+    #[must_use]
+    pub fn with_nix_store_dir(self, dir: impl Into<PathBuf>) -> Self {
+        let dir = dir.into();
+        if !dir.is_absolute() {
+            debug!("Ignoring relative Nix store directory {}", dir.display());
+            return self;
+        }
+        let stores = self
+            .stores
+            .into_iter()
+            .map(|store| match store.flavor {
+                StoreFlavor::Nix => Store::new(dir.clone(), StoreFlavor::Nix),
+                StoreFlavor::Guix => store,
+            })
+            .collect();
+        Self::new(stores)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Store> {
+        self.stores.iter()
+    }
+
+    /// Decompose a path inside any of the stores.
+    #[must_use]
+    pub fn parse(&self, path: &Path) -> Option<StorePath> {
+        self.stores.iter().find_map(|store| store.parse(path))
+    }
+
+    /// Check whether a path lies inside one of the stores.
+    #[must_use]
+    pub fn contains(&self, path: &Path) -> bool {
+        self.parse(path).is_some()
+    }
+
+    /// Find every store path mentioned in `content`.
+    pub fn find_paths<'a>(&self, content: &'a str) -> impl Iterator<Item = &'a str> {
+        self.path_regex.find_iter(content).map(|m| m.as_str())
+    }
+}
+
+/// A path inside a Nix or Guix store, split into its components.
+///
+/// `<store_dir>/<hash>-<name>-<version>/<relative>`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorePath {
+    pub store_dir: PathBuf,
+    pub flavor: StoreFlavor,
+    pub hash: String,
+    pub name: String,
+    pub version: Option<String>,
+    /// Path below the store entry, empty for the entry itself
+    pub relative: PathBuf,
+}
+
+impl StorePath {
+    /// Get the top-level store entry, e.g. `/nix/store/<hash>-hello-2.12`.
+    #[must_use]
+    pub fn entry(&self) -> PathBuf {
+        let entry_name = match &self.version {
+            Some(version) => format!("{}-{}-{version}", self.hash, self.name),
+            None => format!("{}-{}", self.hash, self.name),
+        };
+        self.store_dir.join(entry_name)
+    }
+
+    /// Get the full path this was parsed from.
+    #[must_use]
+    pub fn full_path(&self) -> PathBuf {
+        if self.relative.as_os_str().is_empty() {
+            self.entry()
+        } else {
+            self.entry().join(&self.relative)
+        }
+    }
+
+    /// Get the hash shortened for display.
+    #[must_use]
+    pub fn short_hash(&self) -> String {
+        if self.hash.len() > SHORT_HASH_LEN {
+            format!("{}…", &self.hash[..SHORT_HASH_LEN])
+        } else {
+            self.hash.clone()
+        }
+    }
+}
+
+impl fmt::Display for StorePath {
+    /// Render as `name version (hash…)`, without the relative path.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if let Some(version) = &self.version {
+            write!(f, " {version}")?;
+        }
+        write!(f, " ({})", self.short_hash())
+    }
+}
+
+/// Split a store name into package name and version.
+///
+/// Follows `builtins.parseDrvName`: the version starts after the first dash
+/// that is followed by a character other than a letter.
+fn split_name_version(full: &str) -> (&str, Option<&str>) {
+    full.char_indices()
+        .find(|&(idx, c)| {
+            c == '-'
+                && full[idx + 1..]
+                    .chars()
+                    .next()
+                    .is_some_and(|next| !next.is_ascii_alphabetic())
+        })
+        .map_or((full, None), |(idx, _)| {
+            (&full[..idx], Some(&full[idx + 1..]))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_store_path() {
+        let store_path = Stores::defaults()
+            .parse(Path::new("/nix/store/abc123-quickshell-0.2.1/bin/qs"))
+            .unwrap();
+
+        assert_eq!(store_path.hash, "abc123");
+        assert_eq!(store_path.name, "quickshell");
+        assert_eq!(store_path.version.as_deref(), Some("0.2.1"));
+        assert_eq!(store_path.relative, PathBuf::from("bin/qs"));
+        assert_eq!(
+            store_path.entry(),
+            PathBuf::from("/nix/store/abc123-quickshell-0.2.1")
+        );
+        assert_eq!(
+            store_path.full_path(),
+            PathBuf::from("/nix/store/abc123-quickshell-0.2.1/bin/qs")
+        );
+    }
+
+    #[test]
+    fn test_parse_store_entry_without_version() {
+        let store_path = Stores::defaults()
+            .parse(Path::new("/nix/store/abc123-nvim-wrapper"))
+            .unwrap();
+
+        assert_eq!(store_path.name, "nvim-wrapper");
+        assert_eq!(store_path.version, None);
+        assert!(store_path.relative.as_os_str().is_empty());
+    }
+
+    #[test]
+    fn test_parse_not_store_path() {
+        assert!(
+            Stores::defaults()
+                .parse(Path::new("/usr/bin/python3"))
+                .is_none()
+        );
+        assert!(Stores::defaults().parse(Path::new("/nix/store")).is_none());
+        assert!(
+            Stores::defaults()
+                .parse(Path::new("/nix/var/nix/profiles"))
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_split_name_version() {
+        assert_eq!(split_name_version("hello-2.12"), ("hello", Some("2.12")));
+        assert_eq!(
+            split_name_version("python3-3.12.1"),
+            ("python3", Some("3.12.1"))
+        );
+        assert_eq!(
+            split_name_version("gnome-shell-45.0-man"),
+            ("gnome-shell", Some("45.0-man"))
+        );
+        assert_eq!(split_name_version("nvim-wrapper"), ("nvim-wrapper", None));
+    }
+
+    #[test]
+    fn test_display_abbreviates_hash() {
+        let store_path = Stores::defaults()
+            .parse(Path::new(
+                "/nix/store/0c5xyzqv8n2l4k6h3j9f1d7s5a2w8e4r-quickshell-0.2.1/bin/qs",
+            ))
+            .unwrap();
+
+        assert_eq!(store_path.to_string(), "quickshell 0.2.1 (0c5xyzq…)");
+    }
+
+    #[test]
+    fn test_parse_guix_store_path() {
+        let store_path = Stores::defaults()
+            .parse(Path::new(
+                "/gnu/store/4kzmsrpshb4m0yhlrn0pylrdlx0kqw66-emacs-29.4/bin/emacs",
+            ))
+            .unwrap();
+
+        assert_eq!(store_path.flavor, StoreFlavor::Guix);
+        assert_eq!(store_path.store_dir, PathBuf::from(GUIX_STORE_DIR));
+        assert_eq!(store_path.name, "emacs");
+        assert_eq!(store_path.version.as_deref(), Some("29.4"));
+        assert_eq!(store_path.relative, PathBuf::from("bin/emacs"));
+    }
+
+    #[test]
+    fn test_custom_store_dir() {
+        let stores = Stores::new(vec![Store::new("/opt/nix/store", StoreFlavor::Nix)]);
+
+        let store_path = stores
+            .parse(Path::new("/opt/nix/store/abc123-hello-2.12/bin/hello"))
+            .unwrap();
+        assert_eq!(
+            store_path.entry(),
+            PathBuf::from("/opt/nix/store/abc123-hello-2.12")
+        );
+        assert!(!stores.contains(Path::new("/nix/store/abc123-hello-2.12")));
+    }
+
+    #[test]
+    fn test_with_nix_store_dir_keeps_guix() {
+        let stores = Stores::default().with_nix_store_dir("/home/u/.nix/store");
+
+        let nix = stores
+            .parse(Path::new("/home/u/.nix/store/abc123-hello-2.12/bin/hello"))
+            .unwrap();
+        assert_eq!(nix.flavor, StoreFlavor::Nix);
+        assert!(stores.contains(Path::new("/gnu/store/abc123-hello-2.12")));
+        assert!(!stores.contains(Path::new("/nix/store/abc123-hello-2.12")));
+        assert_eq!(
+            stores
+                .find_paths("exec /home/u/.nix/store/abc123-hello-2.12/bin/.hello-wrapped")
+                .collect::<Vec<_>>(),
+            vec!["/home/u/.nix/store/abc123-hello-2.12/bin/.hello-wrapped"]
+        );
+    }
+
+    #[test]
+    fn test_with_nix_store_dir_normalizes_dir() {
+        let stores = Stores::default().with_nix_store_dir("/x/store/");
+        let path = "/x/store/abc123-hello-2.12/bin/hello";
+
+        assert_eq!(
+            stores.parse(Path::new(path)).unwrap().store_dir,
+            Path::new("/x/store")
+        );
+        assert_eq!(
+            stores
+                .find_paths(&format!("exec {path}"))
+                .collect::<Vec<_>>(),
+            vec![path]
+        );
+
+        let unchanged = Stores::default().with_nix_store_dir("x/store");
+        assert!(unchanged.contains(Path::new("/nix/store/abc123-hello-2.12")));
+    }
+
+    #[test]
+    fn test_find_paths_in_content() {
+        let content = "exec /gnu/store/abc-foo-1.0/bin/.foo-real \"$@\"\n\
+                       source /nix/store/def-bar/lib/env.sh\n\
+                       /opt/other/store/ghi-baz/bin/baz\n";
+
+        let found: Vec<_> = Stores::default().find_paths(content).collect();
+        assert_eq!(
+            found,
+            vec![
+                "/gnu/store/abc-foo-1.0/bin/.foo-real",
+                "/nix/store/def-bar/lib/env.sh"
+            ]
+        );
+    }
+}
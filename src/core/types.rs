@@ -1,19 +1,30 @@
-use std::path::PathBuf;
+use crate::core::detector::compiler_cache::CompilerCache;
+use crate::core::detector::launcher::AppLauncher;
+use crate::core::detector::multi_call::Applet;
+use crate::core::detector::self_extracting::BundleInfo;
+use crate::core::homebrew::HomebrewKeg;
+use crate::core::mount::MountInfo;
+use crate::core::nix_db::StorePathInfo;
+use crate::core::profile::ProfileLayer;
+use crate::core::store::StorePath;
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LinkType {
     Symlink,
     Wrapper(WrapperKind),
     Terminal(FileKind),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum WrapperKind {
     Binary,
     Text(ScriptType),
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ScriptType {
     Shell,
     Python,
@@ -21,7 +32,7 @@ pub enum ScriptType {
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum FileKind {
     Binary,
     Text,
@@ -36,7 +47,14 @@ pub enum FileLocation {
 #[derive(Debug, Clone)]
 pub struct SymlinkChain {
     pub origin: PathBuf,
+    /// Nix profiles traversed when opening the origin
+    pub origin_profiles: Vec<ProfileLayer>,
+    /// Mount the origin lives on
+    pub origin_mount: Option<MountInfo>,
     pub links: Vec<SymlinkNode>,
+    /// Whether resolution stopped at the symlink limit before reaching the
+    /// final target
+    pub truncated: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -44,9 +62,58 @@ pub struct SymlinkNode {
     pub target: PathBuf,
     pub is_final: bool,
     pub link_type: LinkType,
+    /// Decomposed store path, if the target lives in a Nix or Guix store
+    pub store_path: Option<StorePath>,
+    /// Nix database information about the store entry, if requested
+    pub store_info: Option<StorePathInfo>,
+    /// Nix profiles traversed when opening this hop
+    pub profiles: Vec<ProfileLayer>,
+    /// Homebrew keg containing the target, if any
+    pub keg: Option<HomebrewKeg>,
+    /// Flatpak or Snap application started by this hop
+    pub launcher: Option<AppLauncher>,
+    /// Compiler cache masquerading as the compiler it runs
+    pub compiler_cache: Option<CompilerCache>,
+    /// Details of a self-extracting target such as an AppImage
+    pub bundle: Option<BundleInfo>,
+    /// Applet selected by the name a multi-call binary was invoked as
+    pub applet: Option<Applet>,
+    /// Device and inode of the hop itself, without following symlinks
+    pub file_id: Option<FileId>,
+    /// Mount the hop itself lives on
+    pub mount: Option<MountInfo>,
     pub metadata: Option<NodeMetadata>,
 }
 
+/// Identity of a file on disk, shared by all of its hard links and by every
+/// path reaching it through a bind mount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileId {
+    pub dev: u64,
+    pub ino: u64,
+    /// Number of hard links to the inode
+    pub nlink: u64,
+}
+
+impl FileId {
+    /// Read the identity of `path` itself, not of a symlink's target.
+    #[must_use]
+    pub fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::symlink_metadata(path).ok()?;
+        Some(Self {
+            dev: metadata.dev(),
+            ino: metadata.ino(),
+            nlink: metadata.nlink(),
+        })
+    }
+
+    /// Check whether both identities refer to the same inode.
+    #[must_use]
+    pub const fn same_file(&self, other: &Self) -> bool {
+        self.dev == other.dev && self.ino == other.ino
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct NodeMetadata {
     pub is_broken: bool,
@@ -59,13 +126,26 @@ impl SymlinkChain {
     pub const fn new(origin: PathBuf) -> Self {
         Self {
             origin,
+            origin_profiles: Vec::new(),
+            origin_mount: None,
             links: Vec::new(),
+            truncated: false,
         }
     }
 
     /// Add a link to the chain.
     pub fn add_link(&mut self, target: PathBuf, is_final: bool, link_type: LinkType) {
         self.links.push(SymlinkNode {
+            store_path: None,
+            store_info: None,
+            profiles: Vec::new(),
+            keg: None,
+            launcher: None,
+            compiler_cache: None,
+            bundle: None,
+            applet: None,
+            file_id: None,
+            mount: None,
             target,
             is_final,
             link_type,
@@ -78,6 +158,41 @@ impl SymlinkChain {
     pub const fn is_empty(&self) -> bool {
         self.links.is_empty()
     }
+
+    /// Get the path the chain finally resolves to.
+    ///
+    /// This is the origin itself if no links were followed.
+    #[must_use]
+    pub fn final_target(&self) -> &Path {
+        self.links.last().map_or(&self.origin, |node| &node.target)
+    }
+
+    /// For every hop, find the first earlier hop that is the same file,
+    /// reached again through a hard link or bind mount.
+    #[must_use]
+    pub fn same_file_hops(&self) -> Vec<Option<usize>> {
+        self.links
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| {
+                let id = node.file_id?;
+                self.links[..idx].iter().position(|earlier| {
+                    earlier
+                        .file_id
+                        .is_some_and(|earlier| earlier.same_file(&id))
+                })
+            })
+            .collect()
+    }
+
+    /// Count the wrappers passed through along the chain.
+    #[must_use]
+    pub fn wrapper_count(&self) -> usize {
+        self.links
+            .iter()
+            .filter(|node| matches!(node.link_type, LinkType::Wrapper(_)))
+            .count()
+    }
 }
 
 #[cfg(test)]
@@ -112,6 +227,26 @@ mod tests {
         assert!(chain.links[1].is_final);
     }
 
+    #[test]
+    fn test_same_file_hops() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let file = temp.path().join("file");
+        fs::write(&file, "").unwrap();
+        let hard_link = temp.path().join("hard-link");
+        fs::hard_link(&file, &hard_link).unwrap();
+        let other = temp.path().join("other");
+        fs::write(&other, "").unwrap();
+
+        let mut chain = SymlinkChain::new(PathBuf::from("/origin"));
+        for path in [&file, &other, &hard_link] {
+            chain.add_link(path.clone(), false, LinkType::Wrapper(WrapperKind::Binary));
+            chain.links.last_mut().unwrap().file_id = FileId::read(path);
+        }
+
+        assert_eq!(chain.same_file_hops(), vec![None, None, Some(0)]);
+        assert_eq!(chain.links[2].file_id.unwrap().nlink, 2);
+    }
+
     #[test]
     fn test_file_location_current_directory() {
         let loc = FileLocation::CurrentDirectory(PathBuf::from("/cwd/file"));
@@ -144,6 +279,16 @@ mod tests {
             target: PathBuf::from("/target"),
             is_final: true,
             link_type: LinkType::Terminal(FileKind::Binary),
+            store_path: None,
+            store_info: None,
+            profiles: Vec::new(),
+            keg: None,
+            launcher: None,
+            compiler_cache: None,
+            bundle: None,
+            applet: None,
+            file_id: None,
+            mount: None,
             metadata: None,
         };
 
@@ -169,4 +314,37 @@ mod tests {
         assert!(chain.links[4].is_final);
         assert!(!chain.links[0].is_final);
     }
+
+    #[test]
+    fn test_final_target_and_wrapper_count() {
+        let mut chain = SymlinkChain::new(PathBuf::from("/start"));
+        assert_eq!(chain.final_target(), Path::new("/start"));
+        assert_eq!(chain.wrapper_count(), 0);
+
+        chain.add_link(
+            PathBuf::from("/wrapper"),
+            false,
+            LinkType::Wrapper(WrapperKind::Binary),
+        );
+        chain.add_link(
+            PathBuf::from("/end"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        assert_eq!(chain.final_target(), Path::new("/end"));
+        assert_eq!(chain.wrapper_count(), 1);
+    }
+
+    #[test]
+    fn test_add_link_leaves_store_path_to_resolver() {
+        // Which directories are stores depends on the resolver's options
+        let mut chain = SymlinkChain::new(PathBuf::from("/run/current-system/sw/bin/qs"));
+        chain.add_link(
+            PathBuf::from("/nix/store/abc123-quickshell-0.2.1/bin/qs"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+
+        assert!(chain.links[0].store_path.is_none());
+    }
 }
@@ -0,0 +1,634 @@
+use crate::error::{Result, SymseekError};
+use log::{debug, trace};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// System unit directories in order of precedence, from systemd.unit(5).
+const SYSTEM_UNIT_DIRS: [&str; 8] = [
+    "/etc/systemd/system.control",
+    "/run/systemd/system.control",
+    "/run/systemd/transient",
+    "/etc/systemd/system",
+    "/run/systemd/system",
+    "/usr/local/lib/systemd/system",
+    "/usr/lib/systemd/system",
+    "/lib/systemd/system",
+];
+/// User unit directories below `$HOME`, followed by the global ones.
+const USER_UNIT_DIR: &str = ".config/systemd/user";
+const GLOBAL_USER_UNIT_DIRS: [&str; 2] = ["/etc/systemd/user", "/usr/lib/systemd/user"];
+
+/// A unit file linked here, or left empty, masks the unit.
+const MASK_TARGET: &str = "/dev/null";
+
+const SERVICE_SECTION: &str = "[Service]";
+const DROP_IN_SUFFIX: &str = ".conf";
+
+/// Search path systemd is compiled with, used when the unit sets no `PATH`.
+const DEFAULT_SEARCH_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin";
+
+/// Directives whose value is a command line.
+const EXEC_DIRECTIVES: [&str; 7] = [
+    "ExecCondition",
+    "ExecStartPre",
+    "ExecStart",
+    "ExecStartPost",
+    "ExecReload",
+    "ExecStop",
+    "ExecStopPost",
+];
+/// Characters that may precede the executable of a command line.
+const EXEC_PREFIXES: &[char] = &['-', '@', ':', '+', '!'];
+
+/// Which service manager a unit is looked up for, like `systemctl --user`.
+///
+/// The scopes never share unit files or drop-ins.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum UnitScope {
+    #[default]
+    System,
+    User,
+}
+
+impl UnitScope {
+    /// Unit directories of the scope in order of precedence.
+    fn dirs(self) -> Vec<PathBuf> {
+        match self {
+            Self::System => SYSTEM_UNIT_DIRS.iter().map(PathBuf::from).collect(),
+            Self::User => user_unit_dirs(env::var_os("HOME").as_deref().map(Path::new)),
+        }
+    }
+}
+
+/// A command from an `Exec*=` directive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitCommand {
+    /// Directive the command came from, e.g. `ExecStartPre`
+    pub directive: String,
+    /// Special prefixes such as `-` (failure ignored), `@` (custom argv0) or
+    /// `!` (runs with full privileges)
+    pub prefixes: String,
+    /// Executable as written in the unit
+    pub executable: String,
+    /// argv0 given with the `@` prefix
+    pub argv0: Option<String>,
+    /// Executable after the `PATH` lookup, if found
+    pub path: Option<PathBuf>,
+}
+
+/// A service unit read from disk together with its drop-ins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnitFile {
+    pub name: String,
+    /// Unit file used, a template for instantiated units
+    pub path: PathBuf,
+    /// Drop-ins applied after the unit file, in order
+    pub drop_ins: Vec<PathBuf>,
+    /// `PATH` set with `Environment=` or `EnvironmentFile=`
+    pub path_var: Option<String>,
+    pub commands: Vec<UnitCommand>,
+}
+
+/// Raw directives of the `[Service]` section, in order of appearance.
+#[derive(Debug, Default)]
+struct ServiceSection {
+    exec: Vec<(String, String)>,
+    environment: Vec<String>,
+    environment_files: Vec<String>,
+}
+
+impl UnitFile {
+    /// Find and read a unit in the unit directories of `scope`.
+    ///
+    /// A name without a type suffix is taken to be a service.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the unit is not found, is masked, or cannot be
+    /// read.
+    pub fn load(name: &str, scope: UnitScope) -> Result<Self> {
+        Self::load_from(&scope.dirs(), name)
+    }
+
+    fn load_from(dirs: &[PathBuf], name: &str) -> Result<Self> {
+        let name = if name.contains('.') {
+            name.to_string()
+        } else {
+            format!("{name}.service")
+        };
+        let template = template_name(&name);
+
+        let path = find_unit(dirs, &name, template.as_deref())?;
+        debug!("Unit {name} found at {}", path.display());
+
+        let drop_ins = drop_ins(dirs, &name, template.as_deref());
+        let mut section = ServiceSection::default();
+        for file in std::iter::once(&path).chain(&drop_ins) {
+            let content = fs::read_to_string(file).map_err(|e| SymseekError::Io {
+                context: format!("Failed to read {}", file.display()),
+                source: e,
+            })?;
+            section.apply(&content);
+        }
+
+        let path_var = section.path_var(&name);
+        let search_path = path_var.as_deref().unwrap_or(DEFAULT_SEARCH_PATH);
+        let commands = section
+            .exec
+            .iter()
+            .filter_map(|(directive, value)| {
+                parse_command(directive, &expand_specifiers(value, &name), search_path)
+            })
+            .collect();
+
+        Ok(Self {
+            name,
+            path,
+            drop_ins,
+            path_var,
+            commands,
+        })
+    }
+}
+
+impl ServiceSection {
+    /// Apply the `[Service]` directives of one file on top of those read so
+    /// far. An empty assignment clears the commands of that directive.
+    fn apply(&mut self, content: &str) {
+        let mut in_service = false;
+        for line in logical_lines(content) {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_service = line == SERVICE_SECTION;
+                continue;
+            }
+            if !in_service {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match key {
+                "Environment" if value.is_empty() => self.environment.clear(),
+                "Environment" => self.environment.extend(split_quoted(value)),
+                "EnvironmentFile" if value.is_empty() => self.environment_files.clear(),
+                "EnvironmentFile" => self.environment_files.push(value.to_string()),
+                _ if EXEC_DIRECTIVES.contains(&key) => {
+                    if value.is_empty() {
+                        self.exec.retain(|(directive, _)| directive != key);
+                    } else {
+                        self.exec.push((key.to_string(), value.to_string()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Get the `PATH` the service runs with. Environment files override
+    /// `Environment=`, as in systemd.
+    fn path_var(&self, unit_name: &str) -> Option<String> {
+        let mut path_var = None;
+        for assignment in &self.environment {
+            if let Some(value) = assignment.strip_prefix("PATH=") {
+                path_var = Some(value.to_string());
+            }
+        }
+
+        for file in &self.environment_files {
+            let file = expand_specifiers(file, unit_name);
+            let file = file.strip_prefix('-').unwrap_or(&file);
+            let Ok(content) = fs::read_to_string(file) else {
+                debug!("Cannot read environment file {file}");
+                continue;
+            };
+            for line in content.lines().map(str::trim) {
+                if let Some(value) = line.strip_prefix("PATH=") {
+                    path_var = Some(unquote(value).to_string());
+                }
+            }
+        }
+
+        trace!("Unit {unit_name} PATH: {path_var:?}");
+        path_var
+    }
+}
+
+fn user_unit_dirs(home: Option<&Path>) -> Vec<PathBuf> {
+    home.map(|home| home.join(USER_UNIT_DIR))
+        .into_iter()
+        .chain(GLOBAL_USER_UNIT_DIRS.iter().map(PathBuf::from))
+        .collect()
+}
+
+/// Find the file of a unit, or of its template, in the first directory that
+/// has one. A masking file stops the search, as it does for systemd.
+fn find_unit(dirs: &[PathBuf], name: &str, template: Option<&str>) -> Result<PathBuf> {
+    for candidate in std::iter::once(name).chain(template) {
+        for path in dirs.iter().map(|dir| dir.join(candidate)) {
+            if is_masked(&path) {
+                debug!("Unit {name} masked by {}", path.display());
+                return Err(SymseekError::UnitMasked {
+                    name: name.to_string(),
+                    path,
+                });
+            }
+            if path.is_file() {
+                return Ok(path);
+            }
+        }
+    }
+
+    Err(SymseekError::NotFound {
+        name: name.to_string(),
+        searched_locations: dirs.iter().map(|dir| dir.display().to_string()).collect(),
+    })
+}
+
+fn is_masked(path: &Path) -> bool {
+    fs::canonicalize(path).is_ok_and(|target| target == Path::new(MASK_TARGET))
+        || fs::metadata(path).is_ok_and(|metadata| metadata.is_file() && metadata.len() == 0)
+}
+
+/// Get the template of an instantiated unit, e.g. `getty@.service` for
+/// `getty@tty1.service`.
+fn template_name(name: &str) -> Option<String> {
+    let (prefix, rest) = name.split_once('@')?;
+    let (instance, suffix) = rest.rsplit_once('.')?;
+    (!instance.is_empty()).then(|| format!("{prefix}@.{suffix}"))
+}
+
+/// Collect the `*.conf` drop-ins of a unit and its template.
+///
+/// A drop-in in a directory of higher precedence masks one with the same
+/// file name further down, and within a directory the instance's drop-in
+/// masks the template's; the survivors apply in file name order.
+fn drop_ins(dirs: &[PathBuf], name: &str, template: Option<&str>) -> Vec<PathBuf> {
+    let mut by_name: BTreeMap<String, PathBuf> = BTreeMap::new();
+    for dir in dirs {
+        for unit in std::iter::once(name).chain(template) {
+            let Ok(entries) = fs::read_dir(dir.join(format!("{unit}.d"))) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                let Some(file_name) = entry.file_name().to_str().map(str::to_string) else {
+                    continue;
+                };
+                if file_name.ends_with(DROP_IN_SUFFIX) {
+                    by_name.entry(file_name).or_insert_with(|| entry.path());
+                }
+            }
+        }
+    }
+    by_name.into_values().collect()
+}
+
+/// Join lines continued with a trailing backslash.
+fn logical_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        match line.strip_suffix('\\') {
+            Some(continued) => {
+                current.push_str(continued);
+                current.push(' ');
+            }
+            None => {
+                current.push_str(line);
+                lines.push(std::mem::take(&mut current));
+            }
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+/// Parse an `Exec*=` value such as `-@/usr/bin/foo foo --flag`.
+fn parse_command(directive: &str, value: &str, search_path: &str) -> Option<UnitCommand> {
+    let prefix_len = value
+        .find(|c| !EXEC_PREFIXES.contains(&c))
+        .unwrap_or(value.len());
+    let (prefixes, command) = value.split_at(prefix_len);
+
+    let mut words = split_quoted(command).into_iter();
+    let executable = words.next()?;
+    let argv0 = if prefixes.contains('@') {
+        words.next()
+    } else {
+        None
+    };
+    let path = find_executable(&executable, search_path);
+
+    Some(UnitCommand {
+        directive: directive.to_string(),
+        prefixes: prefixes.to_string(),
+        executable,
+        argv0,
+        path,
+    })
+}
+
+fn find_executable(executable: &str, search_path: &str) -> Option<PathBuf> {
+    let executable = Path::new(executable);
+    if executable.is_absolute() {
+        return Some(executable.to_path_buf());
+    }
+    env::split_paths(search_path)
+        .map(|dir| dir.join(executable))
+        .find(|path| path.is_file())
+}
+
+/// Split a value into words, keeping quoted sections together.
+fn split_quoted(value: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut current));
+                    in_word = false;
+                }
+            }
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (Some(q), c) if c == q => quote = None,
+            (_, '\\') => {
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+                in_word = true;
+            }
+            (_, c) => {
+                current.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(current);
+    }
+    words
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(value)
+}
+
+/// Expand the unit name specifiers `%n`, `%N`, `%p`, `%i`, `%I` and `%%`.
+fn expand_specifiers(value: &str, unit_name: &str) -> String {
+    let stem = unit_name
+        .rsplit_once('.')
+        .map_or(unit_name, |(stem, _)| stem);
+    let (prefix, instance) = stem.split_once('@').unwrap_or((stem, ""));
+
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push_str(unit_name),
+            Some('N') => result.push_str(stem),
+            Some('p') => result.push_str(prefix),
+            Some('i' | 'I') => result.push_str(instance),
+            Some('%') => result.push('%'),
+            Some(other) => {
+                result.push('%');
+                result.push(other);
+            }
+            None => result.push('%'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn test_parse_command_prefixes() {
+        let command = parse_command("ExecStart", "-@/usr/bin/foo foo-daemon --flag", "").unwrap();
+
+        assert_eq!(command.prefixes, "-@");
+        assert_eq!(command.executable, "/usr/bin/foo");
+        assert_eq!(command.argv0.as_deref(), Some("foo-daemon"));
+        assert_eq!(command.path, Some(PathBuf::from("/usr/bin/foo")));
+    }
+
+    #[test]
+    fn test_split_quoted() {
+        assert_eq!(
+            split_quoted(r#""A=1 2" B=3 'C=x'"#),
+            vec!["A=1 2", "B=3", "C=x"]
+        );
+    }
+
+    #[test]
+    fn test_expand_specifiers() {
+        assert_eq!(
+            expand_specifiers("/etc/%p/%i.conf %%", "getty@tty1.service"),
+            "/etc/getty/tty1.conf %"
+        );
+        assert_eq!(
+            template_name("getty@tty1.service").as_deref(),
+            Some("getty@.service")
+        );
+        assert_eq!(template_name("getty@.service"), None);
+    }
+
+    #[test]
+    fn test_load_with_drop_ins_and_path() {
+        let temp = TempDir::new().unwrap();
+        let etc = temp.child("etc");
+        let lib = temp.child("lib");
+        temp.child("opt/bin/helper").touch().unwrap();
+        temp.child("env")
+            .write_str("# comment\nPATH=\"/nowhere\"\n")
+            .unwrap();
+
+        lib.child("web.service")
+            .write_str(&format!(
+                "[Unit]\nDescription=Web\n\n[Service]\nEnvironment=\"LANG=C\" PATH=/x\n\
+                 EnvironmentFile=-{}\nEnvironmentFile=-/missing\n\
+                 ExecStartPre=!/usr/bin/setup \\\n  --all\nExecStart=/usr/bin/web\n",
+                temp.child("env").path().display()
+            ))
+            .unwrap();
+        lib.child("web.service.d/10-path.conf")
+            .write_str(&format!(
+                "[Service]\nEnvironment=PATH={}\n",
+                temp.child("opt/bin").path().display()
+            ))
+            .unwrap();
+        etc.child("web.service.d/20-override.conf")
+            .write_str("[Service]\nExecStart=\nExecStart=-helper --serve\n")
+            .unwrap();
+        lib.child("web.service.d/20-override.conf")
+            .write_str("[Service]\nExecStart=/masked\n")
+            .unwrap();
+
+        let unit = UnitFile::load_from(&[etc.to_path_buf(), lib.to_path_buf()], "web").unwrap();
+
+        assert_eq!(unit.name, "web.service");
+        assert_eq!(unit.path, lib.child("web.service").path());
+        assert_eq!(unit.drop_ins.len(), 2);
+        // The environment file overrides every Environment= line
+        assert_eq!(unit.path_var.as_deref(), Some("/nowhere"));
+
+        assert_eq!(unit.commands.len(), 2);
+        assert_eq!(unit.commands[0].directive, "ExecStartPre");
+        assert_eq!(unit.commands[0].prefixes, "!");
+        assert_eq!(unit.commands[0].executable, "/usr/bin/setup");
+        assert_eq!(unit.commands[1].prefixes, "-");
+        assert_eq!(unit.commands[1].executable, "helper");
+        assert_eq!(unit.commands[1].path, None);
+    }
+
+    #[test]
+    fn test_load_template_instance() {
+        let temp = TempDir::new().unwrap();
+        temp.child("bin/agetty").touch().unwrap();
+        temp.child("getty@.service")
+            .write_str(&format!(
+                "[Service]\nEnvironment=PATH={}\nExecStart=agetty %I\n",
+                temp.child("bin").path().display()
+            ))
+            .unwrap();
+
+        let unit = UnitFile::load_from(&[temp.to_path_buf()], "getty@tty1.service").unwrap();
+
+        assert_eq!(unit.path, temp.child("getty@.service").path());
+        assert_eq!(
+            unit.commands[0].path,
+            Some(temp.child("bin/agetty").to_path_buf())
+        );
+    }
+
+    #[test]
+    fn test_instance_drop_in_masks_template_drop_in() {
+        let temp = TempDir::new().unwrap();
+        let etc = temp.child("etc");
+        let lib = temp.child("lib");
+        lib.child("getty@.service")
+            .write_str("[Service]\nExecStart=/usr/bin/agetty\n")
+            .unwrap();
+        for (dir, unit) in [
+            (&etc, "getty@tty1.service"),
+            (&etc, "getty@.service"),
+            (&lib, "getty@tty1.service"),
+        ] {
+            dir.child(format!("{unit}.d/override.conf"))
+                .write_str("[Service]\nExecStart=\n")
+                .unwrap();
+        }
+
+        let unit = UnitFile::load_from(
+            &[etc.to_path_buf(), lib.to_path_buf()],
+            "getty@tty1.service",
+        )
+        .unwrap();
+
+        assert_eq!(
+            unit.drop_ins,
+            vec![
+                etc.child("getty@tty1.service.d/override.conf")
+                    .to_path_buf()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_masked_unit() {
+        let temp = TempDir::new().unwrap();
+        let etc = temp.child("etc");
+        let lib = temp.child("lib");
+        let dirs = [etc.to_path_buf(), lib.to_path_buf()];
+        lib.child("web.service")
+            .write_str("[Service]\nExecStart=/usr/bin/web\n")
+            .unwrap();
+        lib.child("getty@.service")
+            .write_str("[Service]\nExecStart=/usr/bin/agetty\n")
+            .unwrap();
+        etc.create_dir_all().unwrap();
+        etc.child("web.service")
+            .symlink_to_file("/dev/null")
+            .unwrap();
+        etc.child("getty@tty1.service").touch().unwrap();
+
+        for name in ["web", "getty@tty1.service"] {
+            assert!(matches!(
+                UnitFile::load_from(&dirs, name),
+                Err(SymseekError::UnitMasked { .. })
+            ));
+        }
+        assert!(UnitFile::load_from(&dirs, "getty@tty2.service").is_ok());
+    }
+
+    #[test]
+    fn test_user_unit_dirs_exclude_system_dirs() {
+        let temp = TempDir::new().unwrap();
+        let system = temp.child("system");
+        let home = temp.child("home");
+        let user = home.child(USER_UNIT_DIR);
+        system
+            .child("web.service")
+            .write_str("[Service]\nExecStart=/usr/bin/system-web\n")
+            .unwrap();
+        system
+            .child("web.service.d/10-system.conf")
+            .write_str("[Service]\nExecStart=\nExecStart=/usr/bin/override\n")
+            .unwrap();
+        user.child("web.service")
+            .write_str("[Service]\nExecStart=/usr/bin/user-web\n")
+            .unwrap();
+
+        let dirs = user_unit_dirs(Some(home.path()));
+        assert_eq!(dirs[0], user.path());
+        assert!(
+            SYSTEM_UNIT_DIRS
+                .iter()
+                .all(|dir| !dirs.contains(&PathBuf::from(dir)))
+        );
+
+        let unit = UnitFile::load_from(&dirs, "web").unwrap();
+        assert_eq!(unit.path, user.child("web.service").path());
+        assert!(unit.drop_ins.is_empty());
+        assert_eq!(unit.commands[0].executable, "/usr/bin/user-web");
+
+        let unit = UnitFile::load_from(&[system.to_path_buf()], "web").unwrap();
+        assert_eq!(unit.commands[0].executable, "/usr/bin/override");
+    }
+
+    #[test]
+    fn test_load_missing_unit() {
+        let temp = TempDir::new().unwrap();
+        assert!(matches!(
+            UnitFile::load_from(&[temp.to_path_buf()], "missing.service"),
+            Err(SymseekError::NotFound { .. })
+        ));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::core::types::SymlinkChain;
+use crate::error::{Result, SymseekError};
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask};
+use log::{debug, trace};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+const EVENT_BUFFER_SIZE: usize = 4096;
+
+/// Time to wait after the first event so that a burst of changes, such as
+/// a profile switch replacing many links, triggers a single re-resolution.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Collect every path whose change could alter the given chain.
+#[must_use]
+pub fn chain_paths(chain: &SymlinkChain) -> Vec<PathBuf> {
+    std::iter::once(chain.origin.clone())
+        .chain(chain.links.iter().map(|node| node.target.clone()))
+        .collect()
+}
+
+/// Map each directory to the entry names inside it that must be watched.
+///
+/// Every ancestor of every path is included, since replacing any directory
+/// symlink along the way (e.g. `/run/current-system`) changes where the
+/// path points to.
+#[must_use]
+pub fn watch_targets(paths: &[PathBuf]) -> BTreeMap<PathBuf, BTreeSet<OsString>> {
+    let mut targets: BTreeMap<PathBuf, BTreeSet<OsString>> = BTreeMap::new();
+
+    for path in paths {
+        let mut current = path.as_path();
+        while let (Some(parent), Some(name)) = (current.parent(), current.file_name()) {
+            targets
+                .entry(parent.to_path_buf())
+                .or_default()
+                .insert(name.to_os_string());
+            current = parent;
+        }
+    }
+
+    targets
+}
+
+/// Watches the paths of a chain with a single inotify instance that lives
+/// as long as the watch loop, so changes made between two waits are queued
+/// rather than lost.
+pub struct Watcher {
+    inotify: Inotify,
+    /// Entry names that matter in each watched directory
+    watched: HashMap<WatchDescriptor, HashSet<OsString>>,
+}
+
+impl Watcher {
+    /// Create a watcher that does not watch anything yet.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if inotify cannot be initialized.
+    pub fn new() -> Result<Self> {
+        let inotify = Inotify::init().map_err(|e| SymseekError::Io {
+            context: "Failed to initialize inotify".to_string(),
+            source: e,
+        })?;
+        Ok(Self {
+            inotify,
+            watched: HashMap::new(),
+        })
+    }
+
+    /// Watch exactly the given paths and the directories leading to them.
+    ///
+    /// New watches are added before stale ones are removed, so paths
+    /// watched before and after never miss an event. Directories that do
+    /// not exist are skipped; their creation is still seen through the
+    /// watch on their own parent.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if none of the paths can be watched.
+    pub fn watch(&mut self, paths: &[PathBuf]) -> Result<()> {
+        let mask = WatchMask::CREATE
+            | WatchMask::DELETE
+            | WatchMask::MODIFY
+            | WatchMask::ATTRIB
+            | WatchMask::MOVE
+            | WatchMask::DELETE_SELF
+            | WatchMask::MOVE_SELF
+            | WatchMask::ONLYDIR;
+
+        let mut watched: HashMap<WatchDescriptor, HashSet<OsString>> = HashMap::new();
+        for (dir, names) in watch_targets(paths) {
+            match self.inotify.watches().add(&dir, mask) {
+                Ok(wd) => {
+                    trace!("Watching {} for {names:?}", dir.display());
+                    watched.entry(wd).or_default().extend(names);
+                }
+                Err(e) => debug!("Cannot watch {}: {e}", dir.display()),
+            }
+        }
+
+        for wd in self.watched.keys() {
+            if !watched.contains_key(wd) {
+                // Fails harmlessly if the directory is already gone
+                let _ = self.inotify.watches().remove(wd.clone());
+            }
+        }
+        self.watched = watched;
+
+        if self.watched.is_empty() {
+            return Err(SymseekError::InvalidInput {
+                message: "None of the paths in the chain can be watched".to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Block until a watched path, or a directory leading to it, changes.
+    /// Events queued since the paths were watched are taken into account.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if inotify events cannot be read.
+    pub fn wait(&mut self) -> Result<()> {
+        let mut buffer = [0u8; EVENT_BUFFER_SIZE];
+        loop {
+            let events = self
+                .inotify
+                .read_events_blocking(&mut buffer)
+                .map_err(|e| SymseekError::Io {
+                    context: "Failed to read inotify events".to_string(),
+                    source: e,
+                })?;
+
+            for event in events {
+                let Some(names) = self.watched.get(&event.wd) else {
+                    continue;
+                };
+                let relevant = match event.name {
+                    Some(name) => names.contains(name),
+                    None => !event.mask.contains(EventMask::IGNORED),
+                };
+                if relevant {
+                    debug!("Change detected: {:?} {:?}", event.mask, event.name);
+                    thread::sleep(DEBOUNCE);
+                    self.drain();
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Discard the events of the burst that ended the wait.
+    fn drain(&mut self) {
+        let mut buffer = [0u8; EVENT_BUFFER_SIZE];
+        while self
+            .inotify
+            .read_events(&mut buffer)
+            .is_ok_and(|mut events| events.next().is_some())
+        {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::LinkType;
+    use assert_fs::TempDir;
+    use assert_fs::prelude::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_chain_paths() {
+        let mut chain = SymlinkChain::new(PathBuf::from("/bin/a"));
+        chain.add_link(PathBuf::from("/opt/b"), false, LinkType::Symlink);
+
+        assert_eq!(
+            chain_paths(&chain),
+            vec![PathBuf::from("/bin/a"), PathBuf::from("/opt/b")]
+        );
+    }
+
+    #[test]
+    fn test_watch_targets_includes_ancestors() {
+        let targets = watch_targets(&[
+            PathBuf::from("/run/current-system/sw/bin/foo"),
+            PathBuf::from("/run/booted-system"),
+        ]);
+
+        assert_eq!(
+            targets[Path::new("/run")],
+            BTreeSet::from([
+                OsString::from("booted-system"),
+                OsString::from("current-system")
+            ])
+        );
+        assert_eq!(
+            targets[Path::new("/run/current-system/sw/bin")],
+            BTreeSet::from([OsString::from("foo")])
+        );
+        assert!(targets.contains_key(Path::new("/")));
+    }
+
+    #[test]
+    fn test_watcher_wait_on_relink() {
+        let temp = TempDir::new().unwrap();
+        let first = temp.child("first");
+        first.touch().unwrap();
+        let second = temp.child("second");
+        second.touch().unwrap();
+        let link = temp.child("link");
+        link.symlink_to_file(first.path()).unwrap();
+
+        let link_path = link.to_path_buf();
+        let second_path = second.to_path_buf();
+        let handle = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(100));
+            std::fs::remove_file(&link_path).unwrap();
+            std::os::unix::fs::symlink(&second_path, &link_path).unwrap();
+        });
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher
+            .watch(&[link.to_path_buf(), first.to_path_buf()])
+            .unwrap();
+        watcher.wait().unwrap();
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_watcher_sees_change_before_wait() {
+        let temp = TempDir::new().unwrap();
+        let target = temp.child("target");
+        target.touch().unwrap();
+        let link = temp.child("link");
+        link.symlink_to_file(target.path()).unwrap();
+
+        let mut watcher = Watcher::new().unwrap();
+        watcher.watch(&[link.to_path_buf()]).unwrap();
+        // Changed while the chain was being resolved and printed
+        std::fs::remove_file(link.path()).unwrap();
+        watcher.watch(&[link.to_path_buf()]).unwrap();
+
+        watcher.wait().unwrap();
+    }
+}
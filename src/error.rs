@@ -28,9 +28,21 @@ pub enum SymseekError {
     #[error("Cycle detected in chain at {path:?}")]
     CycleDetected { path: PathBuf },
 
+    #[error("Chain exceeds the maximum depth of {max_depth} hops at {path:?}")]
+    MaxDepthExceeded { path: PathBuf, max_depth: usize },
+
+    #[error("Unit '{name}' is masked by {path:?}")]
+    UnitMasked { name: String, path: PathBuf },
+
     #[error("Failed to parse wrapper at {path:?}: {reason}")]
     WrapperParsing { path: PathBuf, reason: String },
 
+    #[error("Failed to read Nix database {path:?}: {reason}")]
+    NixDatabase { path: PathBuf, reason: String },
+
+    #[error("Invalid JSON chain: {reason}")]
+    InvalidJson { reason: String },
+
     #[error("JSON serialization failed: {0}")]
     JsonSerialization(#[from] serde_json::Error),
 }
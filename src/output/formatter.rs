@@ -1,65 +1,752 @@
-use crate::core::types::{FileKind, LinkType, ScriptType, SymlinkChain, WrapperKind};
+use crate::core::desktop::DesktopEntry;
+use crate::core::detector::compiler_cache::CompilerCache;
+use crate::core::detector::launcher::{AppLauncher, LauncherKind};
+use crate::core::detector::multi_call::Applet;
+use crate::core::detector::self_extracting::{AppImageType, BundleInfo, SelfExtractingKind};
+use crate::core::diff::{Change, DiffReport};
+use crate::core::events::{DetectorOutcome, ResolveEvent};
+use crate::core::homebrew::HomebrewKeg;
+use crate::core::inventory::{Inventory, InventoryMatch, Resolution};
+use crate::core::mount::{self, MountInfo};
+use crate::core::process::ProcessInfo;
+use crate::core::profile::ProfileLayer;
+use crate::core::shadow::MatchStatus;
+use crate::core::types::{
+    FileId, FileKind, LinkType, ScriptType, SymlinkChain, SymlinkNode, WrapperKind,
+};
+use crate::core::unit::UnitFile;
 use crate::output::styles::TreeChars;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Marks where resolution stopped at the symlink limit
+const TRUNCATED_LABEL: &str = " [truncated at symlink limit]";
 
 pub fn print_tree(chain: &SymlinkChain) {
-    println!("{}", format_path(&chain.origin));
+    let truncated = if chain.is_empty() && chain.truncated {
+        TRUNCATED_LABEL
+    } else {
+        ""
+    };
+    println!(
+        "{}{}{truncated}",
+        format_path(&chain.origin),
+        profile_labels(&chain.origin_profiles)
+    );
+    for detail in sibling_details(&chain.origin_profiles) {
+        println!("     {detail}");
+    }
 
     if chain.is_empty() {
         return;
     }
 
     let chars = TreeChars::default();
+    let same_file_hops = chain.same_file_hops();
+    let mount_crossings = mount::crossings(chain);
     for (idx, node) in chain.links.iter().enumerate() {
         let is_last = idx == chain.links.len() - 1;
         let prefix = if is_last { chars.last } else { chars.branch };
 
-        let (indicator, label) = link_type_info(&node.link_type);
+        let (indicator, mut label) = link_type_info(&node.link_type);
+        label.push_str(&profile_labels(&node.profiles));
+        if let Some(keg) = &node.keg {
+            label.push_str(&keg_label(keg));
+        }
+        if let Some(app) = &node.launcher {
+            label.push_str(&launcher_label(app));
+        }
+        if let Some(cache) = &node.compiler_cache {
+            label.push_str(&compiler_cache_label(cache));
+        }
+        if let Some(bundle) = &node.bundle {
+            label.push_str(bundle_label(bundle.kind));
+        }
+        if let Some(applet) = &node.applet {
+            label.push_str(&format!(" [{}]", applet.kind.as_str()));
+        }
+        if let Some(earlier) = same_file_hops[idx] {
+            label.push_str(&format!(" [same file as hop {}]", earlier + 1));
+        }
+        if is_last && chain.truncated {
+            label.push_str(TRUNCATED_LABEL);
+        }
 
         println!(
             "{}{}{} {}{}",
             prefix,
             chars.connector,
             indicator,
-            format_path(&node.target),
+            format_node_target(node),
             label
         );
+
+        let continuation = if is_last { " " } else { chars.vertical };
+        if mount_crossings[idx]
+            && let Some(mount) = &node.mount
+        {
+            println!(
+                "{continuation}    warning: {}",
+                mount_crossing_details(mount)
+            );
+        }
+        for detail in node_details(node) {
+            println!("{continuation}    {detail}");
+        }
+    }
+}
+
+fn profile_labels(layers: &[ProfileLayer]) -> String {
+    layers
+        .iter()
+        .map(|layer| match layer.generation {
+            Some(generation) => format!(" [{} generation {generation}]", layer.name),
+            None => format!(" [{} profile]", layer.name),
+        })
+        .collect()
+}
+
+fn keg_label(keg: &HomebrewKeg) -> String {
+    let mut parts = vec![format!("brew {} {}", keg.formula, keg.version)];
+    if keg.keg_only {
+        parts.push("keg-only".to_string());
+    }
+    if keg.linked {
+        parts.push("linked".to_string());
+    } else if !keg.keg_only {
+        parts.push("unlinked".to_string());
+    }
+    format!(" [{}]", parts.join(", "))
+}
+
+fn launcher_label(app: &AppLauncher) -> String {
+    let kind = match app.kind {
+        LauncherKind::Flatpak => "flatpak",
+        LauncherKind::Snap => "snap",
+    };
+    format!(" [{kind} {}]", app.app_id)
+}
+
+fn compiler_cache_label(cache: &CompilerCache) -> String {
+    format!(" [{} as {}]", cache.kind.as_str(), cache.compiler_name)
+}
+
+const fn bundle_label(kind: SelfExtractingKind) -> &'static str {
+    match kind {
+        SelfExtractingKind::AppImage(AppImageType::Type1) => " [AppImage type 1]",
+        SelfExtractingKind::AppImage(AppImageType::Type2) => " [AppImage type 2]",
+        SelfExtractingKind::Makeself => " [makeself archive]",
+        SelfExtractingKind::Upx => " [UPX packed]",
+        SelfExtractingKind::PyInstaller => " [PyInstaller bundle]",
     }
 }
 
+fn applet_details(applet: &Applet) -> String {
+    let status = if applet.compiled_in {
+        "compiled in"
+    } else {
+        "not compiled in"
+    };
+    format!("invoked as applet `{}`, {status}", applet.name)
+}
+
+fn mount_crossing_details(mount: &MountInfo) -> String {
+    let mut flags = vec![if mount.read_only { "ro" } else { "rw" }];
+    if mount.is_bind() {
+        flags.push("bind");
+    }
+    if mount.is_overlay() {
+        flags.push("overlay");
+    }
+    format!(
+        "crosses onto mount {} ({} from {}, {})",
+        format_path(&mount.mount_point),
+        mount.fs_type,
+        mount.source,
+        flags.join(", ")
+    )
+}
+
+fn file_id_details(file_id: &FileId) -> String {
+    format!(
+        "inode {} on device {}, {} hard links",
+        file_id.ino,
+        format_device(file_id.dev),
+        file_id.nlink
+    )
+}
+
+/// Split a Linux device number into `major:minor`.
+fn format_device(dev: u64) -> String {
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    format!("{major}:{minor}")
+}
+
+fn bundle_details(bundle: &BundleInfo) -> Option<String> {
+    let mut summary = Vec::new();
+    if let Some(offset) = bundle.payload_offset {
+        summary.push(format!("squashfs at offset {offset}"));
+    }
+    if let Some(entry_point) = &bundle.entry_point {
+        summary.push(format!("entry point {entry_point}"));
+    }
+    if let Some(desktop_file) = &bundle.desktop_file {
+        summary.push(format!("desktop file {desktop_file}"));
+    }
+    (!summary.is_empty()).then(|| summary.join(", "))
+}
+
+fn sibling_details(layers: &[ProfileLayer]) -> Vec<String> {
+    layers
+        .iter()
+        .flat_map(|layer| {
+            layer.siblings.iter().map(|sibling| {
+                let target = sibling
+                    .target
+                    .as_deref()
+                    .map_or_else(|| "missing".to_string(), format_path);
+                format!("{} generation {}: {target}", layer.name, sibling.generation)
+            })
+        })
+        .collect()
+}
+
+fn node_details(node: &SymlinkNode) -> Vec<String> {
+    let mut details = sibling_details(&node.profiles);
+
+    if let Some(app) = &node.launcher {
+        details.push(format!(
+            "deployment: {}, command: {}",
+            format_path(&app.deployment),
+            app.command
+        ));
+    }
+
+    if let Some(summary) = node.bundle.as_ref().and_then(bundle_details) {
+        details.push(summary);
+    }
+
+    if let Some(applet) = &node.applet {
+        details.push(applet_details(applet));
+    }
+
+    if let Some(file_id) = node.file_id
+        && file_id.nlink > 1
+        && node.link_type != LinkType::Symlink
+    {
+        details.push(file_id_details(&file_id));
+    }
+
+    if let Some(info) = &node.store_info {
+        if let Some(deriver) = &info.deriver {
+            details.push(format!("deriver: {}", format_path(deriver)));
+        }
+        let mut summary = Vec::new();
+        if let Some(nar_size) = info.nar_size {
+            summary.push(format!("nar size {}", format_size(nar_size)));
+        }
+        if let Some(time) = info.registration_time {
+            summary.push(format!("registered {}", format_unix_time(time)));
+        }
+        summary.push(format!("{} references", info.references.len()));
+        if info.is_gc_root {
+            summary.push("GC root".to_string());
+        }
+        details.push(summary.join(", "));
+    }
+
+    if let Some(receipt) = node.keg.as_ref().and_then(|keg| keg.receipt.as_ref()) {
+        let mut summary = vec![
+            if receipt.installed_on_request {
+                "installed on request"
+            } else {
+                "installed as dependency"
+            }
+            .to_string(),
+            if receipt.poured_from_bottle {
+                "poured from bottle"
+            } else {
+                "built from source"
+            }
+            .to_string(),
+        ];
+        if let Some(time) = receipt.time {
+            summary.push(format!("installed {}", format_unix_time(time)));
+        }
+        details.push(summary.join(", "));
+    }
+
+    details
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Format seconds since the Unix epoch as a UTC date and time.
+fn format_unix_time(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let time = secs.rem_euclid(86_400);
+
+    // Civil-from-days conversion from Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
 fn link_type_info(link_type: &LinkType) -> (&'static str, String) {
     match link_type {
         LinkType::Symlink => ("", String::new()),
-        LinkType::Wrapper(wrapper_kind) => match wrapper_kind {
-            WrapperKind::Binary => ("", " [binary wrapper]".to_string()),
-            WrapperKind::Text(script_type) => {
-                let label = match script_type {
-                    ScriptType::Shell => " [sh wrapper]",
-                    ScriptType::Python => " [py wrapper]",
-                    ScriptType::Perl => " [pl wrapper]",
-                    ScriptType::Unknown => " [script wrapper]",
-                };
-                ("", label.to_string())
+        LinkType::Wrapper(wrapper_kind) => ("", format!(" [{}]", wrapper_label(wrapper_kind))),
+        LinkType::Terminal(file_kind) => ("", format!(" [{}]", file_label(file_kind))),
+    }
+}
+
+const fn wrapper_label(wrapper_kind: &WrapperKind) -> &'static str {
+    match wrapper_kind {
+        WrapperKind::Binary => "binary wrapper",
+        WrapperKind::Text(ScriptType::Shell) => "sh wrapper",
+        WrapperKind::Text(ScriptType::Python) => "py wrapper",
+        WrapperKind::Text(ScriptType::Perl) => "pl wrapper",
+        WrapperKind::Text(ScriptType::Unknown) => "script wrapper",
+    }
+}
+
+const fn file_label(file_kind: &FileKind) -> &'static str {
+    match file_kind {
+        FileKind::Binary => "binary",
+        FileKind::Text => "plaintext",
+    }
+}
+
+pub fn print_diff(report: &DiffReport) {
+    if report.is_empty() {
+        println!("No differences");
+        return;
+    }
+
+    let chars = TreeChars::default();
+    for chain_diff in &report.changed {
+        println!("{}", format_path(&chain_diff.origin));
+        for (idx, change) in chain_diff.changes.iter().enumerate() {
+            let is_last = idx == chain_diff.changes.len() - 1;
+            let prefix = if is_last { chars.last } else { chars.branch };
+            println!("{}{} {}", prefix, chars.connector, describe_change(change));
+        }
+        println!();
+    }
+
+    for origin in &report.added {
+        println!("+ {} [new target]", format_path(origin));
+    }
+    for origin in &report.removed {
+        println!("- {} [removed target]", format_path(origin));
+    }
+}
+
+fn describe_change(change: &Change) -> String {
+    match change {
+        Change::HopAdded(path) => format!("+ {}", format_path(path)),
+        Change::HopRemoved(path) => format!("- {}", format_path(path)),
+        Change::StorePathChanged { before, after } => {
+            format!("{} -> {}", format_path(before), format_path(after))
+        }
+        Change::WrapperKindChanged {
+            path,
+            before,
+            after,
+        } => format!(
+            "{} [{} -> {}]",
+            format_path(path),
+            wrapper_label(before),
+            wrapper_label(after)
+        ),
+        Change::TerminalKindChanged { before, after } => {
+            format!(
+                "final file [{} -> {}]",
+                file_label(before),
+                file_label(after)
+            )
+        }
+    }
+}
+
+pub fn print_inventory(inventory: &Inventory) {
+    let summary = inventory.summary();
+    println!("Inventory of {} commands in PATH", summary.commands);
+    println!(
+        "{} shadowed ({} resolving differently), {} wrappers, {} broken\n",
+        summary.shadowed, summary.conflicts, summary.wrappers, summary.broken
+    );
+
+    for entry in &inventory.entries {
+        let winner = entry.winner();
+        let mut line = match &winner.resolution {
+            Resolution::Resolved(chain) => {
+                let mut line = format!("{} -> {}", entry.name, format_path(&source_of(winner)));
+                let wrappers = chain.wrapper_count();
+                if wrappers > 0 {
+                    line.push_str(&format!(" [{wrappers} wrappers]"));
+                }
+                line
+            }
+            Resolution::Broken(reason) => format!("{} [broken: {reason}]", entry.name),
+        };
+        if entry.has_conflict() {
+            line.push_str(&format!(
+                " [shadows {}, resolves differently]",
+                entry.matches.len() - 1
+            ));
+        } else if entry.is_shadowed() {
+            line.push_str(&format!(" [shadows {}]", entry.matches.len() - 1));
+        }
+        println!("{line}");
+    }
+}
+
+fn source_of(inventory_match: &InventoryMatch) -> PathBuf {
+    inventory_match
+        .source()
+        .unwrap_or_else(|| inventory_match.path.clone())
+}
+
+pub fn print_match_status(status: MatchStatus, same_file_as: Option<usize>) {
+    let label = match status {
+        MatchStatus::Active => "[active]",
+        MatchStatus::Duplicate => "[shadowed, same final target]",
+        MatchStatus::Shadowed => "[shadowed, resolves differently]",
+    };
+    match same_file_as {
+        Some(earlier) => println!("{label} [same file as match {}]", earlier + 1),
+        None => println!("{label}"),
+    }
+}
+
+pub fn print_process(process: &ProcessInfo, loads: bool) {
+    match &process.argv0 {
+        Some(argv0) => println!("PID {} invoked as `{argv0}`", process.pid),
+        None => println!("PID {}", process.pid),
+    }
+    let deleted = if process.deleted { " [deleted]" } else { "" };
+    println!("loaded {}{deleted}", format_path(&process.exe));
+    if loads {
+        println!("[final target is the loaded executable]\n");
+    } else {
+        println!("[warning: final target differs from the loaded executable]\n");
+    }
+}
+
+pub fn print_desktop(entry: &DesktopEntry) {
+    match &entry.name {
+        Some(name) => println!("Desktop entry {} ({name})", entry.id),
+        None => println!("Desktop entry {}", entry.id),
+    }
+    println!("from {}", format_path(&entry.path));
+    println!("Exec: {}", entry.exec);
+    if let Some(try_exec) = &entry.try_exec {
+        match &entry.try_exec_path {
+            Some(path) => println!("TryExec: {try_exec} -> {}", format_path(path)),
+            None => println!("TryExec: {try_exec} [not found, entry is hidden]"),
+        }
+    }
+    println!();
+}
+
+pub fn print_unit(unit: &UnitFile, resolutions: &[Resolution]) {
+    println!("Unit {} from {}", unit.name, format_path(&unit.path));
+    for drop_in in &unit.drop_ins {
+        println!("drop-in {}", format_path(drop_in));
+    }
+    if let Some(path_var) = &unit.path_var {
+        println!("PATH={path_var}");
+    }
+    println!();
+
+    for (command, resolution) in unit.commands.iter().zip(resolutions) {
+        let mut line = command.directive.clone();
+        if !command.prefixes.is_empty() {
+            line.push_str(&format!(" [{}]", command.prefixes));
+        }
+        line.push_str(&format!(": {}", command.executable));
+        if let Some(argv0) = &command.argv0 {
+            line.push_str(&format!(" as `{argv0}`"));
+        }
+
+        match resolution {
+            Resolution::Resolved(chain) => {
+                println!("{line}");
+                print_tree(chain);
             }
-        },
-        LinkType::Terminal(file_kind) => match file_kind {
-            FileKind::Binary => ("", " [binary]".to_string()),
-            FileKind::Text => ("", " [plaintext]".to_string()),
-        },
+            Resolution::Broken(reason) => println!("{line} [broken: {reason}]"),
+        }
+        print_separator();
     }
 }
 
+/// Print the events of a resolution as a narrative on stderr.
+pub fn print_explanation(events: &[ResolveEvent]) {
+    for line in explanation_lines(events) {
+        eprintln!("{line}");
+    }
+}
+
+fn explanation_lines(events: &[ResolveEvent]) -> Vec<String> {
+    events
+        .iter()
+        .map(|event| match event {
+            ResolveEvent::Started { path } => format!("explain: resolving {}", format_path(path)),
+            ResolveEvent::Visited { path, depth } => {
+                format!("  [{depth}] looking at {}", format_path(path))
+            }
+            ResolveEvent::Syscall {
+                call,
+                path,
+                outcome,
+                elapsed,
+            } => format!(
+                "      {} {}: {outcome} ({elapsed:?})",
+                call.as_str(),
+                format_path(path)
+            ),
+            ResolveEvent::DetectorRan {
+                detector,
+                outcome,
+                cached,
+                elapsed,
+                ..
+            } => {
+                let timing = if *cached {
+                    "cached".to_string()
+                } else {
+                    format!("{elapsed:?}")
+                };
+                match outcome {
+                    DetectorOutcome::Matched(found) => {
+                        format!("      {}: found {found} ({timing})", detector.as_str())
+                    }
+                    DetectorOutcome::NotMatched => {
+                        format!("      {}: no match ({timing})", detector.as_str())
+                    }
+                    DetectorOutcome::Disabled => format!("      {}: disabled", detector.as_str()),
+                    DetectorOutcome::Failed(reason) => {
+                        format!("      {}: failed: {reason}", detector.as_str())
+                    }
+                }
+            }
+            ResolveEvent::HopAdded {
+                index,
+                path,
+                link_type,
+            } => {
+                let role = match link_type {
+                    LinkType::Symlink => "symlink to",
+                    LinkType::Wrapper(_) => "wrapper",
+                    LinkType::Terminal(_) => "final target",
+                };
+                format!("      => hop {}: {role} {}", index + 1, format_path(path))
+            }
+            ResolveEvent::Finished {
+                links,
+                truncated,
+                elapsed,
+            } => {
+                let truncated = if *truncated { ", truncated" } else { "" };
+                format!("  resolved in {links} hops{truncated} ({elapsed:?})")
+            }
+            ResolveEvent::Failed { error, elapsed } => {
+                format!("  failed after {elapsed:?}: {error}")
+            }
+        })
+        .collect()
+}
+
 pub fn print_header(count: usize) {
     println!("Found {count} matches in PATH\n");
 }
 
+const CHANGE_MARKER: &str = "--- change detected, re-resolving ---";
+
+pub fn print_change_marker() {
+    println!("\n{CHANGE_MARKER}\n");
+}
+
+/// Report a change on stderr, keeping stdout for machine-readable output.
+pub fn eprint_change_marker() {
+    eprintln!("{CHANGE_MARKER}");
+}
+
 pub fn print_separator() {
     println!();
 }
 
+fn format_node_target(node: &SymlinkNode) -> String {
+    match &node.store_path {
+        Some(store_path) if store_path.relative.as_os_str().is_empty() => store_path.to_string(),
+        Some(store_path) => format!("{store_path} {}", store_path.relative.display()),
+        None => format_path(&node.target),
+    }
+}
+
 fn format_path(path: &Path) -> String {
     path_clean::clean(path).to_str().map_or_else(
         || "<invalid UTF-8>".to_string(),
         std::string::ToString::to_string,
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keg_label() {
+        let mut keg = HomebrewKeg {
+            prefix: PathBuf::from("/home/linuxbrew/.linuxbrew"),
+            formula: "openssl@3".to_string(),
+            version: "3.3.0".to_string(),
+            relative: PathBuf::from("bin/openssl"),
+            linked: false,
+            keg_only: true,
+            receipt: None,
+        };
+        assert_eq!(keg_label(&keg), " [brew openssl@3 3.3.0, keg-only]");
+
+        keg.keg_only = false;
+        assert_eq!(keg_label(&keg), " [brew openssl@3 3.3.0, unlinked]");
+
+        keg.linked = true;
+        assert_eq!(keg_label(&keg), " [brew openssl@3 3.3.0, linked]");
+    }
+
+    #[test]
+    fn test_bundle_details() {
+        let mut bundle = BundleInfo {
+            kind: SelfExtractingKind::AppImage(AppImageType::Type2),
+            payload_offset: Some(193_728),
+            entry_point: Some("usr/bin/hello".to_string()),
+            desktop_file: Some("hello.desktop".to_string()),
+        };
+        assert_eq!(
+            bundle_details(&bundle).as_deref(),
+            Some(
+                "squashfs at offset 193728, entry point usr/bin/hello, desktop file hello.desktop"
+            )
+        );
+
+        bundle.kind = SelfExtractingKind::Upx;
+        bundle.payload_offset = None;
+        bundle.entry_point = None;
+        bundle.desktop_file = None;
+        assert_eq!(bundle_details(&bundle), None);
+    }
+
+    #[test]
+    fn test_mount_crossing_details() {
+        let mount = MountInfo {
+            mount_id: 30,
+            mount_point: PathBuf::from("/nix/store"),
+            root: PathBuf::from("/nix/store"),
+            fs_type: "ext4".to_string(),
+            source: "/dev/nvme0n1p2".to_string(),
+            read_only: true,
+        };
+        assert_eq!(
+            mount_crossing_details(&mount),
+            "crosses onto mount /nix/store (ext4 from /dev/nvme0n1p2, ro, bind)"
+        );
+    }
+
+    #[test]
+    fn test_explanation_lines() {
+        use crate::core::events::Syscall;
+        use crate::core::resolver::Detector;
+        use std::time::Duration;
+
+        let events = [
+            ResolveEvent::Started {
+                path: PathBuf::from("/bin/a"),
+            },
+            ResolveEvent::Syscall {
+                call: Syscall::ReadLink,
+                path: PathBuf::from("/bin/a"),
+                outcome: "-> b".to_string(),
+                elapsed: Duration::from_micros(3),
+            },
+            ResolveEvent::DetectorRan {
+                detector: Detector::CompilerCache,
+                path: PathBuf::from("/bin/b"),
+                outcome: DetectorOutcome::Disabled,
+                cached: false,
+                elapsed: Duration::ZERO,
+            },
+            ResolveEvent::DetectorRan {
+                detector: Detector::NixProgramName,
+                path: PathBuf::from("/bin/b"),
+                outcome: DetectorOutcome::NotMatched,
+                cached: true,
+                elapsed: Duration::ZERO,
+            },
+            ResolveEvent::HopAdded {
+                index: 0,
+                path: PathBuf::from("/bin/b"),
+                link_type: LinkType::Terminal(FileKind::Binary),
+            },
+        ];
+
+        assert_eq!(
+            explanation_lines(&events),
+            vec![
+                "explain: resolving /bin/a",
+                "      readlink /bin/a: -> b (3µs)",
+                "      compiler cache: disabled",
+                "      Nix program name: no match (cached)",
+                "      => hop 1: final target /bin/b",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_device() {
+        assert_eq!(format_device(0x0803), "8:3");
+        assert_eq!(format_device(0x0001_0301), "259:1");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(274_432), "268.0 KiB");
+        assert_eq!(format_size(31_457_280), "30.0 MiB");
+    }
+
+    #[test]
+    fn test_format_unix_time() {
+        assert_eq!(format_unix_time(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_unix_time(1_700_000_000), "2023-11-14 22:13:20 UTC");
+        assert_eq!(format_unix_time(951_782_400), "2000-02-29 00:00:00 UTC");
+    }
+}
@@ -1,63 +1,691 @@
 //! JSON output formatting for symlink chains.
 
-use crate::core::types::{FileKind, LinkType, ScriptType, SymlinkChain, WrapperKind};
-use crate::error::Result;
-use serde::Serialize;
-use std::path::Path;
+use crate::core::desktop::DesktopEntry;
+use crate::core::detector::compiler_cache::{CompilerCache, CompilerCacheKind};
+use crate::core::detector::launcher::{AppLauncher, LauncherKind};
+use crate::core::detector::multi_call::{Applet, MultiCallKind};
+use crate::core::detector::self_extracting::{AppImageType, BundleInfo, SelfExtractingKind};
+use crate::core::diff::{Change, DiffReport};
+use crate::core::homebrew::{HomebrewKeg, InstallReceipt};
+use crate::core::inventory::{Inventory, Resolution};
+use crate::core::mount::{self, MountInfo};
+use crate::core::nix_db::StorePathInfo;
+use crate::core::process::ProcessInfo;
+use crate::core::profile::{ProfileLayer, SiblingGeneration};
+use crate::core::shadow::MatchStatus;
+use crate::core::store::{StoreFlavor, StorePath};
+use crate::core::types::{
+    FileId, FileKind, LinkType, ScriptType, SymlinkChain, SymlinkNode, WrapperKind,
+};
+use crate::core::unit::UnitFile;
+use crate::error::{Result, SymseekError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Version of the JSON output format.
+///
+/// Bumped whenever a field is removed or its meaning changes. Documents
+/// without a `schema_version` field are treated as version 1.
+pub const SCHEMA_VERSION: u32 = 1;
 
 /// JSON representation of a symlink chain
-#[derive(Debug, Serialize, serde::Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+#[schemars(title = "symseek chain")]
 pub struct JsonChain {
+    /// Version of the output format this document was written with
+    #[serde(default = "default_schema_version")]
+    pub schema_version: u32,
+    /// Path the resolution started from
     pub origin: String,
+    /// Nix profiles traversed when opening the origin
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub origin_profiles: Vec<JsonProfileLayer>,
+    /// Mount the origin lives on
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub origin_mount: Option<JsonMount>,
+    /// Every hop followed from the origin, in order
     pub links: Vec<JsonLink>,
+    /// Whether resolution stopped at the symlink limit, so the last link
+    /// is not the final target
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub truncated: bool,
+    /// Role of this chain among several PATH matches of the same name
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    #[schemars(extend("enum" = ["active", "duplicate", "shadowed", null]))]
+    pub path_status: Option<String>,
+    /// Index of the first earlier PATH match whose final target is the same
+    /// file on disk, e.g. a hard link or a path through a bind mount
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub same_file_as: Option<usize>,
 }
 
 /// JSON representation of a link in the chain
-#[derive(Debug, Serialize, serde::Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct JsonLink {
+    /// Path of this hop
     pub path: String,
+    /// How this hop was reached from the previous one
     #[serde(rename = "type")]
+    #[schemars(extend("enum" = ["symlink", "wrapper", "terminal"]))]
     pub link_type: String,
+    /// Kind of wrapper, present only when `type` is `wrapper`
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(extend("enum" = [
+        "binary",
+        "shell_script",
+        "python_script",
+        "perl_script",
+        "unknown_script"
+    ]))]
     pub wrapper_kind: Option<String>,
+    /// Kind of file, present only when `type` is `terminal`
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(extend("enum" = ["binary", "text"]))]
     pub file_kind: Option<String>,
+    /// Whether this hop ends the chain
     #[serde(skip_serializing_if = "std::ops::Not::not", default)]
     pub is_final: bool,
+    /// Decomposition of `path`, present only for paths in a Nix or Guix store
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub store_path: Option<JsonStorePath>,
+    /// Nix database information about the store entry, present only when
+    /// requested with `--nix-info`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub store_info: Option<JsonStoreInfo>,
+    /// Nix profiles traversed when opening this hop
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub profiles: Vec<JsonProfileLayer>,
+    /// Device and inode of `path` itself, without following symlinks
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub file_id: Option<JsonFileId>,
+    /// Index of the first earlier hop that is the same file on disk
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub same_file_as: Option<usize>,
+    /// Mount `path` itself lives on
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub mount: Option<JsonMount>,
+    /// Whether this hop lands on a different mount than the previous hop,
+    /// or than the origin for the first hop
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub crosses_mount: bool,
+    /// Homebrew keg containing `path`, present only for paths in a Cellar
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub keg: Option<JsonHomebrewKeg>,
+    /// Application started by this hop, present only for Flatpak and Snap
+    /// launchers
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub launcher: Option<JsonAppLauncher>,
+    /// Compiler cache or distributor masquerading as a compiler, present
+    /// only for hops followed to the real compiler
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub compiler_cache: Option<JsonCompilerCache>,
+    /// Details of a self-extracting target such as an AppImage
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub bundle: Option<JsonBundle>,
+    /// Applet selected by the name a multi-call binary such as BusyBox was
+    /// invoked as
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub applet: Option<JsonApplet>,
+}
+
+/// JSON representation of a multi-call binary applet
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonApplet {
+    /// Multi-call binary providing the applet
+    #[schemars(extend("enum" = ["busybox", "toybox", "coreutils", "uutils"]))]
+    pub multi_call: String,
+    /// Name the binary was invoked as
+    pub name: String,
+    /// Whether the applet is compiled into the binary
+    pub compiled_in: bool,
+}
+
+impl JsonApplet {
+    fn from_applet(applet: &Applet) -> Self {
+        Self {
+            multi_call: applet.kind.as_str().to_string(),
+            name: applet.name.clone(),
+            compiled_in: applet.compiled_in,
+        }
+    }
+
+    fn into_applet(self, path: &str) -> Result<Applet> {
+        let kind = match self.multi_call.as_str() {
+            "busybox" => MultiCallKind::Busybox,
+            "toybox" => MultiCallKind::Toybox,
+            "coreutils" => MultiCallKind::Coreutils,
+            "uutils" => MultiCallKind::Uutils,
+            other => return Err(invalid_field(path, "applet.multi_call", Some(other))),
+        };
+        Ok(Applet {
+            kind,
+            name: self.name,
+            compiled_in: self.compiled_in,
+        })
+    }
+}
+
+/// JSON representation of a self-extracting file
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonBundle {
+    /// Self-extracting format
+    #[schemars(extend("enum" = ["appimage", "makeself", "upx", "pyinstaller"]))]
+    pub kind: String,
+    /// AppImage type, present only when `kind` is `appimage`
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub appimage_type: Option<u8>,
+    /// Offset of the embedded squashfs image of a type 2 AppImage
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub payload_offset: Option<u64>,
+    /// File `AppRun` points to inside the AppImage
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub entry_point: Option<String>,
+    /// Desktop file at the root of the AppImage
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub desktop_file: Option<String>,
+}
+
+impl JsonBundle {
+    fn from_bundle(bundle: &BundleInfo) -> Self {
+        let (kind, appimage_type) = match bundle.kind {
+            SelfExtractingKind::AppImage(AppImageType::Type1) => ("appimage", Some(1)),
+            SelfExtractingKind::AppImage(AppImageType::Type2) => ("appimage", Some(2)),
+            SelfExtractingKind::Makeself => ("makeself", None),
+            SelfExtractingKind::Upx => ("upx", None),
+            SelfExtractingKind::PyInstaller => ("pyinstaller", None),
+        };
+        Self {
+            kind: kind.to_string(),
+            appimage_type,
+            payload_offset: bundle.payload_offset,
+            entry_point: bundle.entry_point.clone(),
+            desktop_file: bundle.desktop_file.clone(),
+        }
+    }
+
+    fn into_bundle(self, path: &str) -> Result<BundleInfo> {
+        let kind = match (self.kind.as_str(), self.appimage_type) {
+            ("appimage", Some(1)) => SelfExtractingKind::AppImage(AppImageType::Type1),
+            ("appimage", Some(2)) => SelfExtractingKind::AppImage(AppImageType::Type2),
+            ("appimage", other) => {
+                let other = other.map(|t| t.to_string());
+                return Err(invalid_field(
+                    path,
+                    "bundle.appimage_type",
+                    other.as_deref(),
+                ));
+            }
+            ("makeself", _) => SelfExtractingKind::Makeself,
+            ("upx", _) => SelfExtractingKind::Upx,
+            ("pyinstaller", _) => SelfExtractingKind::PyInstaller,
+            (other, _) => return Err(invalid_field(path, "bundle.kind", Some(other))),
+        };
+        Ok(BundleInfo {
+            kind,
+            payload_offset: self.payload_offset,
+            entry_point: self.entry_point,
+            desktop_file: self.desktop_file,
+        })
+    }
+}
+
+/// JSON representation of a mount from `/proc/self/mountinfo`
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonMount {
+    /// Mount id, unique among the mounts of the process
+    pub mount_id: u32,
+    /// Where the mount is attached
+    pub mount_point: String,
+    /// Directory of the filesystem that appears at `mount_point`
+    pub root: String,
+    /// Filesystem type, e.g. `ext4` or `overlay`
+    pub fs_type: String,
+    /// Device or other source of the filesystem
+    pub source: String,
+    /// Whether the mount or its filesystem is read-only
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub read_only: bool,
+    /// Whether a subdirectory of the filesystem is bind-mounted, derived
+    /// from `root`
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub bind: bool,
+    /// Whether the filesystem is an overlay, derived from `fs_type`
+    #[serde(skip_serializing_if = "std::ops::Not::not", default)]
+    pub overlay: bool,
+}
+
+impl JsonMount {
+    fn from_mount(mount: &MountInfo) -> Self {
+        Self {
+            mount_id: mount.mount_id,
+            mount_point: format_path(&mount.mount_point),
+            root: format_path(&mount.root),
+            fs_type: mount.fs_type.clone(),
+            source: mount.source.clone(),
+            read_only: mount.read_only,
+            bind: mount.is_bind(),
+            overlay: mount.is_overlay(),
+        }
+    }
+
+    fn into_mount(self) -> MountInfo {
+        MountInfo {
+            mount_id: self.mount_id,
+            mount_point: PathBuf::from(self.mount_point),
+            root: PathBuf::from(self.root),
+            fs_type: self.fs_type,
+            source: self.source,
+            read_only: self.read_only,
+        }
+    }
+}
+
+/// JSON representation of a file's identity on disk
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonFileId {
+    /// Device number of the containing filesystem
+    pub dev: u64,
+    /// Inode number
+    pub ino: u64,
+    /// Number of hard links to the inode
+    pub nlink: u64,
+}
+
+impl JsonFileId {
+    const fn from_file_id(file_id: &FileId) -> Self {
+        Self {
+            dev: file_id.dev,
+            ino: file_id.ino,
+            nlink: file_id.nlink,
+        }
+    }
+
+    const fn into_file_id(self) -> FileId {
+        FileId {
+            dev: self.dev,
+            ino: self.ino,
+            nlink: self.nlink,
+        }
+    }
+}
+
+/// JSON representation of a compiler cache masquerade
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonCompilerCache {
+    /// Cache or distribution tool
+    #[schemars(extend("enum" = ["ccache", "sccache", "distcc", "icecc"]))]
+    pub tool: String,
+    /// Compiler name the tool was invoked as
+    pub compiler_name: String,
+    /// Real compiler the tool would execute
+    pub compiler: String,
+}
+
+impl JsonCompilerCache {
+    fn from_cache(cache: &CompilerCache) -> Self {
+        Self {
+            tool: cache.kind.as_str().to_string(),
+            compiler_name: cache.compiler_name.clone(),
+            compiler: format_path(&cache.compiler),
+        }
+    }
+
+    fn into_cache(self, path: &str) -> Result<CompilerCache> {
+        let kind = match self.tool.as_str() {
+            "ccache" => CompilerCacheKind::Ccache,
+            "sccache" => CompilerCacheKind::Sccache,
+            "distcc" => CompilerCacheKind::Distcc,
+            "icecc" => CompilerCacheKind::Icecc,
+            other => return Err(invalid_field(path, "compiler_cache.tool", Some(other))),
+        };
+        Ok(CompilerCache {
+            kind,
+            compiler_name: self.compiler_name,
+            compiler: PathBuf::from(self.compiler),
+        })
+    }
+}
+
+/// JSON representation of a Flatpak or Snap launcher
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonAppLauncher {
+    /// Sandboxing system launching the application
+    #[schemars(extend("enum" = ["flatpak", "snap"]))]
+    pub kind: String,
+    /// Flatpak application id, or `snap.app` for Snap
+    pub app_id: String,
+    /// Installed deployment directory of the application
+    pub deployment: String,
+    /// Command declared in the application's metadata
+    pub command: String,
+}
+
+impl JsonAppLauncher {
+    fn from_launcher(app: &AppLauncher) -> Self {
+        let kind = match app.kind {
+            LauncherKind::Flatpak => "flatpak",
+            LauncherKind::Snap => "snap",
+        };
+        Self {
+            kind: kind.to_string(),
+            app_id: app.app_id.clone(),
+            deployment: format_path(&app.deployment),
+            command: app.command.clone(),
+        }
+    }
+
+    fn into_launcher(self, path: &str) -> Result<AppLauncher> {
+        let kind = match self.kind.as_str() {
+            "flatpak" => LauncherKind::Flatpak,
+            "snap" => LauncherKind::Snap,
+            other => return Err(invalid_field(path, "launcher.kind", Some(other))),
+        };
+        Ok(AppLauncher {
+            kind,
+            app_id: self.app_id,
+            deployment: PathBuf::from(self.deployment),
+            command: self.command,
+        })
+    }
+}
+
+/// JSON representation of a Homebrew keg
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonHomebrewKeg {
+    /// Homebrew prefix containing the Cellar
+    pub prefix: String,
+    /// Formula name
+    pub formula: String,
+    /// Installed version of the formula
+    pub version: String,
+    /// Path of the hop below the keg
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub relative: String,
+    /// Whether the keg is linked into the prefix
+    #[serde(default)]
+    pub linked: bool,
+    /// Whether the formula is keg-only
+    #[serde(default)]
+    pub keg_only: bool,
+    /// Details from the keg's `INSTALL_RECEIPT.json`, if readable
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub receipt: Option<JsonInstallReceipt>,
+}
+
+/// JSON representation of a Homebrew install receipt
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonInstallReceipt {
+    /// Whether the formula was installed explicitly rather than as a
+    /// dependency
+    pub installed_on_request: bool,
+    /// Whether the keg was installed from a prebuilt bottle
+    pub poured_from_bottle: bool,
+    /// Install time in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub time: Option<i64>,
+}
+
+impl JsonHomebrewKeg {
+    fn from_keg(keg: &HomebrewKeg) -> Self {
+        Self {
+            prefix: format_path(&keg.prefix),
+            formula: keg.formula.clone(),
+            version: keg.version.clone(),
+            relative: keg.relative.to_string_lossy().into_owned(),
+            linked: keg.linked,
+            keg_only: keg.keg_only,
+            receipt: keg.receipt.as_ref().map(|receipt| JsonInstallReceipt {
+                installed_on_request: receipt.installed_on_request,
+                poured_from_bottle: receipt.poured_from_bottle,
+                time: receipt.time,
+            }),
+        }
+    }
+
+    fn into_keg(self) -> HomebrewKeg {
+        HomebrewKeg {
+            prefix: PathBuf::from(self.prefix),
+            formula: self.formula,
+            version: self.version,
+            relative: PathBuf::from(self.relative),
+            linked: self.linked,
+            keg_only: self.keg_only,
+            receipt: self.receipt.map(|receipt| InstallReceipt {
+                installed_on_request: receipt.installed_on_request,
+                poured_from_bottle: receipt.poured_from_bottle,
+                time: receipt.time,
+            }),
+        }
+    }
+}
+
+/// JSON representation of a Nix profile traversed along the chain
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonProfileLayer {
+    /// Profile name, e.g. `system`, `profile` or `home-manager`
+    pub name: String,
+    /// Generation number of the profile
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub generation: Option<u32>,
+    /// Generation link if known, otherwise the profile link itself
+    pub link: String,
+    /// Remainder of the path below the profile
+    pub relative: String,
+    /// Other generations in which the same path resolves differently,
+    /// present only when requested with `--generations`
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub siblings: Vec<JsonSiblingGeneration>,
+}
+
+/// JSON representation of how a path resolves in another generation
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonSiblingGeneration {
+    pub generation: u32,
+    /// Final target in that generation, absent if the path does not exist
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub target: Option<String>,
+}
+
+impl JsonProfileLayer {
+    fn from_layer(layer: &ProfileLayer) -> Self {
+        Self {
+            name: layer.name.clone(),
+            generation: layer.generation,
+            link: format_path(&layer.link),
+            relative: layer.relative.to_string_lossy().into_owned(),
+            siblings: layer
+                .siblings
+                .iter()
+                .map(|sibling| JsonSiblingGeneration {
+                    generation: sibling.generation,
+                    target: sibling.target.as_deref().map(format_path),
+                })
+                .collect(),
+        }
+    }
+
+    fn into_layer(self) -> ProfileLayer {
+        ProfileLayer {
+            name: self.name,
+            generation: self.generation,
+            link: PathBuf::from(self.link),
+            relative: PathBuf::from(self.relative),
+            siblings: self
+                .siblings
+                .into_iter()
+                .map(|sibling| SiblingGeneration {
+                    generation: sibling.generation,
+                    target: sibling.target.map(PathBuf::from),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// JSON representation of a path inside a Nix or Guix store
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonStorePath {
+    /// Package manager owning the store
+    #[serde(default = "default_store")]
+    #[schemars(extend("enum" = ["nix", "guix"]))]
+    pub store: String,
+    /// Top-level store entry containing the hop
+    pub entry: String,
+    /// Store hash of the entry
+    pub hash: String,
+    /// Package name
+    pub name: String,
+    /// Package version, if the name carries one
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<String>,
+    /// Path of the hop below the store entry
+    #[serde(skip_serializing_if = "String::is_empty", default)]
+    pub relative: String,
+}
+
+/// JSON representation of Nix database information about a store entry
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JsonStoreInfo {
+    /// Derivation that produced the store entry
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub deriver: Option<String>,
+    /// Size of the serialized store entry in bytes
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub nar_size: Option<u64>,
+    /// Registration time in seconds since the Unix epoch
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub registration_time: Option<i64>,
+    /// Store entries referenced by this one
+    #[serde(default)]
+    pub references: Vec<String>,
+    /// Whether a GC root keeps the store entry alive
+    #[serde(default)]
+    pub is_gc_root: bool,
+}
+
+impl JsonStoreInfo {
+    fn from_info(info: &StorePathInfo) -> Self {
+        Self {
+            deriver: info.deriver.as_deref().map(format_path),
+            nar_size: info.nar_size,
+            registration_time: info.registration_time,
+            references: info.references.iter().map(|p| format_path(p)).collect(),
+            is_gc_root: info.is_gc_root,
+        }
+    }
+
+    fn into_info(self) -> StorePathInfo {
+        StorePathInfo {
+            deriver: self.deriver.map(PathBuf::from),
+            nar_size: self.nar_size,
+            registration_time: self.registration_time,
+            references: self.references.into_iter().map(PathBuf::from).collect(),
+            is_gc_root: self.is_gc_root,
+        }
+    }
+}
+
+impl JsonStorePath {
+    fn from_store_path(store_path: &StorePath) -> Self {
+        Self {
+            store: store_path.flavor.as_str().to_string(),
+            entry: format_path(&store_path.entry()),
+            hash: store_path.hash.clone(),
+            name: store_path.name.clone(),
+            version: store_path.version.clone(),
+            relative: store_path.relative.to_string_lossy().into_owned(),
+        }
+    }
+
+    fn into_store_path(self) -> StorePath {
+        let entry = PathBuf::from(self.entry);
+        StorePath {
+            store_dir: entry.parent().map(Path::to_path_buf).unwrap_or_default(),
+            flavor: if self.store == StoreFlavor::Guix.as_str() {
+                StoreFlavor::Guix
+            } else {
+                StoreFlavor::Nix
+            },
+            hash: self.hash,
+            name: self.name,
+            version: self.version,
+            relative: PathBuf::from(self.relative),
+        }
+    }
+}
+
+fn default_store() -> String {
+    StoreFlavor::Nix.as_str().to_string()
+}
+
+const fn default_schema_version() -> u32 {
+    1
 }
 
 impl JsonChain {
     /// Convert a `SymlinkChain` to JSON-serializable format
     pub fn from_chain(chain: &SymlinkChain) -> Self {
         Self {
+            schema_version: SCHEMA_VERSION,
             origin: format_path(&chain.origin),
-            links: chain.links.iter().map(JsonLink::from_node).collect(),
+            origin_profiles: chain
+                .origin_profiles
+                .iter()
+                .map(JsonProfileLayer::from_layer)
+                .collect(),
+            origin_mount: chain.origin_mount.as_ref().map(JsonMount::from_mount),
+            links: chain
+                .links
+                .iter()
+                .zip(chain.same_file_hops())
+                .zip(mount::crossings(chain))
+                .map(|((node, same_file_as), crosses_mount)| JsonLink {
+                    same_file_as,
+                    crosses_mount,
+                    ..JsonLink::from_node(node)
+                })
+                .collect(),
+            truncated: chain.truncated,
+            path_status: None,
+            same_file_as: None,
+        }
+    }
+
+    /// Convert a PATH match to JSON-serializable format
+    pub fn from_match(
+        chain: &SymlinkChain,
+        status: MatchStatus,
+        same_file_as: Option<usize>,
+    ) -> Self {
+        let status_str = match status {
+            MatchStatus::Active => "active",
+            MatchStatus::Duplicate => "duplicate",
+            MatchStatus::Shadowed => "shadowed",
+        };
+        Self {
+            path_status: Some(status_str.to_string()),
+            same_file_as,
+            ..Self::from_chain(chain)
         }
     }
 }
 
 impl JsonLink {
     /// Convert a `SymlinkNode` to JSON-serializable format
-    fn from_node(node: &crate::core::types::SymlinkNode) -> Self {
+    fn from_node(node: &SymlinkNode) -> Self {
         let (link_type, wrapper_kind, file_kind) = match &node.link_type {
             LinkType::Symlink => ("symlink".to_string(), None, None),
-            LinkType::Wrapper(kind) => {
-                let wrapper_str = match kind {
-                    WrapperKind::Binary => "binary",
-                    WrapperKind::Text(ScriptType::Shell) => "shell_script",
-                    WrapperKind::Text(ScriptType::Python) => "python_script",
-                    WrapperKind::Text(ScriptType::Perl) => "perl_script",
-                    WrapperKind::Text(ScriptType::Unknown) => "unknown_script",
-                };
-                ("wrapper".to_string(), Some(wrapper_str.to_string()), None)
-            }
-            LinkType::Terminal(kind) => {
-                let file_str = match kind {
-                    FileKind::Binary => "binary",
-                    FileKind::Text => "text",
-                };
-                ("terminal".to_string(), None, Some(file_str.to_string()))
-            }
+            LinkType::Wrapper(kind) => (
+                "wrapper".to_string(),
+                Some(wrapper_kind_str(kind).to_string()),
+                None,
+            ),
+            LinkType::Terminal(kind) => (
+                "terminal".to_string(),
+                None,
+                Some(file_kind_str(kind).to_string()),
+            ),
         };
 
         Self {
@@ -66,10 +694,496 @@ impl JsonLink {
             wrapper_kind,
             file_kind,
             is_final: node.is_final,
+            store_path: node.store_path.as_ref().map(JsonStorePath::from_store_path),
+            store_info: node.store_info.as_ref().map(JsonStoreInfo::from_info),
+            profiles: node
+                .profiles
+                .iter()
+                .map(JsonProfileLayer::from_layer)
+                .collect(),
+            file_id: node.file_id.as_ref().map(JsonFileId::from_file_id),
+            same_file_as: None,
+            mount: node.mount.as_ref().map(JsonMount::from_mount),
+            crosses_mount: false,
+            keg: node.keg.as_ref().map(JsonHomebrewKeg::from_keg),
+            launcher: node.launcher.as_ref().map(JsonAppLauncher::from_launcher),
+            compiler_cache: node
+                .compiler_cache
+                .as_ref()
+                .map(JsonCompilerCache::from_cache),
+            bundle: node.bundle.as_ref().map(JsonBundle::from_bundle),
+            applet: node.applet.as_ref().map(JsonApplet::from_applet),
+        }
+    }
+
+    /// Parse the stringly-typed kind fields back into a `LinkType`
+    fn parse_link_type(&self) -> Result<LinkType> {
+        match self.link_type.as_str() {
+            "symlink" => Ok(LinkType::Symlink),
+            "wrapper" => {
+                let kind = match self.wrapper_kind.as_deref() {
+                    Some("binary") => WrapperKind::Binary,
+                    Some("shell_script") => WrapperKind::Text(ScriptType::Shell),
+                    Some("python_script") => WrapperKind::Text(ScriptType::Python),
+                    Some("perl_script") => WrapperKind::Text(ScriptType::Perl),
+                    Some("unknown_script") => WrapperKind::Text(ScriptType::Unknown),
+                    other => return Err(invalid_field(&self.path, "wrapper_kind", other)),
+                };
+                Ok(LinkType::Wrapper(kind))
+            }
+            "terminal" => {
+                let kind = match self.file_kind.as_deref() {
+                    Some("binary") => FileKind::Binary,
+                    Some("text") => FileKind::Text,
+                    other => return Err(invalid_field(&self.path, "file_kind", other)),
+                };
+                Ok(LinkType::Terminal(kind))
+            }
+            other => Err(invalid_field(&self.path, "type", Some(other))),
         }
     }
 }
 
+impl TryFrom<JsonChain> for SymlinkChain {
+    type Error = SymseekError;
+
+    fn try_from(json: JsonChain) -> Result<Self> {
+        if json.schema_version > SCHEMA_VERSION {
+            return Err(SymseekError::InvalidJson {
+                reason: format!(
+                    "unsupported schema version {} (newest supported is {SCHEMA_VERSION})",
+                    json.schema_version
+                ),
+            });
+        }
+
+        let mut chain = Self::new(PathBuf::from(json.origin));
+        chain.origin_profiles = json
+            .origin_profiles
+            .into_iter()
+            .map(JsonProfileLayer::into_layer)
+            .collect();
+        chain.origin_mount = json.origin_mount.map(JsonMount::into_mount);
+        chain.truncated = json.truncated;
+        for link in json.links {
+            let link_type = link.parse_link_type()?;
+            let launcher = link
+                .launcher
+                .map(|launcher| launcher.into_launcher(&link.path))
+                .transpose()?;
+            let compiler_cache = link
+                .compiler_cache
+                .map(|cache| cache.into_cache(&link.path))
+                .transpose()?;
+            let bundle = link
+                .bundle
+                .map(|bundle| bundle.into_bundle(&link.path))
+                .transpose()?;
+            let applet = link
+                .applet
+                .map(|applet| applet.into_applet(&link.path))
+                .transpose()?;
+            chain.add_link(PathBuf::from(link.path), link.is_final, link_type);
+            if let Some(node) = chain.links.last_mut() {
+                node.store_path = link.store_path.map(JsonStorePath::into_store_path);
+                node.store_info = link.store_info.map(JsonStoreInfo::into_info);
+                node.profiles = link
+                    .profiles
+                    .into_iter()
+                    .map(JsonProfileLayer::into_layer)
+                    .collect();
+                node.file_id = link.file_id.map(JsonFileId::into_file_id);
+                node.mount = link.mount.map(JsonMount::into_mount);
+                node.keg = link.keg.map(JsonHomebrewKeg::into_keg);
+                node.launcher = launcher;
+                node.compiler_cache = compiler_cache;
+                node.bundle = bundle;
+                node.applet = applet;
+            }
+        }
+        Ok(chain)
+    }
+}
+
+const fn wrapper_kind_str(kind: &WrapperKind) -> &'static str {
+    match kind {
+        WrapperKind::Binary => "binary",
+        WrapperKind::Text(ScriptType::Shell) => "shell_script",
+        WrapperKind::Text(ScriptType::Python) => "python_script",
+        WrapperKind::Text(ScriptType::Perl) => "perl_script",
+        WrapperKind::Text(ScriptType::Unknown) => "unknown_script",
+    }
+}
+
+const fn file_kind_str(kind: &FileKind) -> &'static str {
+    match kind {
+        FileKind::Binary => "binary",
+        FileKind::Text => "text",
+    }
+}
+
+fn invalid_field(path: &str, field: &str, value: Option<&str>) -> SymseekError {
+    SymseekError::InvalidJson {
+        reason: format!("link {path:?} has invalid {field} {value:?}"),
+    }
+}
+
+/// Saved `--json` output, which is a single chain or an array of chains
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonDocument {
+    Single(JsonChain),
+    Multiple(Vec<JsonChain>),
+}
+
+/// Load chains previously saved with `--json`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is not valid JSON, or
+/// contains a chain that cannot be converted back.
+pub fn load_chains(path: &Path) -> Result<Vec<SymlinkChain>> {
+    let content = std::fs::read_to_string(path).map_err(|e| SymseekError::Io {
+        context: format!("Failed to read {}", path.display()),
+        source: e,
+    })?;
+
+    let chains = match serde_json::from_str(&content)? {
+        JsonDocument::Single(chain) => vec![chain],
+        JsonDocument::Multiple(chains) => chains,
+    };
+
+    chains.into_iter().map(SymlinkChain::try_from).collect()
+}
+
+/// JSON representation of a diff between two sets of resolutions
+#[derive(Debug, Serialize)]
+pub struct JsonDiff {
+    pub schema_version: u32,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<JsonChainDiff>,
+}
+
+/// JSON representation of the changes to a single target
+#[derive(Debug, Serialize)]
+pub struct JsonChainDiff {
+    pub origin: String,
+    pub changes: Vec<JsonChange>,
+}
+
+/// JSON representation of a single change
+#[derive(Debug, Serialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum JsonChange {
+    HopAdded {
+        path: String,
+    },
+    HopRemoved {
+        path: String,
+    },
+    StorePathChanged {
+        before: String,
+        after: String,
+    },
+    WrapperKindChanged {
+        path: String,
+        before: &'static str,
+        after: &'static str,
+    },
+    TerminalKindChanged {
+        before: &'static str,
+        after: &'static str,
+    },
+}
+
+impl JsonDiff {
+    /// Convert a `DiffReport` to JSON-serializable format
+    pub fn from_report(report: &DiffReport) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            added: report.added.iter().map(|p| format_path(p)).collect(),
+            removed: report.removed.iter().map(|p| format_path(p)).collect(),
+            changed: report
+                .changed
+                .iter()
+                .map(|chain_diff| JsonChainDiff {
+                    origin: format_path(&chain_diff.origin),
+                    changes: chain_diff
+                        .changes
+                        .iter()
+                        .map(JsonChange::from_change)
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl JsonChange {
+    fn from_change(change: &Change) -> Self {
+        match change {
+            Change::HopAdded(path) => Self::HopAdded {
+                path: format_path(path),
+            },
+            Change::HopRemoved(path) => Self::HopRemoved {
+                path: format_path(path),
+            },
+            Change::StorePathChanged { before, after } => Self::StorePathChanged {
+                before: format_path(before),
+                after: format_path(after),
+            },
+            Change::WrapperKindChanged {
+                path,
+                before,
+                after,
+            } => Self::WrapperKindChanged {
+                path: format_path(path),
+                before: wrapper_kind_str(before),
+                after: wrapper_kind_str(after),
+            },
+            Change::TerminalKindChanged { before, after } => Self::TerminalKindChanged {
+                before: file_kind_str(before),
+                after: file_kind_str(after),
+            },
+        }
+    }
+}
+
+/// JSON representation of a resolved running process
+#[derive(Debug, Serialize)]
+pub struct JsonProcess {
+    pub schema_version: u32,
+    pub pid: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub argv0: Option<String>,
+    /// Executable the kernel loaded
+    pub exe: String,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub deleted: bool,
+    /// Whether `chain` ends at the loaded executable
+    pub final_target_loaded: bool,
+    /// Chain from the path the process was invoked through
+    pub chain: JsonChain,
+}
+
+impl JsonProcess {
+    /// Convert a process and its chain to JSON-serializable format
+    pub fn from_process(process: &ProcessInfo, chain: &SymlinkChain, loads: bool) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            pid: process.pid,
+            argv0: process.argv0.clone(),
+            exe: format_path(&process.exe),
+            deleted: process.deleted,
+            final_target_loaded: loads,
+            chain: JsonChain::from_chain(chain),
+        }
+    }
+}
+
+/// JSON representation of a desktop entry and the command it launches
+#[derive(Debug, Serialize)]
+pub struct JsonDesktop {
+    pub schema_version: u32,
+    pub id: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// `Exec=` value as written
+    pub exec: String,
+    /// Command line with quoting removed and field codes dropped
+    pub argv: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub try_exec: Option<String>,
+    /// Where `try_exec` was found; absent if it was not, which hides the entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub try_exec_path: Option<String>,
+    /// Chains of every match of the executable, in lookup order
+    pub matches: Vec<JsonChain>,
+}
+
+impl JsonDesktop {
+    /// Convert a desktop entry and the chains of its executable to
+    /// JSON-serializable format
+    pub fn from_entry(
+        entry: &DesktopEntry,
+        chains: &[SymlinkChain],
+        statuses: &[MatchStatus],
+        same_files: &[Option<usize>],
+    ) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            id: entry.id.clone(),
+            path: format_path(&entry.path),
+            name: entry.name.clone(),
+            exec: entry.exec.clone(),
+            argv: entry.argv.clone(),
+            try_exec: entry.try_exec.clone(),
+            try_exec_path: entry.try_exec_path.as_deref().map(format_path),
+            matches: chains
+                .iter()
+                .zip(statuses)
+                .zip(same_files)
+                .map(|((chain, status), same_file_as)| {
+                    JsonChain::from_match(chain, *status, *same_file_as)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// JSON representation of the commands of a systemd unit
+#[derive(Debug, Serialize)]
+pub struct JsonUnit {
+    pub schema_version: u32,
+    pub unit: String,
+    /// Unit file read, a template for instantiated units
+    pub path: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub drop_ins: Vec<String>,
+    /// `PATH` from the unit's environment, if set
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_var: Option<String>,
+    pub commands: Vec<JsonUnitCommand>,
+}
+
+/// JSON representation of one `Exec*=` command of a unit
+#[derive(Debug, Serialize)]
+pub struct JsonUnitCommand {
+    pub directive: String,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub prefixes: String,
+    pub executable: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub argv0: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<JsonChain>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JsonUnit {
+    /// Convert a unit and the resolution of each of its commands to
+    /// JSON-serializable format
+    pub fn from_unit(unit: &UnitFile, resolutions: &[Resolution]) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            unit: unit.name.clone(),
+            path: format_path(&unit.path),
+            drop_ins: unit.drop_ins.iter().map(|p| format_path(p)).collect(),
+            path_var: unit.path_var.clone(),
+            commands: unit
+                .commands
+                .iter()
+                .zip(resolutions)
+                .map(|(command, resolution)| {
+                    let (chain, error) = match resolution {
+                        Resolution::Resolved(chain) => (Some(JsonChain::from_chain(chain)), None),
+                        Resolution::Broken(reason) => (None, Some(reason.clone())),
+                    };
+                    JsonUnitCommand {
+                        directive: command.directive.clone(),
+                        prefixes: command.prefixes.clone(),
+                        executable: command.executable.clone(),
+                        argv0: command.argv0.clone(),
+                        chain,
+                        error,
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// JSON representation of a PATH inventory
+#[derive(Debug, Serialize)]
+pub struct JsonInventory {
+    pub schema_version: u32,
+    pub summary: JsonInventorySummary,
+    pub commands: Vec<JsonInventoryEntry>,
+}
+
+/// JSON representation of inventory totals
+#[derive(Debug, Serialize)]
+pub struct JsonInventorySummary {
+    pub commands: usize,
+    pub shadowed: usize,
+    pub conflicts: usize,
+    pub wrappers: usize,
+    pub broken: usize,
+}
+
+/// JSON representation of one command in the inventory
+#[derive(Debug, Serialize)]
+pub struct JsonInventoryEntry {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub shadowed: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub conflict: bool,
+    pub matches: Vec<JsonInventoryMatch>,
+}
+
+/// JSON representation of one PATH match in the inventory
+#[derive(Debug, Serialize)]
+pub struct JsonInventoryMatch {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chain: Option<JsonChain>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl JsonInventory {
+    /// Convert an `Inventory` to JSON-serializable format
+    pub fn from_inventory(inventory: &Inventory) -> Self {
+        let summary = inventory.summary();
+        Self {
+            schema_version: SCHEMA_VERSION,
+            summary: JsonInventorySummary {
+                commands: summary.commands,
+                shadowed: summary.shadowed,
+                conflicts: summary.conflicts,
+                wrappers: summary.wrappers,
+                broken: summary.broken,
+            },
+            commands: inventory
+                .entries
+                .iter()
+                .map(|entry| JsonInventoryEntry {
+                    name: entry.name.clone(),
+                    source: entry.winner().source().map(|p| format_path(&p)),
+                    shadowed: entry.is_shadowed(),
+                    conflict: entry.has_conflict(),
+                    matches: entry
+                        .matches
+                        .iter()
+                        .map(|m| JsonInventoryMatch {
+                            path: format_path(&m.path),
+                            chain: m.chain().map(JsonChain::from_chain),
+                            error: match &m.resolution {
+                                Resolution::Resolved(_) => None,
+                                Resolution::Broken(reason) => Some(reason.clone()),
+                            },
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Generate the JSON Schema describing a single serialized chain.
+///
+/// Multi-match output is an array of documents matching this schema.
+#[must_use]
+pub fn json_schema() -> serde_json::Value {
+    schemars::schema_for!(JsonChain).to_value()
+}
+
 /// Format a path consistently with the tree formatter
 fn format_path(path: &Path) -> String {
     path_clean::clean(path).to_str().map_or_else(
@@ -78,6 +1192,17 @@ fn format_path(path: &Path) -> String {
     )
 }
 
+/// Print the JSON Schema of the output format
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails.
+pub fn print_json_schema() -> Result<()> {
+    let json = serde_json::to_string_pretty(&json_schema())?;
+    println!("{json}");
+    Ok(())
+}
+
 /// Print a single chain as JSON
 ///
 /// # Errors
@@ -90,13 +1215,84 @@ pub fn print_json_single(chain: &SymlinkChain) -> Result<()> {
     Ok(())
 }
 
+/// Print a diff report as JSON
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails.
+pub fn print_json_diff(report: &DiffReport) -> Result<()> {
+    let json = serde_json::to_string_pretty(&JsonDiff::from_report(report))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Print a PATH inventory as JSON
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails.
+pub fn print_json_inventory(inventory: &Inventory) -> Result<()> {
+    let json = serde_json::to_string_pretty(&JsonInventory::from_inventory(inventory))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Print a desktop entry and the chains of its executable as JSON
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails.
+pub fn print_json_desktop(
+    entry: &DesktopEntry,
+    chains: &[SymlinkChain],
+    statuses: &[MatchStatus],
+    same_files: &[Option<usize>],
+) -> Result<()> {
+    let json = serde_json::to_string_pretty(&JsonDesktop::from_entry(
+        entry, chains, statuses, same_files,
+    ))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Print the commands of a unit as JSON
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails.
+pub fn print_json_unit(unit: &UnitFile, resolutions: &[Resolution]) -> Result<()> {
+    let json = serde_json::to_string_pretty(&JsonUnit::from_unit(unit, resolutions))?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Print a resolved process as JSON
+///
+/// # Errors
+///
+/// Returns an error if JSON serialization fails.
+pub fn print_json_process(process: &ProcessInfo, chain: &SymlinkChain, loads: bool) -> Result<()> {
+    let json = serde_json::to_string_pretty(&JsonProcess::from_process(process, chain, loads))?;
+    println!("{json}");
+    Ok(())
+}
+
 /// Print multiple chains as a JSON array
 ///
 /// # Errors
 ///
 /// Returns an error if JSON serialization fails.
-pub fn print_json_multiple(chains: &[SymlinkChain]) -> Result<()> {
-    let json_chains: Vec<JsonChain> = chains.iter().map(JsonChain::from_chain).collect();
+pub fn print_json_multiple(
+    chains: &[SymlinkChain],
+    statuses: &[MatchStatus],
+    same_files: &[Option<usize>],
+) -> Result<()> {
+    let json_chains: Vec<JsonChain> = chains
+        .iter()
+        .zip(statuses)
+        .zip(same_files)
+        .map(|((chain, status), same_file_as)| JsonChain::from_match(chain, *status, *same_file_as))
+        .collect();
     let json = serde_json::to_string_pretty(&json_chains)?;
     println!("{json}");
     Ok(())
@@ -105,6 +1301,7 @@ pub fn print_json_multiple(chains: &[SymlinkChain]) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::store::Stores;
 
     #[test]
     fn test_json_chain_from_simple_symlink() {
@@ -220,4 +1417,367 @@ mod tests {
             assert!(json_chain.links[0].is_final);
         }
     }
+
+    #[test]
+    fn test_json_chain_into_symlink_chain() {
+        let mut chain = SymlinkChain::new(std::path::PathBuf::from("/usr/bin/nvim"));
+        chain.add_link(
+            std::path::PathBuf::from("/nix/store/xxx-nvim-wrapper/bin/nvim"),
+            false,
+            LinkType::Wrapper(WrapperKind::Text(ScriptType::Python)),
+        );
+        chain.add_link(
+            std::path::PathBuf::from("/nix/store/yyy-nvim/bin/nvim"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+
+        let json_str = serde_json::to_string(&JsonChain::from_chain(&chain)).unwrap();
+        let json_chain: JsonChain = serde_json::from_str(&json_str).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+
+        assert_eq!(restored.origin, chain.origin);
+        assert_eq!(restored.links.len(), 2);
+        assert!(matches!(
+            restored.links[0].link_type,
+            LinkType::Wrapper(WrapperKind::Text(ScriptType::Python))
+        ));
+        assert!(matches!(
+            restored.links[1].link_type,
+            LinkType::Terminal(FileKind::Binary)
+        ));
+        assert!(restored.links[1].is_final);
+    }
+
+    #[test]
+    fn test_json_chain_missing_schema_version() {
+        let json_str = r#"{"origin": "/a", "links": [{"path": "/b", "type": "symlink"}]}"#;
+        let json_chain: JsonChain = serde_json::from_str(json_str).unwrap();
+
+        assert_eq!(json_chain.schema_version, 1);
+        assert!(SymlinkChain::try_from(json_chain).is_ok());
+    }
+
+    #[test]
+    fn test_json_chain_rejects_invalid_input() {
+        let invalid = [
+            r#"{"schema_version": 999, "origin": "/a", "links": []}"#,
+            r#"{"origin": "/a", "links": [{"path": "/b", "type": "hardlink"}]}"#,
+            r#"{"origin": "/a", "links": [{"path": "/b", "type": "wrapper"}]}"#,
+            r#"{"origin": "/a", "links": [{"path": "/b", "type": "terminal", "file_kind": "x"}]}"#,
+        ];
+
+        for json_str in invalid {
+            let json_chain: JsonChain = serde_json::from_str(json_str).unwrap();
+            assert!(matches!(
+                SymlinkChain::try_from(json_chain),
+                Err(SymseekError::InvalidJson { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_json_truncated_roundtrip() {
+        let mut chain = SymlinkChain::new(std::path::PathBuf::from("/bin/a"));
+        chain.add_link(std::path::PathBuf::from("/bin/b"), false, LinkType::Symlink);
+
+        let json = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert!(json.get("truncated").is_none());
+
+        chain.truncated = true;
+        let json = serde_json::to_string(&JsonChain::from_chain(&chain)).unwrap();
+        assert!(json.contains(r#""truncated":true"#));
+        let restored =
+            SymlinkChain::try_from(serde_json::from_str::<JsonChain>(&json).unwrap()).unwrap();
+        assert!(restored.truncated);
+    }
+
+    #[test]
+    fn test_json_schema_documents_fields() {
+        let schema = json_schema();
+        let properties = &schema["properties"];
+
+        assert!(properties.get("schema_version").is_some());
+        assert!(properties.get("origin").is_some());
+        assert!(properties.get("links").is_some());
+    }
+
+    #[test]
+    fn test_load_chains_single_and_multiple() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let mut chain = SymlinkChain::new(std::path::PathBuf::from("/bin/a"));
+        chain.add_link(
+            std::path::PathBuf::from("/bin/b"),
+            true,
+            LinkType::Terminal(FileKind::Text),
+        );
+
+        let single = temp.path().join("single.json");
+        std::fs::write(
+            &single,
+            serde_json::to_string(&JsonChain::from_chain(&chain)).unwrap(),
+        )
+        .unwrap();
+        let multiple = temp.path().join("multiple.json");
+        std::fs::write(
+            &multiple,
+            serde_json::to_string(&vec![
+                JsonChain::from_chain(&chain),
+                JsonChain::from_chain(&chain),
+            ])
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(load_chains(&single).unwrap().len(), 1);
+        assert_eq!(load_chains(&multiple).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_json_diff_change_tags() {
+        let report = DiffReport {
+            added: vec![],
+            removed: vec![],
+            changed: vec![crate::core::diff::ChainDiff {
+                origin: std::path::PathBuf::from("/bin/a"),
+                changes: vec![Change::WrapperKindChanged {
+                    path: std::path::PathBuf::from("/bin/b"),
+                    before: WrapperKind::Binary,
+                    after: WrapperKind::Text(ScriptType::Shell),
+                }],
+            }],
+        };
+
+        let value = serde_json::to_value(JsonDiff::from_report(&report)).unwrap();
+        let change = &value["changed"][0]["changes"][0];
+        assert_eq!(change["change"], "wrapper_kind_changed");
+        assert_eq!(change["before"], "binary");
+        assert_eq!(change["after"], "shell_script");
+    }
+
+    #[test]
+    fn test_json_link_store_path() {
+        let mut chain = SymlinkChain::new(std::path::PathBuf::from("/usr/bin/qs"));
+        chain.add_link(
+            std::path::PathBuf::from("/nix/store/abc123-quickshell-0.2.1/bin/qs"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        chain.links[0].store_path = Stores::default().parse(&chain.links[0].target);
+
+        let json_chain = JsonChain::from_chain(&chain);
+        let store_path = json_chain.links[0].store_path.as_ref().unwrap();
+
+        assert_eq!(
+            json_chain.links[0].path,
+            "/nix/store/abc123-quickshell-0.2.1/bin/qs"
+        );
+        assert_eq!(store_path.store, "nix");
+        assert_eq!(store_path.entry, "/nix/store/abc123-quickshell-0.2.1");
+        assert_eq!(store_path.hash, "abc123");
+        assert_eq!(store_path.name, "quickshell");
+        assert_eq!(store_path.version.as_deref(), Some("0.2.1"));
+        assert_eq!(store_path.relative, "bin/qs");
+
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[0].store_path, chain.links[0].store_path);
+    }
+
+    #[test]
+    fn test_json_keg_roundtrip() {
+        let mut chain = SymlinkChain::new(std::path::PathBuf::from(
+            "/home/linuxbrew/.linuxbrew/bin/hello",
+        ));
+        chain.add_link(
+            std::path::PathBuf::from("/home/linuxbrew/.linuxbrew/Cellar/hello/2.12/bin/hello"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        let keg = HomebrewKeg {
+            prefix: std::path::PathBuf::from("/home/linuxbrew/.linuxbrew"),
+            formula: "hello".to_string(),
+            version: "2.12".to_string(),
+            relative: std::path::PathBuf::from("bin/hello"),
+            linked: true,
+            keg_only: false,
+            receipt: Some(InstallReceipt {
+                installed_on_request: true,
+                poured_from_bottle: true,
+                time: Some(1_700_000_000),
+            }),
+        };
+        chain.links[0].keg = Some(keg.clone());
+
+        let value = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert_eq!(value["links"][0]["keg"]["formula"], "hello");
+        assert_eq!(value["links"][0]["keg"]["linked"], true);
+
+        let json_chain: JsonChain = serde_json::from_value(value).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[0].keg, Some(keg));
+    }
+
+    #[test]
+    fn test_json_launcher_rejects_unknown_kind() {
+        let document = r#"{
+            "origin": "/snap/bin/hello",
+            "links": [{
+                "path": "/usr/bin/snap",
+                "type": "wrapper",
+                "wrapper_kind": "binary",
+                "launcher": {
+                    "kind": "appimage",
+                    "app_id": "hello.hello",
+                    "deployment": "/snap/hello/42",
+                    "command": "bin/hello"
+                }
+            }]
+        }"#;
+        let json_chain: JsonChain = serde_json::from_str(document).unwrap();
+
+        assert!(matches!(
+            SymlinkChain::try_from(json_chain),
+            Err(SymseekError::InvalidJson { .. })
+        ));
+    }
+
+    #[test]
+    fn test_json_bundle_roundtrip() {
+        let bundle = BundleInfo {
+            kind: SelfExtractingKind::AppImage(AppImageType::Type2),
+            payload_offset: Some(193_728),
+            entry_point: Some("usr/bin/hello".to_string()),
+            desktop_file: Some("hello.desktop".to_string()),
+        };
+        let mut chain = SymlinkChain::new(PathBuf::from("/usr/local/bin/hello"));
+        chain.add_link(
+            PathBuf::from("/opt/Hello.AppImage"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        chain.links[0].bundle = Some(bundle.clone());
+
+        let value = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert_eq!(value["links"][0]["bundle"]["kind"], "appimage");
+        assert_eq!(value["links"][0]["bundle"]["appimage_type"], 2);
+
+        let json_chain: JsonChain = serde_json::from_value(value).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[0].bundle, Some(bundle));
+    }
+
+    #[test]
+    fn test_json_applet_roundtrip() {
+        let applet = Applet {
+            kind: MultiCallKind::Busybox,
+            name: "ls".to_string(),
+            compiled_in: false,
+        };
+        let mut chain = SymlinkChain::new(PathBuf::from("/bin/ls"));
+        chain.add_link(
+            PathBuf::from("/bin/busybox"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        chain.links[0].applet = Some(applet.clone());
+
+        let value = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert_eq!(value["links"][0]["applet"]["multi_call"], "busybox");
+        assert_eq!(value["links"][0]["applet"]["compiled_in"], false);
+
+        let json_chain: JsonChain = serde_json::from_value(value).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[0].applet, Some(applet));
+    }
+
+    #[test]
+    fn test_json_compiler_cache_roundtrip() {
+        let cache = CompilerCache {
+            kind: CompilerCacheKind::Distcc,
+            compiler_name: "cc".to_string(),
+            compiler: PathBuf::from("/usr/bin/cc"),
+        };
+        let mut chain = SymlinkChain::new(PathBuf::from("/usr/lib/distcc/cc"));
+        chain.add_link(
+            PathBuf::from("/usr/bin/distcc"),
+            false,
+            LinkType::Wrapper(WrapperKind::Binary),
+        );
+        chain.links[0].compiler_cache = Some(cache.clone());
+
+        let value = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert_eq!(value["links"][0]["compiler_cache"]["tool"], "distcc");
+
+        let json_chain: JsonChain = serde_json::from_value(value).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[0].compiler_cache, Some(cache));
+    }
+
+    #[test]
+    fn test_json_same_file_hops() {
+        let file_id = FileId {
+            dev: 2049,
+            ino: 131_075,
+            nlink: 2,
+        };
+        let mut chain = SymlinkChain::new(PathBuf::from("/usr/bin/vi"));
+        chain.add_link(
+            PathBuf::from("/usr/bin/vim"),
+            false,
+            LinkType::Wrapper(WrapperKind::Binary),
+        );
+        chain.add_link(
+            PathBuf::from("/usr/libexec/vim"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        chain.links[0].file_id = Some(file_id);
+        chain.links[1].file_id = Some(file_id);
+
+        let value = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert!(value["links"][0].get("same_file_as").is_none());
+        assert_eq!(value["links"][1]["same_file_as"], 0);
+        assert_eq!(value["links"][1]["file_id"]["ino"], 131_075);
+
+        let json_chain: JsonChain = serde_json::from_value(value).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[1].file_id, Some(file_id));
+    }
+
+    #[test]
+    fn test_json_mount_crossing() {
+        let root = MountInfo {
+            mount_id: 22,
+            mount_point: PathBuf::from("/"),
+            root: PathBuf::from("/"),
+            fs_type: "ext4".to_string(),
+            source: "/dev/nvme0n1p2".to_string(),
+            read_only: false,
+        };
+        let store = MountInfo {
+            mount_id: 30,
+            mount_point: PathBuf::from("/nix/store"),
+            root: PathBuf::from("/nix/store"),
+            read_only: true,
+            ..root.clone()
+        };
+        let mut chain = SymlinkChain::new(PathBuf::from("/run/current-system/sw/bin/ls"));
+        chain.origin_mount = Some(root);
+        chain.add_link(
+            PathBuf::from("/nix/store/abc-coreutils/bin/ls"),
+            true,
+            LinkType::Terminal(FileKind::Binary),
+        );
+        chain.links[0].mount = Some(store.clone());
+
+        let value = serde_json::to_value(JsonChain::from_chain(&chain)).unwrap();
+        assert_eq!(value["links"][0]["crosses_mount"], true);
+        assert_eq!(value["links"][0]["mount"]["bind"], true);
+        assert_eq!(value["links"][0]["mount"]["read_only"], true);
+
+        let json_chain: JsonChain = serde_json::from_value(value).unwrap();
+        let restored = SymlinkChain::try_from(json_chain).unwrap();
+        assert_eq!(restored.links[0].mount, Some(store));
+        assert_eq!(restored.origin_mount.unwrap().mount_id, 22);
+    }
 }
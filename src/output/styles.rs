@@ -2,6 +2,7 @@ pub struct TreeChars {
     pub branch: &'static str,
     pub last: &'static str,
     pub connector: &'static str,
+    pub vertical: &'static str,
 }
 
 impl Default for TreeChars {
@@ -10,6 +11,7 @@ impl Default for TreeChars {
             branch: "├",
             last: "└",
             connector: "─",
+            vertical: "│",
         }
     }
 }